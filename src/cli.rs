@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Arguments given to the shell itself when it is started
+#[derive(Parser, Debug)]
+#[command(name = "rush", version, about = "A modern shell")]
+pub struct CommandLineArgs {
+    #[arg(
+        short = 'c',
+        long = "command",
+        help = "Run the given commands and exit, instead of starting an interactive shell"
+    )]
+    pub command: Option<String>,
+    #[arg(
+        short = 'l',
+        long = "login",
+        help = "Act as a login shell, running the login profile on startup"
+    )]
+    pub login: bool,
+    #[arg(
+        help = "The path of a script to run, instead of starting an interactive shell (with '-c', the name that the commands run as, which they can read as '$0')"
+    )]
+    pub script: Option<PathBuf>,
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        requires = "script",
+        help = "Arguments for the script or '-c' commands, which they can read as '$1', '$2' and so on"
+    )]
+    pub arguments: Vec<String>,
+}
+
+impl CommandLineArgs {
+    /// Gets the name of the script being run ('$0'), if the shell was given one
+    /// With '-c', the first argument after the commands is used as the name instead, as in other shells
+    pub fn script_name(&self) -> Option<String> {
+        self.script
+            .as_ref()
+            .map(|script| script.to_string_lossy().into_owned())
+    }
+}
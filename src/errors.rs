@@ -1,10 +1,16 @@
 use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::ops::Range;
 use std::path::PathBuf;
 
+use nix::sys::signal::Signal;
+
 use crate::state::EnvVariable;
 
 /// `Result` alias which automatically uses `RushError` as the error type.
 pub type Result<T> = std::result::Result<T, RushError>;
+/// Range of byte offsets within a line of input, used to point out where a parse error occurred.
+pub type Span = Range<usize>;
 pub trait Handle<T> {
     /// Replaces any error kind with a new one, without overriding the default error message.
     /// Useful in situations where additional context provides no additional clarity.
@@ -55,6 +61,9 @@ impl<T> Handle<T> for std::option::Option<T> {
 pub struct RushError {
     kind: ErrorKind,
     custom_message: Option<String>,
+    /// Whether the error has already been written to the standard error of the command which
+    /// caused it, in which case it is only used for the command's exit status.
+    reported: bool,
 }
 
 impl Display for RushError {
@@ -74,6 +83,7 @@ impl RushError {
         Self {
             kind,
             custom_message: None,
+            reported: false,
         }
     }
 
@@ -82,6 +92,95 @@ impl RushError {
         self.custom_message = Some(context.to_owned());
         self
     }
+
+    /// Marks the error as having been written to the standard error of the command which caused
+    /// it, so that it is not reported a second time.
+    pub fn set_reported(mut self) -> Self {
+        self.reported = true;
+        self
+    }
+
+    /// Prints the error to the shell's standard error, unless it has already been reported.
+    pub fn report(&self) {
+        self.report_to(&mut std::io::stderr());
+    }
+
+    /// Writes the error to the standard error of a command, unless it has already been reported.
+    pub fn report_to(&self, stderr: &mut impl Write) {
+        if !self.reported {
+            let _ = writeln!(stderr, "{}", self);
+        }
+    }
+
+    /// Checks whether the error was caused by a builtin writing to an output that was closed,
+    /// which happens when the next command in a pipeline stops reading early.
+    pub fn is_output_closed(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::Builtin(BuiltinError::CouldNotWriteOutput)
+        )
+    }
+
+    /// Attaches the line of input in which a parse error was found, so that the error is displayed
+    /// along with the line and a caret under the text which caused it.
+    /// Errors of any other kind are left unchanged.
+    pub fn with_source(mut self, source: &str) -> Self {
+        if let ErrorKind::Parse(error) = &self.kind {
+            self.custom_message = Some(format!("{}\n{}", self, error.diagnostic(source)));
+        }
+
+        self
+    }
+
+    /// Checks whether the error was caused by a line of input which ended too early, and which
+    /// would be complete if more input was added (e.g. after an unclosed quote or a trailing '|').
+    pub fn is_incomplete_input(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::Parse(
+                ParseError::UnterminatedQuote(..)
+                    | ParseError::TrailingBackslash(_)
+                    | ParseError::MissingCommand(..)
+                    | ParseError::MissingKeyword(..)
+            )
+        )
+    }
+
+    /// Checks whether the error only reports that a command exited with a non-zero code, rather
+    /// than a problem with running the command.
+    pub fn is_failed_status(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::Executable(ExecutableError::FailedToExecute(_))
+        )
+    }
+
+    /// Gets the exit code reported for a command which failed with this error,
+    /// following the conventions used by other shells where they apply.
+    pub fn exit_code(&self) -> i32 {
+        use DispatchError::*;
+        use ExecutableError::*;
+        match &self.kind {
+            ErrorKind::Dispatch(UnknownCommand(_)) => 127,
+            ErrorKind::Dispatch(CommandNotExecutable(_) | UnreadableExecutableMetadata(_)) => 126,
+            // * Other shells use 2 for syntax errors, which are never caused by the command itself
+            ErrorKind::Parse(_) => 2,
+            ErrorKind::Executable(FailedToExecute(exit_code)) => *exit_code,
+            ErrorKind::Executable(KilledBySignal(signal)) => 128 + signal,
+            // * Jobs are usually stopped by SIGTSTP (Ctrl-Z)
+            ErrorKind::Executable(JobStopped(_)) => 128 + Signal::SIGTSTP as i32,
+            ErrorKind::Builtin(BuiltinError::Interrupted) => 130,
+            _ => 1,
+        }
+    }
+
+    /// Gets the signal which terminated the command that caused this error, if there was one.
+    pub fn signal(&self) -> Option<i32> {
+        match self.kind {
+            ErrorKind::Executable(ExecutableError::KilledBySignal(signal)) => Some(signal),
+            _ => None,
+        }
+    }
 }
 
 /// Enum representing every type of error which can occur in Rush.
@@ -89,6 +188,7 @@ impl RushError {
 /// about how the error occurred, such as the name of a command which was not found.
 #[derive(Debug)]
 pub enum ErrorKind {
+    Parse(ParseError),
     Dispatch(DispatchError),
     Builtin(BuiltinError),
     Executable(ExecutableError),
@@ -96,6 +196,171 @@ pub enum ErrorKind {
     Path(FileError),
 }
 
+/// Error type for errors which occur while a line of input is being tokenized or parsed.
+/// Every variant includes the span of the input where the error was found.
+#[derive(Debug)]
+pub enum ParseError {
+    /// OVERVIEW
+    /// This error occurs when a line of input contains an operator in a position where it is not
+    /// allowed.
+    ///
+    /// COMMON CAUSES
+    /// - An operator such as '|' or '&&' was placed at the start of a line.
+    /// - Two operators were placed next to each other (e.g. 'ls | | wc').
+    ///
+    /// SOLUTIONS
+    /// - Check that every operator is placed between two commands.
+    /// - Enclose the operator in quotes if it was meant to be passed as an argument.
+    ///
+    /// TECHNICAL DETAILS
+    /// Before a line of input is executed, the dispatcher parses it into a tree of commands,
+    /// pipelines and lists. If a token appears where the grammar does not allow it, the line cannot
+    /// be parsed and this error is returned.
+    UnexpectedToken(String, Span),
+
+    /// OVERVIEW
+    /// This error occurs when a line of input ends before a command is complete.
+    ///
+    /// CAUSE
+    /// - A here-document operator (e.g. '<<EOF') is missing its body.
+    ///
+    /// SOLUTION
+    /// - Add the missing text after the end of the line.
+    ///
+    /// TECHNICAL DETAILS
+    /// Before a line of input is executed, the dispatcher parses it into a tree of commands,
+    /// pipelines and lists. If the parser expects more input but reaches the end of the line,
+    /// this error is returned.
+    UnexpectedEndOfInput(Span),
+
+    /// OVERVIEW
+    /// This error occurs when an operator which joins two commands is not followed by a command.
+    ///
+    /// CAUSE
+    /// - The line ends with an operator such as '|', '&&' or '||'.
+    ///
+    /// SOLUTIONS
+    /// - Add the missing command after the operator.
+    /// - Remove the operator if only a single command was meant to be run.
+    ///
+    /// TECHNICAL DETAILS
+    /// Pipes and and-or operators always come between two commands. If the parser reaches the end
+    /// of the line while expecting the second command, this error is returned.
+    MissingCommand(String, Span),
+
+    /// OVERVIEW
+    /// This error occurs when a redirection operator is not followed by a file name.
+    ///
+    /// COMMON CAUSES
+    /// - The line ends with a redirection operator (e.g. 'ls >').
+    /// - A redirection operator was followed by another operator (e.g. 'ls > | wc').
+    ///
+    /// SOLUTION
+    /// - Add the name of the file, file descriptor or delimiter after the redirection operator.
+    ///
+    /// TECHNICAL DETAILS
+    /// Every redirection operator applies to the word directly after it, which is the file to
+    /// redirect to or from. If the next token is an operator, or there are no more tokens, this
+    /// error is returned.
+    MissingRedirectionTarget(String, Span),
+
+    /// OVERVIEW
+    /// This error occurs when a quoted section of a line of input is never closed.
+    ///
+    /// COMMON CAUSES
+    /// - The closing quote was forgotten.
+    /// - A quote was meant to be part of an argument (e.g. an apostrophe in "don't"), but was not
+    ///   escaped.
+    ///
+    /// SOLUTIONS
+    /// - Add the closing quote.
+    /// - Escape the quote using a backslash (e.g. 'don\'t'), or enclose it in the other kind of
+    ///   quotes.
+    ///
+    /// TECHNICAL DETAILS
+    /// Quotes group text into a single argument and control which expansions are performed on it.
+    /// If the end of the input is reached while still inside quotes, the tokenizer cannot tell
+    /// where the quoted text was meant to end, so this error is returned.
+    UnterminatedQuote(char, Span),
+
+    /// OVERVIEW
+    /// This error occurs when a line of input ends with a backslash.
+    ///
+    /// CAUSE
+    /// - A backslash was written at the end of the line without a character to escape.
+    ///
+    /// SOLUTIONS
+    /// - Remove the backslash.
+    /// - Escape the backslash itself using a second backslash (e.g. '\\').
+    ///
+    /// TECHNICAL DETAILS
+    /// A backslash escapes the character directly after it, so that it is treated as plain text.
+    /// If there is no character after the backslash, there is nothing for it to escape, and this
+    /// error is returned.
+    TrailingBackslash(Span),
+
+    /// OVERVIEW
+    /// This error occurs when a compound command, such as an 'if' or a loop, is missing one of the
+    /// reserved words which make it up.
+    ///
+    /// COMMON CAUSES
+    /// - The closing word (e.g. 'fi', 'done' or 'esac') was forgotten.
+    /// - The ';' or newline before a reserved word was left out (e.g. 'if true then'), so the word
+    ///   was treated as an argument instead.
+    ///
+    /// SOLUTIONS
+    /// - Add the missing reserved word.
+    /// - Put a ';' or newline before each reserved word which follows a command.
+    ///
+    /// TECHNICAL DETAILS
+    /// Reserved words are only recognized as the first word of a command, so a word such as 'then'
+    /// directly after a command's arguments is passed to that command. If the parser reaches the
+    /// end of the input without finding the reserved word it expects, this error is returned with
+    /// the span of the word which began the compound command.
+    MissingKeyword(String, String, Span),
+}
+
+impl ParseError {
+    /// Gets the span of the input where the error was found
+    fn span(&self) -> &Span {
+        use ParseError::*;
+        match self {
+            UnexpectedToken(_, span)
+            | UnexpectedEndOfInput(span)
+            | MissingCommand(_, span)
+            | MissingRedirectionTarget(_, span)
+            | UnterminatedQuote(_, span)
+            | TrailingBackslash(span)
+            | MissingKeyword(_, _, span) => span,
+        }
+    }
+
+    /// Reprints the line of the input containing the error, with carets under the error's span
+    fn diagnostic(&self, source: &str) -> String {
+        let span = self.span();
+        let Some(before) = source.get(..span.start) else {
+            return String::new();
+        };
+
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |index| span.start + index);
+        let underlined = source
+            .get(span.start..span.end.clamp(span.start, line_end))
+            .unwrap_or_default();
+
+        // * Tabs are kept in the padding so that the carets line up with the text above them
+        let padding: String = before[line_start..]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(underlined.chars().count().max(1));
+        let line = &source[line_start..line_end];
+        format!("  {}\n  {}{}", line, padding, carets)
+    }
+}
+
 /// Error type for errors which occur during command dispatch.
 #[derive(Debug)]
 pub enum DispatchError {
@@ -159,6 +424,155 @@ pub enum DispatchError {
     /// dispatcher then checks whether the file's permissions indicate that it is executable. If it
     /// cannot, this error is returned.
     UnreadableExecutableMetadata(PathBuf),
+
+    /// OVERVIEW
+    /// This error occurs when a line of input uses syntax which the shell can parse, but cannot
+    /// yet execute.
+    ///
+    /// CAUSE
+    /// - An and-or list (e.g. 'make && ./app') was run in the background.
+    ///
+    /// SOLUTION
+    /// - Run the commands separately, without the unsupported syntax.
+    /// - Run only a single pipeline in the background.
+    ///
+    /// TECHNICAL DETAILS
+    /// The parser recognizes the full command-line grammar, but some constructs have no execution
+    /// support yet. When the dispatcher encounters one of these constructs, this error is returned.
+    UnsupportedSyntax(String),
+
+    /// OVERVIEW
+    /// This error occurs when a redirection refers to a file descriptor which cannot be used.
+    ///
+    /// COMMON CAUSES
+    /// - A redirection refers to a file descriptor other than 0 (input), 1 (output) or 2 (errors).
+    /// - A duplication such as '2>&' is missing its file descriptor number.
+    /// - An input stream was redirected as an output stream or vice versa (e.g. '0>&1').
+    ///
+    /// SOLUTION
+    /// - Only redirect file descriptors 0, 1 and 2, and only duplicate outputs onto outputs.
+    ///
+    /// TECHNICAL DETAILS
+    /// Every command is given three standard streams: input (0), output (1) and errors (2).
+    /// Redirections can replace these streams with files or make them refer to each other. If a
+    /// redirection names any other file descriptor, or combines an input with an output, this
+    /// error is returned.
+    InvalidFileDescriptor(String),
+
+    /// OVERVIEW
+    /// This error occurs when a '${...}' expansion or a '$(...)' command substitution is malformed.
+    ///
+    /// COMMON CAUSES
+    /// - The closing brace, parenthesis or backtick of the expansion is missing.
+    /// - The variable name contains characters other than letters, digits and underscores.
+    /// - The expansion uses an operator which is not supported.
+    ///
+    /// SOLUTIONS
+    /// - Check that the expansion is written as '${NAME}', '${NAME:-default}', '${NAME:?error}'
+    ///   or '${#NAME}'.
+    /// - Quote the text using single quotes if it is not meant to be expanded.
+    ///
+    /// TECHNICAL DETAILS
+    /// Before a command is run, the dispatcher substitutes the values of any variables and the
+    /// output of any commands referenced in its arguments. If the text between the braces of an
+    /// expansion cannot be interpreted, or a substitution is never closed, this error is returned.
+    BadSubstitution(String),
+
+    /// OVERVIEW
+    /// This error occurs when a '${NAME:?error}' expansion refers to a variable with no value.
+    ///
+    /// CAUSE
+    /// - The variable has not been set, or has been set to an empty string.
+    ///
+    /// SOLUTION
+    /// - Set the variable using the 'set-variable' builtin before running the command.
+    ///
+    /// TECHNICAL DETAILS
+    /// The '${NAME:?error}' expansion is used to require that a variable is set before a command
+    /// is run. If the variable is unset or empty, the command is not run, and this error is
+    /// returned with the provided error message, if any.
+    UnsetVariable(String),
+
+    /// OVERVIEW
+    /// This error occurs when a glob pattern (e.g. '*.txt') does not match any files, and the shell
+    /// is configured to treat this as an error.
+    ///
+    /// COMMON CAUSES
+    /// - The pattern was misspelled, or refers to the wrong directory.
+    /// - The files it refers to are hidden (their names begin with a '.').
+    /// - The argument was meant to be passed to the command as-is, but was not quoted.
+    ///
+    /// SOLUTIONS
+    /// - Check that the files exist, using the 'list-directory' builtin.
+    /// - Quote the argument if it is not meant to be matched against file names.
+    /// - Use 'configure --glob-no-match pass' to pass unmatched patterns to commands unchanged.
+    ///
+    /// TECHNICAL DETAILS
+    /// Before a command is run, any of its arguments containing unquoted wildcards ('*', '?' or
+    /// '[...]') are replaced with the paths of the files they match. If an argument does not match
+    /// any files, the 'glob-no-match' setting determines whether it is kept, removed, or whether
+    /// this error is returned instead of running the command.
+    NoGlobMatches(String),
+
+    /// OVERVIEW
+    /// This error occurs when an arithmetic expression (e.g. '$(( 1 + 2 ))') cannot be evaluated.
+    ///
+    /// COMMON CAUSES
+    /// - The expression contains a typo, such as a missing operand or closing parenthesis.
+    /// - A number was divided by zero.
+    /// - A variable used in the expression does not contain a number.
+    ///
+    /// RARE CAUSES
+    /// - The result of an integer operation was too large to be stored (above 9223372036854775807).
+    /// - A bitwise operator (e.g. '&' or '<<') was used with a float.
+    ///
+    /// SOLUTIONS
+    /// - Check the expression for typos, and that every variable it uses contains a number.
+    /// - Use floats (e.g. '2.0') for calculations with very large numbers.
+    ///
+    /// TECHNICAL DETAILS
+    /// Arithmetic expressions are evaluated with 64-bit integers, unless a float is involved, in
+    /// which case 64-bit floats are used instead. The expression is parsed before it is evaluated,
+    /// and this error is returned with the reason if either step fails.
+    InvalidArithmetic(String),
+
+    /// OVERVIEW
+    /// This error occurs when functions or sourced scripts call themselves too many times.
+    ///
+    /// COMMON CAUSES
+    /// - A function calls itself without a condition which eventually stops it.
+    /// - A function has the same name as the command it runs (e.g. 'ls() { ls -a; }').
+    /// - A script sources itself, or two scripts source each other.
+    ///
+    /// SOLUTIONS
+    /// - Check that recursive functions stop calling themselves at some point.
+    /// - Call a builtin by its full name (e.g. 'list-directory' rather than 'ls') from a function
+    ///   which has the same name as one of the builtin's aliases.
+    ///
+    /// TECHNICAL DETAILS
+    /// Functions and sourced scripts run inside the shell, with each call nested inside the one
+    /// which made it. To stop the shell from running out of memory, this error is returned once
+    /// the calls are nested too deeply.
+    RecursionLimitReached(String),
+
+    /// OVERVIEW
+    /// This error occurs when a Rhai script fails to compile or fails while it is running.
+    ///
+    /// COMMON CAUSES
+    /// - The script has a syntax error, such as a missing semicolon or closing bracket.
+    /// - The script called a function which does not exist, or with the wrong types of arguments.
+    /// - A function provided by the shell failed, such as 'cd' with a directory that does not
+    ///   exist, and the script did not catch the error.
+    ///
+    /// SOLUTIONS
+    /// - Check the script at the line and position given in the error.
+    /// - Use 'try' and 'catch' in the script to handle errors from the shell's functions.
+    ///
+    /// TECHNICAL DETAILS
+    /// Rhai scripts are run by an embedded Rhai engine on a separate thread, which sends any
+    /// requests that need the shell's state back to the shell. This error is returned with the
+    /// message from the engine if the script does not finish successfully.
+    ScriptFailed(String),
 }
 
 /// Error type for errors that occur during the execution of builtin commands.
@@ -204,6 +618,71 @@ pub enum BuiltinError {
     /// the screen, moving the cursor around, or querying the terminal size. If for whatever reason
     /// it is unable to do so, this error is returned.
     TerminalOperationFailed,
+
+    /// OVERVIEW
+    /// This error occurs when a builtin is unable to write its output.
+    ///
+    /// COMMON CAUSES
+    /// - The builtin's output was piped into a command which exited before reading all of it.
+    ///
+    /// RARE CAUSES
+    /// - The stdout or stderr streams between the shell and the terminal have been corrupted.
+    ///
+    /// SOLUTIONS
+    /// - Ensure that the command receiving the builtin's output reads all of its input.
+    ///
+    /// TECHNICAL DETAILS
+    /// Builtins write their output to a stream provided by the shell, which may be the terminal or
+    /// a pipe to another command. If the stream has been closed, or is otherwise unwritable, this
+    /// error is returned.
+    CouldNotWriteOutput,
+
+    /// OVERVIEW
+    /// This error occurs when a builtin is interrupted by the user before it finishes.
+    ///
+    /// CAUSE
+    /// - Ctrl-C was pressed while the builtin was waiting (e.g. in 'wait').
+    ///
+    /// SOLUTION
+    /// - Run the builtin again, allowing it to finish.
+    ///
+    /// TECHNICAL DETAILS
+    /// Builtins run inside the shell, which is not terminated by SIGINT. Instead, builtins which
+    /// may wait for a long time check whether the shell has received SIGINT, and return this
+    /// error if it has.
+    Interrupted,
+
+    /// OVERVIEW
+    /// This error occurs when a builtin which controls a loop is used outside of one.
+    ///
+    /// CAUSE
+    /// - 'break' or 'continue' was run outside of a 'while', 'until' or 'for' loop.
+    ///
+    /// SOLUTION
+    /// - Only use the builtin within the body of a loop.
+    ///
+    /// TECHNICAL DETAILS
+    /// 'break' and 'continue' change which command the enclosing loop runs next. If no loop is
+    /// running, there is nothing for them to change, so this error is returned.
+    UsedOutsideOfLoop(String),
+
+    /// OVERVIEW
+    /// This error occurs when a builtin which only makes sense inside a function is used outside
+    /// of one.
+    ///
+    /// COMMON CAUSES
+    /// - 'local' was run outside of a function.
+    /// - 'return' was run outside of a function or sourced script.
+    ///
+    /// SOLUTIONS
+    /// - Use 'set-variable' instead of 'local' to set a variable outside of a function.
+    /// - Use 'exit' instead of 'return' to stop a script which is not sourced.
+    ///
+    /// TECHNICAL DETAILS
+    /// Local variables are restored when the function which set them returns, and 'return' stops
+    /// the function or sourced script which is running. Outside of them, there is nothing for
+    /// either builtin to apply to, so this error is returned.
+    UsedOutsideOfFunction(String),
 }
 
 /// Error type for errors which occur during execution of executable files.
@@ -214,11 +693,11 @@ pub enum ExecutableError {
     ///
     /// CAUSE
     /// - The executable file was deleted or moved after being located by the dispatcher, but before
-    /// being executed.
+    ///   being executed.
     ///
     /// SOLUTION
     /// - Ensure that the executable file is in a location that will not be modified without
-    /// explicit user action.
+    ///   explicit user action.
     ///
     /// TECHNICAL DETAILS
     /// When dispatching an executable, the dispatcher will first locate the executable file in one
@@ -249,10 +728,82 @@ pub enum ExecutableError {
     /// It is conventional for executables to return a zero exit code when they complete
     /// successfully, and a non-zero exit code when they fail. After running an executable, the
     /// disptacher will check its exit code, and if it is non-zero, this error is returned.
-    FailedToExecute(isize),
+    FailedToExecute(i32),
+
+    /// OVERVIEW
+    /// This error occurs when an executable is terminated by a signal before it exits.
+    ///
+    /// COMMON CAUSES
+    /// - The executable was interrupted by the user (e.g. with Ctrl-C).
+    /// - The executable was killed by another program (e.g. with 'kill').
+    ///
+    /// RARE CAUSES
+    /// - The executable crashed (e.g. with a segmentation fault).
+    /// - The executable was killed by the system because it ran out of memory.
+    ///
+    /// SOLUTIONS
+    /// - Run the executable again, allowing it to finish.
+    /// - Report the crash to the maintainers of the associated program.
+    ///
+    /// TECHNICAL DETAILS
+    /// A process which is terminated by a signal has no exit code. Instead, the command is given
+    /// the exit code 128 plus the number of the signal, as is conventional in other shells.
+    KilledBySignal(i32),
+
+    /// OVERVIEW
+    /// This error occurs when a command running in the foreground is stopped, and becomes a job.
+    ///
+    /// COMMON CAUSES
+    /// - Ctrl-Z was pressed while the command was running.
+    ///
+    /// RARE CAUSES
+    /// - The command was stopped by another program (e.g. with 'kill -STOP').
+    ///
+    /// SOLUTIONS
+    /// - Resume the job in the foreground with 'fg', or in the background with 'bg'.
+    ///
+    /// TECHNICAL DETAILS
+    /// When a process in the foreground pipeline is stopped, the shell stops waiting on it and
+    /// adds the pipeline to its job table, with the given job ID. The command is given the exit
+    /// code 148 (128 plus the number of SIGTSTP), as is conventional in other shells.
+    JobStopped(usize),
 
     /// This error is exceedingly rare and its cause is unknown. It is not expected to occur.
     CouldNotWait,
+
+    /// OVERVIEW
+    /// This error occurs when the shell is unable to connect a command's input or output.
+    ///
+    /// COMMON CAUSES
+    /// - The shell has reached the limit on the number of files it may have open at once.
+    ///
+    /// SOLUTIONS
+    /// - Close other programs which have many files open, or raise the open file limit.
+    /// - Use fewer commands in a single pipeline.
+    ///
+    /// TECHNICAL DETAILS
+    /// When commands are joined in a pipeline, the shell creates an OS pipe between each pair of
+    /// commands, and attaches the standard streams of each executable to those pipes or to its
+    /// own. If a pipe cannot be created or a stream cannot be duplicated, this error is returned.
+    CouldNotCreatePipe,
+
+    /// OVERVIEW
+    /// This error occurs when the shell is unable to start a command in the background.
+    ///
+    /// COMMON CAUSES
+    /// - The limit on the number of processes that the user may run has been reached.
+    ///
+    /// RARE CAUSES
+    /// - The system is out of memory.
+    ///
+    /// SOLUTIONS
+    /// - Wait for other jobs or programs to finish, or stop them, and try again.
+    ///
+    /// TECHNICAL DETAILS
+    /// A command followed by '&' is run in a copy of the shell, created with fork(), so that
+    /// builtins, functions and compound commands can run alongside the shell. If the copy cannot
+    /// be created, this error is returned and the command is not run.
+    CouldNotStartJob,
 }
 
 /// Error type for errors which occur during state operations.
@@ -282,7 +833,7 @@ pub enum StateError {
     /// COMMON CAUSES
     /// - The value provided to the environment variable was malformed due to an internal bug.
     /// - The value provided to the environment variable was malformed due to invalid input, such as
-    /// a non-existent directory being provided to CWD.
+    ///   a non-existent directory being provided to CWD.
     ///
     /// RARE CAUSES
     /// - The environment variable is not accessible to the shell.
@@ -338,11 +889,11 @@ pub enum StateError {
     ///
     /// CAUSE
     /// - A non-existent index was provided to the 'edit-path' builtin using the 'insert' or
-    /// 'delete' subcommand.
+    ///   'delete' subcommand.
     ///
     /// SOLUTION
     /// - Check the PATH variable using the 'environment' builtin and ensure that the index provided
-    /// is valid.
+    ///   is valid.
     ///
     /// TECHNICAL DETAILS
     /// The 'edit-path' builtin allows the user to modify the PATH variable. The PATH variable is
@@ -365,6 +916,151 @@ pub enum StateError {
     /// implemented using the Rustyline library, which should support most terminals. If the
     /// terminal being used does not support the requisite features, this error is returned.
     UnsupportedTerminal,
+
+    /// OVERVIEW
+    /// This error occurs when the shell is unable to set up its handling of terminal signals.
+    ///
+    /// CAUSE
+    /// - The operating system refused to install a signal handler.
+    ///
+    /// SOLUTION
+    /// - Run the shell in a different terminal, or outside of any program which restricts signals.
+    ///
+    /// TECHNICAL DETAILS
+    /// When running in a terminal, the shell installs handlers for SIGINT, SIGQUIT and SIGTSTP, so
+    /// that keypresses like Ctrl-C stop the running command instead of the shell itself. If a
+    /// handler cannot be installed, this error is returned and the shell does not start.
+    CouldNotHandleSignals,
+
+    /// OVERVIEW
+    /// This error occurs when a job specifier does not refer to any job in the job table.
+    ///
+    /// COMMON CAUSES
+    /// - The job number was mistyped.
+    /// - The job has already finished, and has been removed from the job table.
+    /// - No jobs are running, so there is no current job.
+    ///
+    /// SOLUTIONS
+    /// - Use the 'jobs' builtin to list the jobs which can be referred to.
+    ///
+    /// TECHNICAL DETAILS
+    /// Jobs are referred to by their number, optionally prefixed with '%' (e.g. '%1'), or with
+    /// '%+' for the current job and '%-' for the previous job. If no job matches, this error is
+    /// returned.
+    UnknownJob(String),
+
+    /// OVERVIEW
+    /// This error occurs when the shell is unable to continue a stopped job.
+    ///
+    /// CAUSE
+    /// - The processes of the job have already exited.
+    ///
+    /// SOLUTION
+    /// - Use the 'jobs' builtin to check whether the job is still running.
+    ///
+    /// TECHNICAL DETAILS
+    /// Jobs are continued by sending SIGCONT to their process group. If the signal cannot be sent,
+    /// this error is returned.
+    CouldNotResumeJob(usize),
+
+    /// OVERVIEW
+    /// This error occurs when the user attempts to set a variable with an invalid name.
+    ///
+    /// CAUSE
+    /// - The name provided to the 'set-variable' builtin is empty, starts with a digit, or
+    ///   contains characters other than letters, digits and underscores.
+    ///
+    /// SOLUTION
+    /// - Choose a name made up of only letters, digits and underscores, such as 'MY_VARIABLE'.
+    ///
+    /// TECHNICAL DETAILS
+    /// Variables are referenced in commands using '$NAME', where the name ends at the first
+    /// character which is not a letter, digit or underscore. Names which could not be referenced
+    /// in this way are rejected, and this error is returned.
+    InvalidVariableName(String),
+
+    /// OVERVIEW
+    /// This error occurs when the user attempts to remove or directly change a variable which is
+    /// managed by the shell.
+    ///
+    /// COMMON CAUSES
+    /// - The user attempted to unset USER, HOME, PWD or PATH.
+    /// - The user attempted to set PWD.
+    ///
+    /// SOLUTIONS
+    /// - Use the 'change-directory' builtin to change the working directory.
+    /// - Use the 'edit-path' builtin to remove directories from the PATH.
+    ///
+    /// TECHNICAL DETAILS
+    /// The shell keeps strongly-typed copies of these variables, which it relies on to function.
+    /// Because of this, they must always have a value, and the working directory can only be
+    /// changed by navigating to a different directory. If an operation would break these
+    /// guarantees, this error is returned.
+    ProtectedVariable(String),
+
+    /// OVERVIEW
+    /// This error occurs when the user attempts to define an alias with an invalid name.
+    ///
+    /// CAUSE
+    /// - The name given to the 'set-alias' builtin is empty, or contains whitespace, quotes, '='
+    ///   or characters which have a special meaning to the shell, such as '|' or '$'.
+    ///
+    /// SOLUTION
+    /// - Choose a name made up of letters, digits and punctuation like '-' or '.', such as 'll'.
+    ///
+    /// TECHNICAL DETAILS
+    /// Aliases are only expanded when their name appears unquoted as a single word at the start of
+    /// a command. A name which could never be read as such a word is rejected, and this error is
+    /// returned.
+    InvalidAliasName(String),
+
+    /// OVERVIEW
+    /// This error occurs when the user refers to an alias which has not been defined.
+    ///
+    /// COMMON CAUSES
+    /// - The alias name was misspelled.
+    /// - The alias was defined in a different session without being persisted.
+    ///
+    /// SOLUTIONS
+    /// - Use the 'set-alias' builtin without any arguments to list the aliases which are defined.
+    /// - Use 'set-alias --persist' to keep an alias for future sessions.
+    ///
+    /// TECHNICAL DETAILS
+    /// Aliases are looked up by their exact name. If no alias has the name given to the
+    /// 'set-alias' or 'unset-alias' builtin, this error is returned.
+    UnknownAlias(String),
+
+    /// OVERVIEW
+    /// This error occurs when the user attempts to define an abbreviation with an invalid name.
+    ///
+    /// CAUSE
+    /// - The name given to the 'abbreviation' builtin is empty or contains whitespace.
+    ///
+    /// SOLUTION
+    /// - Choose a single word as the name, such as 'gco'.
+    ///
+    /// TECHNICAL DETAILS
+    /// Abbreviations are expanded by the line editor when the word before the cursor matches
+    /// their name. Since words are separated by whitespace, a name containing whitespace could
+    /// never match, and this error is returned.
+    InvalidAbbreviationName(String),
+
+    /// OVERVIEW
+    /// This error occurs when the user refers to an abbreviation which has not been defined.
+    ///
+    /// COMMON CAUSES
+    /// - The abbreviation name was misspelled.
+    /// - The abbreviation was defined in a different session without being persisted.
+    ///
+    /// SOLUTIONS
+    /// - Use the 'abbreviation' builtin without any arguments to list the abbreviations which are
+    ///   defined.
+    /// - Use 'abbreviation --persist' to keep an abbreviation for future sessions.
+    ///
+    /// TECHNICAL DETAILS
+    /// Abbreviations are looked up by their exact name. If no abbreviation has the name given to
+    /// the 'abbreviation' builtin, this error is returned.
+    UnknownAbbreviation(String),
 }
 
 /// Error type for errors which occur during path operations.
@@ -434,7 +1130,7 @@ pub enum FileError {
     ///
     /// SOLUTIONS
     /// - Ensure that the file exists, is accessible by the user, and is not a directory any other
-    /// special file type.
+    ///   special file type.
     CouldNotOpenFile(PathBuf),
 
     /// OVERVIEW
@@ -450,7 +1146,7 @@ pub enum FileError {
     /// SOLUTIONS
     /// - If the file in question is a config file, make sure it is formatted correctly.
     /// - Ensure that the file is not open in or being modified by another program. This is usually
-    /// guaranteed by the OS/filesystem.
+    ///   guaranteed by the OS/filesystem.
     ///
     /// TECHNICAL DETAILS
     /// When the shell is reading a configuration file, it will attempt to parse its contents based
@@ -476,6 +1172,21 @@ pub enum FileError {
     /// - Ensure that the file's enclosing directory exists and is writable.
     CouldNotCreateFile(PathBuf),
 
+    /// OVERVIEW
+    /// This error occurs when a file cannot be written to.
+    ///
+    /// COMMON CAUSES
+    /// - The file is not writable by the current user.
+    /// - The file's enclosing directory does not exist.
+    ///
+    /// RARE CAUSES
+    /// - The disk is full.
+    /// - The filesystem is read-only.
+    ///
+    /// SOLUTIONS
+    /// - Ensure that the file and its enclosing directory exist and are writable.
+    CouldNotWriteFile(PathBuf),
+
     /// OVERVIEW
     /// This error occurs when a file cannot be deleted.
     ///
@@ -549,12 +1260,31 @@ pub enum FileError {
     /// SOLUTION
     /// - Ensure that the file or directory exists and is accessible by the user.
     UnknownPath(PathBuf),
+
+    /// OVERVIEW
+    /// This error occurs when a redirection would overwrite an existing file while overwriting
+    /// files is disabled.
+    ///
+    /// CAUSE
+    /// - The 'noclobber' setting is enabled and a '>' or '&>' redirection targets an existing file.
+    ///
+    /// SOLUTIONS
+    /// - Use the '>|' operator to overwrite the file anyway.
+    /// - Use the '>>' operator to add to the end of the file instead.
+    /// - Disable the 'noclobber' setting using the 'configure' builtin.
+    ///
+    /// TECHNICAL DETAILS
+    /// The 'noclobber' setting protects existing files from being accidentally replaced by output
+    /// redirections. Only regular files are protected, so special files such as '/dev/null' can
+    /// still be written to.
+    OverwriteDisallowed(PathBuf),
 }
 
 impl Display for ErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use ErrorKind::*;
         match self {
+            Parse(error) => write!(f, "{}", error),
             Dispatch(error) => write!(f, "{}", error),
             Builtin(error) => write!(f, "{}", error),
             Executable(error) => write!(f, "{}", error),
@@ -564,6 +1294,25 @@ impl Display for ErrorKind {
     }
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use ParseError::*;
+        match self {
+            UnexpectedToken(token, _) => write!(f, "Unexpected token '{}'", token),
+            UnexpectedEndOfInput(_) => write!(f, "Unexpected end of input"),
+            MissingCommand(operator, _) => write!(f, "Expected a command after '{}'", operator),
+            MissingRedirectionTarget(operator, _) => {
+                write!(f, "Expected a file name after '{}'", operator)
+            }
+            UnterminatedQuote(quote, _) => write!(f, "Unterminated quote ({})", quote),
+            TrailingBackslash(_) => write!(f, "Nothing to escape after backslash"),
+            MissingKeyword(keyword, opener, _) => {
+                write!(f, "Expected '{}' to complete the '{}'", keyword, opener)
+            }
+        }
+    }
+}
+
 impl Display for DispatchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use DispatchError::*;
@@ -585,6 +1334,18 @@ impl Display for DispatchError {
                     path.display()
                 )
             }
+            UnsupportedSyntax(syntax) => write!(f, "{} are not supported yet", syntax),
+            InvalidFileDescriptor(fd) => {
+                write!(f, "File descriptor '{}' cannot be redirected", fd)
+            }
+            BadSubstitution(expansion) => write!(f, "Bad substitution '{}'", expansion),
+            UnsetVariable(name) => write!(f, "Variable '{}' is unset or empty", name),
+            NoGlobMatches(pattern) => write!(f, "No files matched the pattern '{}'", pattern),
+            InvalidArithmetic(reason) => write!(f, "Invalid arithmetic expression: {}", reason),
+            RecursionLimitReached(name) => {
+                write!(f, "'{}' was called too many times within itself", name)
+            }
+            ScriptFailed(reason) => write!(f, "Rhai script failed: {}", reason),
         }
     }
 }
@@ -595,6 +1356,12 @@ impl Display for BuiltinError {
         match self {
             CouldNotParseArgs => write!(f, "Unable to parse the provided arguments"),
             TerminalOperationFailed => write!(f, "Terminal operation failed"),
+            CouldNotWriteOutput => write!(f, "Could not write output"),
+            Interrupted => write!(f, "Interrupted"),
+            UsedOutsideOfLoop(builtin) => write!(f, "'{}' can only be used inside a loop", builtin),
+            UsedOutsideOfFunction(builtin) => {
+                write!(f, "'{}' can only be used inside a function", builtin)
+            }
         }
     }
 }
@@ -609,7 +1376,19 @@ impl Display for ExecutableError {
             FailedToExecute(exit_code) => {
                 write!(f, "Executable failed with exit code {}", exit_code)
             }
+            KilledBySignal(signal) => {
+                write!(f, "Executable was terminated by signal {}", signal)
+            }
+            JobStopped(id) => {
+                write!(
+                    f,
+                    "Job [{}] was stopped, and can be resumed with 'fg' or 'bg'",
+                    id
+                )
+            }
             CouldNotWait => write!(f, "Failed to wait for executable to complete"),
+            CouldNotCreatePipe => write!(f, "Failed to connect the streams of a command"),
+            CouldNotStartJob => write!(f, "Failed to start the command in the background"),
         }
     }
 }
@@ -638,6 +1417,23 @@ impl Display for StateError {
                 write!(f, "Path index {} is invalid", index)
             }
             UnsupportedTerminal => write!(f, "Terminal is not supported"),
+            CouldNotHandleSignals => write!(f, "Could not set up handling of terminal signals"),
+            UnknownJob(specifier) => write!(f, "No job matching '{}'", specifier),
+            CouldNotResumeJob(id) => write!(f, "Could not resume job [{}]", id),
+            InvalidVariableName(name) => write!(f, "'{}' is not a valid variable name", name),
+            ProtectedVariable(name) => {
+                write!(
+                    f,
+                    "Variable '{}' is managed by the shell and cannot be changed this way",
+                    name
+                )
+            }
+            InvalidAliasName(name) => write!(f, "'{}' is not a valid alias name", name),
+            UnknownAlias(name) => write!(f, "No alias named '{}'", name),
+            InvalidAbbreviationName(name) => {
+                write!(f, "'{}' is not a valid abbreviation name", name)
+            }
+            UnknownAbbreviation(name) => write!(f, "No abbreviation named '{}'", name),
         }
     }
 }
@@ -672,6 +1468,9 @@ impl Display for FileError {
             CouldNotCreateFile(path) => {
                 write!(f, "Could not create file at path '{}'", path.display())
             }
+            CouldNotWriteFile(path) => {
+                write!(f, "Could not write to file at path '{}'", path.display())
+            }
             CouldNotDeleteFile(path) => {
                 write!(f, "Could not delete file at path '{}'", path.display())
             }
@@ -702,10 +1501,26 @@ impl Display for FileError {
                     path.display()
                 )
             }
+            OverwriteDisallowed(path) => {
+                write!(
+                    f,
+                    "Cannot overwrite existing file '{}' (use '>|' to override)",
+                    path.display()
+                )
+            }
         }
     }
 }
 
+/// Shortcut for creating a `RushError::Parse` without explicit imports
+macro_rules! parse_err {
+    ($variant:ident$(: $($content:expr),* $(,)?)?) => {{
+        crate::errors::RushError::new(crate::errors::ErrorKind::Parse(
+            crate::errors::ParseError::$variant$(($($content.clone().into()),*))?
+        ))
+    }};
+}
+
 /// Shortcut for creating a `RushError::Dispatch` without explicit imports
 macro_rules! dispatch_err {
     ($variant:ident) => {{
@@ -760,16 +1575,22 @@ macro_rules! file_err {
     }};
 }
 
-/// Shortcut for printing a `clap::Error` and returning a `BuiltinError::CouldNotParseArgs`
+/// Shortcut for handling a `clap::Error`, by printing the help message if it was requested,
+/// or returning a `BuiltinError::CouldNotParseArgs` with clap's explanation as its message
 macro_rules! clap_handle {
     ($expr:expr) => {
         match $expr {
             Ok(val) => val,
-            Err(e) => {
-                eprintln!("{}", e.render().ansi());    
-                match e.kind() {
-                    clap::error::ErrorKind::DisplayHelp => return crate::errors::Result::Ok(()),
-                    _ => return crate::errors::Result::Err(builtin_err!(CouldNotParseArgs)),
+            Err(e) => match e.kind() {
+                clap::error::ErrorKind::DisplayHelp => {
+                    eprintln!("{}", e.render().ansi());
+                    return crate::errors::Result::Ok(());
+                }
+                _ => {
+                    let message = e.render().ansi().to_string();
+                    return crate::errors::Result::Err(
+                        builtin_err!(CouldNotParseArgs).set_context(message.trim_end()),
+                    );
                 }
             },
         }
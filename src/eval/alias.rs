@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use super::parser::Assignment;
+use super::symbols::{
+    Symbols, BACKSLASH, BEGIN_GROUP, CASE, CONTROL_ESCAPES, DLESS, DLESSDASH, DO, DSEMI, ELIF,
+    ELSE, ESAC, IF, IN, NEWLINE, PIPELINE, RIGHT_PAREN, THEN, UNTIL, WHILE, WHITESPACE,
+};
+use super::tokenizer::{tokenize, HereDocument, Token, TokenizedInput, Word, WordPart};
+use crate::errors::{Result, Span};
+
+/// What the next word of the input is expected to be, which decides whether it can be an alias
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expecting {
+    /// The name of a command, or a reserved word or assignment before it
+    Command,
+    /// An argument, or anything else which is never expanded as an alias
+    Argument,
+    /// The word after 'case', which is followed by 'in'
+    CaseWord,
+    /// The 'in' after the word of a 'case' command
+    CaseIn,
+    /// The patterns of a case item, which end at a ')'
+    Pattern,
+}
+
+/// Replaces each alias at the start of a command with the tokens of the command line it stands for
+/// Like in other shells, an alias whose command line ends with a space also allows the word after
+/// it to be an alias (e.g. 'alias sudo="sudo "')
+// * Only words which are entirely unquoted are expanded, so '\ls' or "ls" always runs 'ls' itself
+pub fn expand_aliases(
+    input: TokenizedInput,
+    aliases: &HashMap<String, String>,
+) -> Result<TokenizedInput> {
+    let mut expander = AliasExpander::new(aliases, input.unclosed);
+    expander.expand(input.tokens, input.here_documents, Expecting::Command, None)?;
+    Ok(expander.output)
+}
+
+/// Finds the unquoted words at the start of each command in a line, where an alias or
+/// abbreviation would be expanded, along with the span of the line that each one was read from
+/// A line which cannot be tokenized has no command names
+pub fn command_names(line: &str) -> Vec<(String, Span)> {
+    let Ok(input) = tokenize(line) else {
+        return Vec::new();
+    };
+
+    let aliases = HashMap::new();
+    let mut expander = AliasExpander::new(&aliases, input.unclosed);
+    let _ = expander.expand(input.tokens, input.here_documents, Expecting::Command, None);
+    expander.command_names
+}
+
+/// State of the alias expansion of a line of input
+struct AliasExpander<'a> {
+    aliases: &'a HashMap<String, String>,
+    /// The aliases currently being expanded, innermost last
+    // * An alias is not expanded again within its own command line, which stops aliases like
+    // * 'alias ls="ls -a"' from expanding forever
+    expanding: Vec<&'a str>,
+    /// The unquoted words which were found at the start of a command without being an alias
+    command_names: Vec<(String, Span)>,
+    output: TokenizedInput,
+}
+
+impl<'a> AliasExpander<'a> {
+    fn new(aliases: &'a HashMap<String, String>, unclosed: usize) -> Self {
+        Self {
+            aliases,
+            expanding: Vec::new(),
+            command_names: Vec::new(),
+            output: TokenizedInput {
+                tokens: Vec::new(),
+                here_documents: Vec::new(),
+                unclosed,
+            },
+        }
+    }
+
+    /// Copies tokens to the output, expanding any aliases among them, and gives what the word after
+    /// them is expected to be
+    /// Tokens from an alias's command line are given the span of the alias's name
+    fn expand(
+        &mut self,
+        tokens: Vec<(Token, Span)>,
+        here_documents: Vec<HereDocument>,
+        mut expecting: Expecting,
+        alias_span: Option<&Span>,
+    ) -> Result<Expecting> {
+        // * Here-document bodies are kept in the same order as the operators which they belong to
+        let mut here_documents = here_documents.into_iter();
+        let mut redirection_target = false;
+
+        for (token, span) in tokens {
+            let span = alias_span.cloned().unwrap_or(span);
+            let word = match token {
+                Token::Operator(operator) => {
+                    let delimiter = operator.trim_start_matches(|c: char| c.is_ascii_digit());
+                    if delimiter == DLESS || delimiter == DLESSDASH {
+                        self.output.here_documents.extend(here_documents.next());
+                    }
+
+                    if Symbols::is_redirection(&operator) {
+                        redirection_target = true;
+                    } else {
+                        expecting = match (expecting, operator.as_str()) {
+                            // * Case patterns are separated by '|', and can come after newlines
+                            (Expecting::CaseIn | Expecting::Pattern, NEWLINE | PIPELINE) => {
+                                expecting
+                            }
+                            // * A ';;' ends a case item, so the patterns of the next item follow it
+                            (_, DSEMI) => Expecting::Pattern,
+                            _ => Expecting::Command,
+                        };
+                    }
+
+                    self.output.tokens.push((Token::Operator(operator), span));
+                    continue;
+                }
+                Token::Word(word) => word,
+            };
+
+            // * The target of a redirection does not change what the next word is expected to be
+            if redirection_target {
+                redirection_target = false;
+                self.output.tokens.push((Token::Word(word), span));
+                continue;
+            }
+
+            expecting = match expecting {
+                Expecting::Command => match self.alias(&word) {
+                    Some((name, command_line)) => self.expand_alias(name, command_line, &span)?,
+                    None => {
+                        if let Some(name) = word.as_unquoted() {
+                            self.command_names.push((name.to_owned(), span.clone()));
+                        }
+
+                        let expecting = after_command_word(&word);
+                        let word = remove_escape(word);
+                        self.output.tokens.push((Token::Word(word), span));
+                        expecting
+                    }
+                },
+                expecting => {
+                    let expecting = match expecting {
+                        Expecting::CaseWord => Expecting::CaseIn,
+                        Expecting::CaseIn if word.as_unquoted() == Some(IN) => Expecting::Pattern,
+                        Expecting::Pattern if word.as_unquoted() == Some(ESAC) => {
+                            Expecting::Argument
+                        }
+                        Expecting::Pattern if ends_pattern(&word) => Expecting::Command,
+                        expecting @ Expecting::Pattern => expecting,
+                        _ => Expecting::Argument,
+                    };
+
+                    self.output.tokens.push((Token::Word(word), span));
+                    expecting
+                }
+            };
+        }
+
+        Ok(expecting)
+    }
+
+    /// Gets the name and command line of the alias that a word refers to, if any
+    fn alias(&self, word: &Word) -> Option<(&'a str, &'a str)> {
+        let (name, command_line) = self.aliases.get_key_value(word.as_unquoted()?)?;
+        match self.expanding.contains(&name.as_str()) {
+            true => None,
+            false => Some((name, command_line)),
+        }
+    }
+
+    /// Replaces an alias with the tokens of its command line, giving what the next word is
+    /// expected to be
+    fn expand_alias(
+        &mut self,
+        name: &'a str,
+        command_line: &str,
+        span: &Span,
+    ) -> Result<Expecting> {
+        let input = tokenize(command_line)?;
+        self.output.unclosed += input.unclosed;
+
+        self.expanding.push(name);
+        let expecting = self.expand(
+            input.tokens,
+            input.here_documents,
+            Expecting::Command,
+            Some(span),
+        );
+        self.expanding.pop();
+
+        match command_line.ends_with(WHITESPACE) {
+            true => expecting.map(|_| Expecting::Command),
+            false => expecting,
+        }
+    }
+}
+
+/// Gets what is expected after a word at the start of a command which is not an alias
+// * Reserved words which begin or continue a compound command are followed by another command
+fn after_command_word(word: &Word) -> Expecting {
+    match word.as_unquoted() {
+        Some(IF | THEN | ELIF | ELSE | WHILE | UNTIL | DO | BEGIN_GROUP) => Expecting::Command,
+        Some(CASE) => Expecting::CaseWord,
+        _ if Assignment::from_word(word).is_some() => Expecting::Command,
+        _ => Expecting::Argument,
+    }
+}
+
+/// Checks whether a word ends the patterns of a case item (e.g. 'a)'), since the first command of
+/// the item follows it
+fn ends_pattern(word: &Word) -> bool {
+    matches!(word.parts.last(), Some(WordPart::Unquoted(text)) if text.ends_with(RIGHT_PAREN))
+}
+
+/// Removes the backslash from a command name like '\ls', which runs the command without expanding
+/// it as an alias
+// * The tokenizer keeps a backslash before most characters, but turns one before a letter like
+// * 'e' into a control character, so the first character of the name is recovered from either
+// * The escaped character stays quoted, so that a word like '\if' is not read as a reserved word
+fn remove_escape(mut word: Word) -> Word {
+    let (escaped, rest) = match word.parts.first() {
+        Some(WordPart::Unquoted(text)) => {
+            let mut characters = text.chars();
+            let (Some(BACKSLASH), Some(escaped)) = (characters.next(), characters.next()) else {
+                return word;
+            };
+            (escaped, WordPart::Unquoted(characters.as_str().to_owned()))
+        }
+        Some(WordPart::Literal(text)) => {
+            let mut characters = text.chars();
+            let Some(&(letter, _)) = characters.next().and_then(|first| {
+                CONTROL_ESCAPES
+                    .iter()
+                    .find(|(_, control)| *control == first)
+            }) else {
+                return word;
+            };
+            (letter, WordPart::Literal(characters.as_str().to_owned()))
+        }
+        _ => return word,
+    };
+
+    let mut parts = vec![WordPart::Literal(escaped.to_string())];
+    if !rest.text().is_empty() {
+        parts.push(rest);
+    }
+
+    word.parts.splice(0..1, parts);
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expands the aliases in a line, giving the text of each resulting token
+    fn expand(line: &str, aliases: &[(&str, &str)]) -> Vec<String> {
+        let aliases: HashMap<String, String> = aliases
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        expand_aliases(tokenize(line).unwrap(), &aliases)
+            .unwrap()
+            .tokens
+            .iter()
+            .map(|(token, _)| token.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn aliases_expand_in_command_position() {
+        let aliases = [("ll", "ls -l")];
+        assert_eq!(expand("ll dir", &aliases), ["ls", "-l", "dir"]);
+        assert_eq!(expand("echo ll", &aliases), ["echo", "ll"]);
+        assert_eq!(
+            expand("true && ll | ll; ll", &aliases),
+            ["true", "&&", "ls", "-l", "|", "ls", "-l", ";", "ls", "-l"]
+        );
+        assert_eq!(
+            expand("if ll; then X=1 ll; fi", &aliases),
+            ["if", "ls", "-l", ";", "then", "X=1", "ls", "-l", ";", "fi"]
+        );
+    }
+
+    #[test]
+    fn quoted_or_escaped_names_are_not_expanded() {
+        let aliases = [("ls", "ls -a")];
+        assert_eq!(expand("\\ls", &aliases), ["ls"]);
+        assert_eq!(expand("'ls'", &aliases), ["ls"]);
+        assert_eq!(expand("ls", &aliases), ["ls", "-a"]);
+    }
+
+    #[test]
+    fn aliases_do_not_expand_within_themselves() {
+        let aliases = [("a", "b x"), ("b", "a y")];
+        assert_eq!(expand("a", &aliases), ["a", "y", "x"]);
+    }
+
+    #[test]
+    fn trailing_space_allows_next_word_to_expand() {
+        let aliases = [("sudo", "sudo "), ("ll", "ls -l"), ("run", "env")];
+        assert_eq!(expand("sudo ll", &aliases), ["sudo", "ls", "-l"]);
+        assert_eq!(expand("run ll", &aliases), ["env", "ll"]);
+    }
+
+    #[test]
+    fn aliases_can_contain_operators_and_comments() {
+        let aliases = [("both", "echo a; echo b # note")];
+        assert_eq!(
+            expand("both && echo c", &aliases),
+            ["echo", "a", ";", "echo", "b", "&&", "echo", "c"]
+        );
+    }
+
+    #[test]
+    fn case_patterns_are_not_expanded() {
+        let aliases = [("x", "echo")];
+        assert_eq!(
+            expand("case x in x) x;; esac", &aliases),
+            ["case", "x", "in", "x)", "echo", ";;", "esac"]
+        );
+    }
+
+    #[test]
+    fn command_names_are_unquoted_words_in_command_position() {
+        let line = "gc -m x && 'gc'; echo gc | \\gc; X=1 gs";
+        let names = command_names(line);
+        assert!(names.iter().all(|(name, span)| line[span.clone()] == *name));
+        assert_eq!(
+            names.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["gc", "echo", "\\gc", "X=1", "gs"]
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::errors::{Result, RushError};
+
+/// Operators which can appear in arithmetic expressions
+// * Operators are listed longest-first so that the tokenizer can use the first match
+const OPERATORS: [&str; 23] = [
+    "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "&",
+    "|", "^", "!", "~", "?", ":",
+];
+
+/// The result of an arithmetic expression, which is an integer unless a float was involved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_float(self) -> f64 {
+        match self {
+            Self::Integer(integer) => integer as f64,
+            Self::Float(float) => float,
+        }
+    }
+
+    fn is_true(self) -> bool {
+        self.as_float() != 0.0
+    }
+
+    fn from_bool(value: bool) -> Self {
+        Self::Integer(value as i64)
+    }
+
+    /// Formats the number, rounding floats to the given number of decimal places if one is provided
+    pub fn format(&self, precision: Option<usize>) -> String {
+        match (self, precision) {
+            (Self::Float(float), Some(precision)) => format!("{:.*}", precision, float),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(integer) => write!(f, "{}", integer),
+            Self::Float(float) => write!(f, "{}", float),
+        }
+    }
+}
+
+/// A single token of an arithmetic expression
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Name(String),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Name(name) => write!(f, "{}", name),
+            Self::Operator(operator) => write!(f, "{}", operator),
+            Self::LeftParen => write!(f, "("),
+            Self::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+/// A parsed arithmetic expression
+enum Expression {
+    Number(Number),
+    Variable(String),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    /// 'condition ? then : otherwise'
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+}
+
+/// Evaluates an arithmetic expression, such as '2 * (x + 1.5)'
+/// Variables are looked up by name, where a variable which is unset or empty counts as zero
+/// Integer division is truncated (e.g. '7 / 2' is 3), unless either side is a float (e.g. '7 / 2.0')
+pub fn evaluate(expression: &str, variables: impl Fn(&str) -> Option<String>) -> Result<Number> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+
+    let parsed = parser.parse_expression(0)?;
+    if let Some(token) = parser.tokens.next() {
+        return Err(arithmetic_error(&format!("unexpected '{}'", token)));
+    }
+
+    Evaluator {
+        variables: &variables,
+    }
+    .evaluate(&parsed)
+}
+
+/// Creates the error for an expression which could not be evaluated, with the reason why
+fn arithmetic_error(reason: &str) -> RushError {
+    dispatch_err!(InvalidArithmetic: reason)
+}
+
+/// Separates an arithmetic expression into tokens
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut characters = expression.chars().peekable();
+
+    while let Some(&character) = characters.peek() {
+        match character {
+            _ if character.is_whitespace() => {
+                characters.next();
+            }
+            '0'..='9' | '.' => tokens.push(Token::Number(read_number(&mut characters)?)),
+            _ if character.is_ascii_alphabetic() || character == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = characters.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+
+                    name.push(c);
+                    characters.next();
+                }
+
+                tokens.push(Token::Name(name));
+            }
+            '(' => {
+                characters.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                characters.next();
+                tokens.push(Token::RightParen);
+            }
+            _ => {
+                let operator = OPERATORS
+                    .iter()
+                    .find(|operator| {
+                        let mut lookahead = characters.clone();
+                        operator.chars().all(|c| lookahead.next() == Some(c))
+                    })
+                    .ok_or_else(|| arithmetic_error(&format!("unexpected '{}'", character)))?;
+
+                for _ in 0..operator.len() {
+                    characters.next();
+                }
+
+                tokens.push(Token::Operator(operator));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a number, which is a float if it contains a decimal point or an exponent (e.g. '1.5e3')
+fn read_number(characters: &mut Peekable<Chars>) -> Result<Number> {
+    let mut text = String::new();
+    while let Some(&c) = characters.peek() {
+        let is_exponent_sign = (c == '+' || c == '-') && text.ends_with(['e', 'E']);
+        if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exponent_sign) {
+            break;
+        }
+
+        text.push(c);
+        characters.next();
+    }
+
+    parse_number(&text)
+}
+
+/// Parses the text of a number, such as '42' or '0.5'
+fn parse_number(text: &str) -> Result<Number> {
+    let invalid = || arithmetic_error(&format!("'{}' is not a number", text));
+    if text.contains(['.', 'e', 'E']) {
+        text.parse::<f64>()
+            .map(Number::Float)
+            .map_err(|_| invalid())
+    } else {
+        text.parse::<i64>()
+            .map(Number::Integer)
+            .map_err(|_| invalid())
+    }
+}
+
+/// Gets the precedence of a binary operator, where operators with a higher precedence are applied first
+/// Gives `None` if the operator cannot be used between two values
+fn binary_precedence(operator: &str) -> Option<u8> {
+    Some(match operator {
+        "?" => 1,
+        "||" => 2,
+        "&&" => 3,
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "==" | "!=" => 7,
+        "<" | "<=" | ">" | ">=" => 8,
+        "<<" | ">>" => 9,
+        "+" | "-" => 10,
+        "*" | "/" | "%" => 11,
+        "**" => 12,
+        _ => return None,
+    })
+}
+
+/// Parses tokens into an `Expression` using precedence climbing
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    /// Parses an expression made up of operators with at least the given precedence
+    fn parse_expression(&mut self, minimum_precedence: u8) -> Result<Expression> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(Token::Operator(operator)) = self.tokens.peek().cloned() {
+            let Some(precedence) = binary_precedence(operator) else {
+                break;
+            };
+
+            if precedence < minimum_precedence {
+                break;
+            }
+
+            self.tokens.next();
+            left = match operator {
+                // * The conditional operator and '**' group from the right, so 'a ** b ** c' is 'a ** (b ** c)'
+                "?" => {
+                    let then = self.parse_expression(0)?;
+                    self.expect(&Token::Operator(":"))?;
+                    let otherwise = self.parse_expression(precedence)?;
+                    Expression::Conditional(Box::new(left), Box::new(then), Box::new(otherwise))
+                }
+                "**" => {
+                    let right = self.parse_expression(precedence)?;
+                    Expression::Binary(operator, Box::new(left), Box::new(right))
+                }
+                _ => {
+                    let right = self.parse_expression(precedence + 1)?;
+                    Expression::Binary(operator, Box::new(left), Box::new(right))
+                }
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parses a single value, along with any unary operators before it
+    fn parse_unary(&mut self) -> Result<Expression> {
+        match self.tokens.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(number)),
+            Some(Token::Name(name)) => Ok(Expression::Variable(name)),
+            Some(Token::Operator(operator @ ("+" | "-" | "!" | "~"))) => {
+                Ok(Expression::Unary(operator, Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LeftParen) => {
+                let expression = self.parse_expression(0)?;
+                self.expect(&Token::RightParen)?;
+                Ok(expression)
+            }
+            Some(token) => Err(arithmetic_error(&format!("unexpected '{}'", token))),
+            None => Err(arithmetic_error("unexpected end of expression")),
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.tokens.next() {
+            Some(token) if token == *expected => Ok(()),
+            Some(token) => Err(arithmetic_error(&format!(
+                "expected '{}' but found '{}'",
+                expected, token
+            ))),
+            None => Err(arithmetic_error(&format!("expected '{}'", expected))),
+        }
+    }
+}
+
+/// Computes the value of an `Expression`
+struct Evaluator<'a> {
+    variables: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(&self, expression: &Expression) -> Result<Number> {
+        match expression {
+            Expression::Number(number) => Ok(*number),
+            Expression::Variable(name) => {
+                let value = (self.variables)(name).unwrap_or_default();
+                match value.trim() {
+                    "" => Ok(Number::Integer(0)),
+                    value => parse_number(value),
+                }
+            }
+            Expression::Unary(operator, operand) => {
+                let operand = self.evaluate(operand)?;
+                match (*operator, operand) {
+                    ("+", _) => Ok(operand),
+                    ("-", Number::Integer(integer)) => integer
+                        .checked_neg()
+                        .map(Number::Integer)
+                        .ok_or_else(|| arithmetic_error("integer overflow")),
+                    ("-", Number::Float(float)) => Ok(Number::Float(-float)),
+                    ("!", _) => Ok(Number::from_bool(!operand.is_true())),
+                    (_, Number::Integer(integer)) => Ok(Number::Integer(!integer)),
+                    (_, Number::Float(_)) => Err(arithmetic_error("'~' only works on integers")),
+                }
+            }
+            // * Only the side of a logical or conditional operator which is needed is evaluated,
+            // * so that expressions like 'x != 0 && 10 / x' do not fail
+            Expression::Binary("&&", left, right) => Ok(Number::from_bool(
+                self.evaluate(left)?.is_true() && self.evaluate(right)?.is_true(),
+            )),
+            Expression::Binary("||", left, right) => Ok(Number::from_bool(
+                self.evaluate(left)?.is_true() || self.evaluate(right)?.is_true(),
+            )),
+            Expression::Conditional(condition, then, otherwise) => {
+                match self.evaluate(condition)?.is_true() {
+                    true => self.evaluate(then),
+                    false => self.evaluate(otherwise),
+                }
+            }
+            Expression::Binary(operator, left, right) => {
+                apply_binary(operator, self.evaluate(left)?, self.evaluate(right)?)
+            }
+        }
+    }
+}
+
+/// Applies a binary operator to two numbers
+/// The result is an integer if both numbers are integers, except for negative powers (e.g. '2 ** -1')
+fn apply_binary(operator: &str, left: Number, right: Number) -> Result<Number> {
+    let comparison = |result: bool| Ok(Number::from_bool(result));
+    let (x, y) = (left.as_float(), right.as_float());
+
+    match operator {
+        "==" => return comparison(x == y),
+        "!=" => return comparison(x != y),
+        "<" => return comparison(x < y),
+        "<=" => return comparison(x <= y),
+        ">" => return comparison(x > y),
+        ">=" => return comparison(x >= y),
+        _ => (),
+    }
+
+    let (Number::Integer(a), Number::Integer(b)) = (left, right) else {
+        return match operator {
+            "+" => Ok(Number::Float(x + y)),
+            "-" => Ok(Number::Float(x - y)),
+            "*" => Ok(Number::Float(x * y)),
+            "/" if y == 0.0 => Err(arithmetic_error("division by zero")),
+            "/" => Ok(Number::Float(x / y)),
+            "%" if y == 0.0 => Err(arithmetic_error("division by zero")),
+            "%" => Ok(Number::Float(x % y)),
+            "**" => Ok(Number::Float(x.powf(y))),
+            _ => Err(arithmetic_error(&format!(
+                "'{}' only works on integers",
+                operator
+            ))),
+        };
+    };
+
+    let overflow = || arithmetic_error("integer overflow");
+    let result = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" | "%" if b == 0 => return Err(arithmetic_error("division by zero")),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        "**" if b < 0 => return Ok(Number::Float(x.powf(y))),
+        "**" => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+        "<<" => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+        ">>" => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+        "&" => Some(a & b),
+        "|" => Some(a | b),
+        "^" => Some(a ^ b),
+        _ => return Err(arithmetic_error(&format!("unexpected '{}'", operator))),
+    };
+
+    result.map(Number::Integer).ok_or_else(overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates an expression where 'x' is 5, 'f' is 2.5, 'e' is empty and nothing else is set
+    fn eval(expression: &str) -> Result<Number> {
+        evaluate(expression, |name| match name {
+            "x" => Some("5".to_owned()),
+            "f" => Some("2.5".to_owned()),
+            "e" => Some(String::new()),
+            _ => None,
+        })
+    }
+
+    fn integer(expression: &str) -> i64 {
+        match eval(expression).unwrap() {
+            Number::Integer(integer) => integer,
+            Number::Float(float) => panic!("{:?} gave float {}", expression, float),
+        }
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!(integer("1 + 2 * 3"), 7);
+        assert_eq!(integer("(1 + 2) * 3"), 9);
+        assert_eq!(integer("2 ** 3 ** 2"), 512);
+        assert_eq!(integer("-2 ** 2"), 4);
+        assert_eq!(integer("1 << 2 + 1"), 8);
+        assert_eq!(integer("1 + 1 == 2 && 3 > 2"), 1);
+        assert_eq!(integer("0 || 0 ? 1 : 2"), 2);
+        assert_eq!(integer("10 - 4 - 3"), 3);
+    }
+
+    #[test]
+    fn integer_division_truncates() {
+        assert_eq!(integer("7 / 2"), 3);
+        assert_eq!(integer("-7 / 2"), -3);
+        assert_eq!(integer("7 % 3"), 1);
+    }
+
+    #[test]
+    fn floats_spread_through_expression() {
+        assert_eq!(eval("7 / 2.0").unwrap(), Number::Float(3.5));
+        assert_eq!(eval("f * 2").unwrap(), Number::Float(5.0));
+        assert_eq!(eval("1 / 3.0").unwrap().format(Some(2)), "0.33");
+        assert_eq!(eval("7").unwrap().format(Some(2)), "7");
+    }
+
+    #[test]
+    fn variables_are_looked_up_by_name() {
+        assert_eq!(integer("x * 2"), 10);
+        assert_eq!(integer("unset + e + 1"), 1);
+    }
+
+    #[test]
+    fn invalid_expressions_are_errors() {
+        for expression in [
+            "1 / 0",
+            "1 % 0",
+            "1 +",
+            "(1",
+            "1 2",
+            "~1.5",
+            "9223372036854775807 + 1",
+        ] {
+            assert!(eval(expression).is_err(), "{:?}", expression);
+        }
+
+        assert!(eval("1 / 0")
+            .unwrap_err()
+            .to_string()
+            .contains("division by zero"));
+    }
+}
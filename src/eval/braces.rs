@@ -0,0 +1,284 @@
+use super::symbols::{DOLLAR, LEFT_BRACE, LEFT_PAREN, RIGHT_BRACE, RIGHT_PAREN};
+use super::tokenizer::{Word, WordPart};
+
+const COMMA: char = ',';
+const RANGE: &str = "..";
+
+/// Expands the braces in a word into separate words, such as 'file.{rs,txt}' into 'file.rs' and 'file.txt'
+/// Supports lists like '{a,b}' and sequences like '{1..10}', '{a..e}' and '{01..10..2}'
+/// Braces which are quoted, or which do not contain a list or a sequence, are kept as they are
+pub fn expand_braces(word: &Word) -> Vec<Word> {
+    // * The word is split into single characters, each quoted the same way as the part it came from
+    // * Empty parts (e.g. from "") are kept, so that a word made of only empty quotes is not lost
+    let characters: Vec<WordPart> = word
+        .parts
+        .iter()
+        .flat_map(|part| match part.text().is_empty() {
+            true => vec![part.clone()],
+            false => part
+                .text()
+                .chars()
+                .map(|character| part.with_text(character.to_string()))
+                .collect(),
+        })
+        .collect();
+
+    expand(&characters)
+        .into_iter()
+        .map(|characters| {
+            let mut word = Word::default();
+            for character in characters {
+                word.push(character);
+            }
+
+            word
+        })
+        .collect()
+}
+
+/// Gets the character of a single-character part, if it is unquoted
+fn unquoted(part: &WordPart) -> Option<char> {
+    match part {
+        WordPart::Unquoted(text) => text.chars().next(),
+        _ => None,
+    }
+}
+
+/// Expands the first valid brace expression in a sequence of characters, along with any after it
+fn expand(characters: &[WordPart]) -> Vec<Vec<WordPart>> {
+    let mut index = 0;
+    while index < characters.len() {
+        if let Some(end) = skip_expansion(characters, index) {
+            index = end + 1;
+            continue;
+        }
+
+        if unquoted(&characters[index]) == Some(LEFT_BRACE) {
+            let expanded = find_closing(characters, index).and_then(|end| {
+                let alternatives = alternatives(&characters[index + 1..end])?;
+                Some((alternatives, end))
+            });
+
+            if let Some((alternatives, end)) = expanded {
+                let prefix = &characters[..index];
+                let suffixes = expand(&characters[end + 1..]);
+                return alternatives
+                    .iter()
+                    .flat_map(|alternative| {
+                        suffixes
+                            .iter()
+                            .map(move |suffix| [prefix, alternative, suffix].concat())
+                    })
+                    .collect();
+            }
+        }
+
+        index += 1;
+    }
+
+    vec![characters.to_vec()]
+}
+
+/// Finds the end of a '${...}' or '$(...)' expansion starting at the given index, if there is one
+// * Braces inside of these expansions belong to them, so they are never brace expanded
+fn skip_expansion(characters: &[WordPart], index: usize) -> Option<usize> {
+    if unquoted(characters.get(index)?) != Some(DOLLAR) {
+        return None;
+    }
+
+    match unquoted(characters.get(index + 1)?) {
+        Some(LEFT_BRACE | LEFT_PAREN) => {
+            Some(find_closing(characters, index + 1).unwrap_or(characters.len()))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the unquoted brace or parenthesis which closes the one at the given index
+fn find_closing(characters: &[WordPart], open: usize) -> Option<usize> {
+    let (opening, closing) = match unquoted(characters.get(open)?)? {
+        LEFT_PAREN => (LEFT_PAREN, RIGHT_PAREN),
+        _ => (LEFT_BRACE, RIGHT_BRACE),
+    };
+
+    let mut depth = 0;
+    for (index, part) in characters.iter().enumerate().skip(open) {
+        match unquoted(part) {
+            Some(character) if character == opening => depth += 1,
+            Some(character) if character == closing => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Gives the alternatives of a brace expression, given the characters between its braces
+/// Gives `None` if the braces contain neither a list nor a sequence (e.g. '{}' or '{a}')
+fn alternatives(body: &[WordPart]) -> Option<Vec<Vec<WordPart>>> {
+    let mut items = Vec::new();
+    let (mut start, mut depth) = (0, 0);
+
+    let mut index = 0;
+    while index < body.len() {
+        if let Some(end) = skip_expansion(body, index) {
+            index = end + 1;
+            continue;
+        }
+
+        match unquoted(&body[index]) {
+            Some(LEFT_BRACE) => depth += 1,
+            Some(RIGHT_BRACE) => depth -= 1,
+            Some(COMMA) if depth == 0 => {
+                items.push(&body[start..index]);
+                start = index + 1;
+            }
+            _ => (),
+        }
+
+        index += 1;
+    }
+
+    if items.is_empty() {
+        return sequence(body);
+    }
+
+    // * Each item of a list can contain braces of its own, such as 'a{b,c},d'
+    items.push(&body[start..]);
+    Some(items.into_iter().flat_map(expand).collect())
+}
+
+/// Gives the items of a sequence expression such as '1..10', 'a..e' or '01..10..2'
+fn sequence(body: &[WordPart]) -> Option<Vec<Vec<WordPart>>> {
+    let text = body.iter().map(unquoted).collect::<Option<String>>()?;
+    let (start, end, step) = match text.split(RANGE).collect::<Vec<&str>>().as_slice() {
+        [start, end] => (*start, *end, 1),
+        // * The direction of the sequence only depends on its bounds, so the sign of the step is ignored
+        [start, end, step] => (
+            *start,
+            *end,
+            step.parse::<i64>().ok()?.unsigned_abs().max(1),
+        ),
+        _ => return None,
+    };
+
+    let step = usize::try_from(step).ok()?;
+    let items =
+        numeric_sequence(start, end, step).or_else(|| character_sequence(start, end, step))?;
+    Some(
+        items
+            .into_iter()
+            .map(|item| vec![WordPart::Unquoted(item)])
+            .collect(),
+    )
+}
+
+/// Gives the numbers between two bounds, such as '1..5' or '10..0'
+/// If either bound has a leading zero (e.g. '01'), every number is padded to the same width
+fn numeric_sequence(start: &str, end: &str, step: usize) -> Option<Vec<String>> {
+    let (first, last) = (start.parse::<i64>().ok()?, end.parse::<i64>().ok()?);
+
+    let is_padded = |bound: &str| {
+        let digits = bound.trim_start_matches('-');
+        digits.len() > 1 && digits.starts_with('0')
+    };
+    let width = match is_padded(start) || is_padded(end) {
+        true => start.len().max(end.len()),
+        false => 0,
+    };
+
+    Some(
+        stepped(first, last, step)
+            .into_iter()
+            .map(|number| format!("{:0width$}", number, width = width))
+            .collect(),
+    )
+}
+
+/// Gives the characters between two single-character bounds, such as 'a..e'
+fn character_sequence(start: &str, end: &str, step: usize) -> Option<Vec<String>> {
+    let single = |bound: &str| {
+        let mut characters = bound.chars();
+        match (characters.next(), characters.next()) {
+            (Some(character), None) => Some(character),
+            _ => None,
+        }
+    };
+
+    let (first, last) = (single(start)?, single(end)?);
+    Some(
+        stepped(first as i64, last as i64, step)
+            .into_iter()
+            .filter_map(|code| char::from_u32(u32::try_from(code).ok()?))
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Gives every `step`th number from `first` to `last`, counting down if `last` is smaller
+fn stepped(first: i64, last: i64, step: usize) -> Vec<i64> {
+    match first <= last {
+        true => (first..=last).step_by(step).collect(),
+        false => (last..=first).rev().step_by(step).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::tokenizer::{tokenize, Token};
+
+    /// Tokenizes a single word and expands its braces, giving the text of each resulting word
+    fn expand(input: &str) -> Vec<String> {
+        let tokens = tokenize(input).unwrap().tokens;
+        let [(Token::Word(word), _)] = tokens.as_slice() else {
+            panic!("expected a single word, found {:?}", tokens);
+        };
+
+        expand_braces(word).iter().map(Word::to_string).collect()
+    }
+
+    #[test]
+    fn lists_are_expanded_in_place() {
+        assert_eq!(expand("a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(expand("file.{rs,}"), ["file.rs", "file."]);
+        assert_eq!(expand("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand("{a,{b,c}}"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn sequences_are_expanded() {
+        assert_eq!(expand("{1..3}"), ["1", "2", "3"]);
+        assert_eq!(expand("{3..1}"), ["3", "2", "1"]);
+        assert_eq!(expand("{a..c}"), ["a", "b", "c"]);
+        assert_eq!(expand("{01..10..4}"), ["01", "05", "09"]);
+    }
+
+    #[test]
+    fn braces_without_list_or_sequence_are_kept() {
+        assert_eq!(expand("{}"), ["{}"]);
+        assert_eq!(expand("{a}"), ["{a}"]);
+        assert_eq!(expand("{a..}"), ["{a..}"]);
+        assert_eq!(expand("a{b,c"), ["a{b,c"]);
+    }
+
+    #[test]
+    fn quoted_braces_are_kept() {
+        assert_eq!(expand("'{a,b}'"), ["{a,b}"]);
+        assert_eq!(expand("\"{a,b}\""), ["{a,b}"]);
+        assert_eq!(expand("\\{a,b}"), ["{a,b}"]);
+        assert_eq!(expand("{a,'b,c'}"), ["a", "b,c"]);
+    }
+
+    #[test]
+    fn parameter_expansions_are_not_expanded_as_braces() {
+        assert_eq!(expand("${x,y}"), ["${x,y}"]);
+        assert_eq!(expand("$(echo {a,b})"), ["$(echo {a,b})"]);
+        assert_eq!(expand("${x}{a,b}"), ["${x}a", "${x}b"]);
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{BTreeSet, HashMap};
+use std::os::unix::prelude::PermissionsExt;
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::Context;
+
+use super::symbols::{Symbols, BEGIN_GROUP, DO, ELIF, ELSE, IF, SLASH, THEN, UNTIL, WHILE};
+use super::Dispatcher;
+use crate::state::{is_valid_variable_name, ShellState};
+
+/// The words which can come before the name of a command, which is completed after them
+const COMMAND_PREFIXES: [&str; 8] = [IF, THEN, ELIF, ELSE, WHILE, UNTIL, DO, BEGIN_GROUP];
+
+/// The names of a builtin, and the parser for its arguments which its completions come from
+struct BuiltinCompletions {
+    names: Vec<String>,
+    arguments: clap::Command,
+}
+
+/// Completer for the names of commands, and the flags, subcommands and values of builtins
+/// Anything else, such as the arguments of an executable, is completed as a file name
+pub struct CommandCompleter {
+    filenames: FilenameCompleter,
+    builtins: Vec<BuiltinCompletions>,
+    /// The user's aliases and the command lines they stand for
+    aliases: HashMap<String, String>,
+    /// The names of the user's functions
+    functions: Vec<String>,
+    /// The directories which executables are found in
+    path: Vec<PathBuf>,
+}
+
+impl CommandCompleter {
+    pub fn new(dispatcher: &Dispatcher) -> Self {
+        let builtins = dispatcher
+            .builtins()
+            .map(|builtin| BuiltinCompletions {
+                names: std::iter::once(builtin.true_name.as_str())
+                    .chain(builtin.aliases.iter())
+                    .map(str::to_owned)
+                    .collect(),
+                arguments: builtin.arguments(),
+            })
+            .collect();
+
+        Self {
+            filenames: FilenameCompleter::new(),
+            builtins,
+            aliases: HashMap::new(),
+            functions: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Updates the aliases, functions and PATH which are completed from the shell's state
+    // * The completer cannot borrow the shell, so these are copied from it before each prompt
+    pub fn refresh(&mut self, shell: &ShellState) {
+        self.aliases.clone_from(&shell.config.aliases);
+        self.functions = shell.functions.keys().cloned().collect();
+        self.path = shell
+            .environment
+            .PATH()
+            .iter()
+            .map(|path| path.path().clone())
+            .collect();
+    }
+
+    /// Gets the names of the builtins, aliases, functions and executables which start with a word
+    fn complete_command(&self, word: &str) -> BTreeSet<String> {
+        let builtins = self.builtins.iter().flat_map(|builtin| &builtin.names);
+        let mut names: BTreeSet<String> = builtins
+            .chain(self.aliases.keys())
+            .chain(&self.functions)
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+
+        for directory in &self.path {
+            let Ok(entries) = fs_err::read_dir(directory) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+
+                // * The metadata is only read for matching files, since the PATH can be large
+                // * Symbolic links are followed, since many executables are links to others
+                if name.starts_with(word) && !names.contains(&name) {
+                    let executable = fs_err::metadata(entry.path()).is_ok_and(|metadata| {
+                        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+                    });
+                    if executable {
+                        names.insert(name);
+                    }
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Gets the parser for the arguments of a command, if it is a builtin
+    /// An alias is followed to the command that it runs, but a function is never a builtin
+    fn builtin_arguments<'a>(&'a self, words: &mut Vec<&'a str>) -> Option<&'a clap::Command> {
+        if let Some(command_line) = self.aliases.get(*words.first()?) {
+            words.splice(0..1, command_line.split_whitespace());
+        }
+
+        let name = *words.first()?;
+        if self.functions.iter().any(|function| function == name) {
+            return None;
+        }
+
+        self.builtins
+            .iter()
+            .find(|builtin| {
+                builtin
+                    .names
+                    .iter()
+                    .any(|builtin_name| builtin_name == name)
+            })
+            .map(|builtin| &builtin.arguments)
+    }
+}
+
+impl Completer for CommandCompleter {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before_cursor = line.get(..pos).unwrap_or_default();
+        let command = before_cursor
+            .rsplit(Symbols::is_command_separator)
+            .next()
+            .unwrap_or_default();
+        let (previous, word) = command
+            .rsplit_once(char::is_whitespace)
+            .unwrap_or(("", command));
+
+        // * Keywords and assignments before a command do not stop its name from being completed
+        let mut words: Vec<&str> = previous
+            .split_whitespace()
+            .skip_while(|word| COMMAND_PREFIXES.contains(word) || is_assignment(word))
+            .collect();
+
+        let completions = match words.is_empty() {
+            // * A command name containing a '/' is a path, which is completed as a file name
+            true if word.contains(SLASH) => None,
+            true => Some(self.complete_command(word)),
+            false => self
+                .builtin_arguments(&mut words)
+                .and_then(|arguments| complete_arguments(arguments, &words[1..], word)),
+        };
+
+        match completions {
+            Some(completions) => Ok((
+                pos - word.len(),
+                completions
+                    .into_iter()
+                    .map(|completion| Pair {
+                        display: completion.clone(),
+                        replacement: completion,
+                    })
+                    .collect(),
+            )),
+            None => self.filenames.complete(line, pos, ctx),
+        }
+    }
+}
+
+/// Gets the flags, subcommands or values which can follow some arguments of a builtin and start
+/// with a word, or `None` if the word should be completed as a file name instead
+fn complete_arguments(
+    parser: &clap::Command,
+    arguments: &[&str],
+    word: &str,
+) -> Option<BTreeSet<String>> {
+    // * The parsers of the subcommands which were given are kept, since the flags of a command
+    // * marked as global can also be used with its subcommands
+    let mut parsers = vec![parser];
+    let mut positionals = 0;
+    let mut option = None;
+    let mut flags_ended = false;
+
+    for argument in arguments {
+        let current = *parsers.last()?;
+        if option.take().is_some() {
+            continue;
+        }
+
+        if !flags_ended && *argument == "--" {
+            flags_ended = true;
+        } else if !flags_ended && argument.starts_with('-') && argument.len() > 1 {
+            option = find_option(&parsers, argument);
+        } else if let Some(subcommand) = current
+            .find_subcommand(argument)
+            .filter(|_| positionals == 0)
+        {
+            parsers.push(subcommand);
+        } else {
+            positionals += 1;
+        }
+    }
+
+    let current = *parsers.last()?;
+    let completions: BTreeSet<String> = match option {
+        // * The value of an option is completed from its possible values, if it has any
+        Some(option) => possible_values(option),
+        None if !flags_ended && word.starts_with('-') => parsers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, parser)| {
+                let is_current = index == parsers.len() - 1;
+                parser
+                    .get_arguments()
+                    .filter(move |arg| is_current || arg.is_global_set())
+            })
+            .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+            .flat_map(|arg| {
+                let long = arg.get_long().map(|long| format!("--{}", long));
+                let short = arg.get_short().map(|short| format!("-{}", short));
+                long.into_iter().chain(short)
+            })
+            .collect(),
+        None if positionals == 0 && current.has_subcommands() => current
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| subcommand.get_name().to_owned())
+            .collect(),
+        None => {
+            // * The last positional argument can take any number of values
+            let positional = current.get_positionals().nth(positionals).or_else(|| {
+                current
+                    .get_positionals()
+                    .last()
+                    .filter(|arg| takes_many(arg))
+            })?;
+            possible_values(positional)
+        }
+    };
+
+    let completions: BTreeSet<String> = completions
+        .into_iter()
+        .filter(|completion| completion.starts_with(word))
+        .collect();
+
+    // * Arguments without fixed values, like paths, are completed as file names
+    match completions.is_empty() && !word.starts_with('-') {
+        true => None,
+        false => Some(completions),
+    }
+}
+
+/// Finds the option which a flag refers to, if it takes its value from the next argument
+// * A group of short flags like '-la' is followed by a value if its last flag takes one
+fn find_option<'a>(parsers: &[&'a clap::Command], flag: &str) -> Option<&'a Arg> {
+    let arg = match flag.strip_prefix("--") {
+        // * A value given after an '=' (e.g. '--truncation=10') is part of the flag itself
+        Some(long) if long.contains('=') => None,
+        Some(long) => parsers
+            .iter()
+            .rev()
+            .flat_map(|parser| parser.get_arguments())
+            .find(|arg| arg.get_long() == Some(long)),
+        None => {
+            let short = flag.chars().last()?;
+            parsers
+                .iter()
+                .rev()
+                .flat_map(|parser| parser.get_arguments())
+                .find(|arg| arg.get_short() == Some(short))
+        }
+    }?;
+
+    match arg.get_action().takes_values() {
+        true => Some(arg),
+        false => None,
+    }
+}
+
+/// Gets the values which an argument accepts, such as those of an enum
+fn possible_values(arg: &Arg) -> BTreeSet<String> {
+    arg.get_possible_values()
+        .into_iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_owned())
+        .collect()
+}
+
+/// Checks whether an argument can be given more than once, like a list of paths
+fn takes_many(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Append)
+        || arg
+            .get_num_args()
+            .is_some_and(|range| range.max_values() > 1)
+}
+
+/// Checks whether a word is a variable assignment (e.g. 'NAME=value')
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| is_valid_variable_name(name))
+}
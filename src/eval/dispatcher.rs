@@ -1,10 +1,300 @@
+use std::collections::HashMap;
+use std::io::{self, Cursor, Write};
 use std::os::unix::prelude::PermissionsExt;
+use std::path::Path as StdPath;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use super::tokenizer::tokenize;
-use crate::errors::Result;
-use crate::exec::{builtin_funcs, Builtin};
-use crate::exec::{Executable, Runnable};
-use crate::state::{Path, ShellState};
+use clap::CommandFactory;
+use nix::sys::signal::Signal;
+use nix::unistd::{self, ForkResult, Pid};
+
+use super::alias::expand_aliases;
+use super::expansion::{expand_fields, expand_pattern_word, expand_word};
+use super::glob::{matches_pattern, GlobOptions};
+use super::parser::{
+    is_complete, parse, AndOrList, Command, CommandList, CompoundCommand, LogicalOperator,
+    Pipeline, Redirection, SimpleCommand,
+};
+use super::redirection::apply_redirections;
+use super::scripting::is_rhai_script;
+use super::symbols::NEWLINE_CHAR;
+use super::tokenizer::{tokenize, Word};
+use crate::errors::{Handle, Result, RushError};
+use crate::exec::{builtin_args, builtin_funcs, signals, Builtin};
+use crate::exec::{Capture, Executable, Input, IoContext, Output, Runnable};
+use crate::state::{ExitStatus, Job, JobState, Jump, Path, ShellState};
+
+/// The number of functions and sourced scripts which can run inside one another
+// * Each call uses some of the shell's stack, so runaway recursion is stopped before it runs out
+const MAX_CALL_DEPTH: usize = 200;
+
+/// A command which has been resolved to a builtin, an executable, or commands run by the shell
+enum ResolvedCommand<'a> {
+    Builtin(&'a Builtin),
+    Executable(Executable),
+    /// A compound command, such as an 'if' or a loop
+    Compound(&'a CompoundCommand),
+    /// A function defined by the user, along with its body
+    Function(Arc<Command>),
+}
+
+/// A single command within a pipeline, along with the streams it has been connected to
+struct Stage<'a> {
+    /// The resolved command, or `None` if it could not be resolved
+    command: Option<ResolvedCommand<'a>>,
+    /// The command name and arguments, after expansion
+    arguments: Vec<String>,
+    /// Variables assigned before the command name, after expansion
+    assignments: Vec<(String, String)>,
+    /// The status of the last command substitution in the command, if any were run
+    substitution_status: Option<ExitStatus>,
+    io: IoContext,
+    /// Output of the previous builtin in the pipeline, which becomes this stage's input
+    capture: Option<Capture>,
+    state: StageState,
+    /// When the stage started running, which is used to measure how long it took
+    started: Instant,
+    /// How long the stage took to run, once it has finished inside the shell
+    duration: Duration,
+}
+
+/// The progress of a `Stage` through the execution of its pipeline
+enum StageState {
+    Pending,
+    Running(Pid),
+    Finished(Result<()>),
+}
+
+impl<'a> Stage<'a> {
+    fn new(dispatcher: &'a Dispatcher, shell: &mut ShellState, command: &'a Command) -> Self {
+        match command {
+            Command::Simple(command) => Self::from_simple_command(dispatcher, shell, command),
+            Command::Compound(compound, _) => Self::with_state(
+                Some(ResolvedCommand::Compound(compound)),
+                StageState::Pending,
+            ),
+            // * A function is defined as soon as its pipeline starts, so it can be used afterwards
+            Command::FunctionDefinition(name, body) => {
+                shell.functions.insert(name.clone(), body.clone());
+                Self::with_state(None, StageState::Finished(Ok(())))
+            }
+        }
+    }
+
+    fn with_state(command: Option<ResolvedCommand<'a>>, state: StageState) -> Self {
+        Self {
+            command,
+            arguments: Vec::new(),
+            assignments: Vec::new(),
+            substitution_status: None,
+            io: IoContext::default(),
+            capture: None,
+            state,
+            started: Instant::now(),
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn from_simple_command(
+        dispatcher: &'a Dispatcher,
+        shell: &mut ShellState,
+        command: &SimpleCommand,
+    ) -> Self {
+        shell.substitution_status = None;
+        let expanded = command
+            .arguments
+            .iter()
+            .map(|argument| expand_fields(argument, shell, dispatcher))
+            .collect::<Result<Vec<Vec<String>>>>()
+            .and_then(|arguments| {
+                let arguments = arguments.into_iter().flatten().collect::<Vec<String>>();
+                let assignments = command
+                    .assignments
+                    .iter()
+                    .map(|assignment| {
+                        Ok((
+                            assignment.name.clone(),
+                            expand_word(&assignment.value, shell, dispatcher)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<(String, String)>>>()?;
+                Ok((arguments, assignments))
+            });
+
+        let (arguments, assignments, resolved) = match expanded {
+            Ok((arguments, assignments)) => {
+                let resolved = arguments
+                    .first()
+                    .map(|command_name| dispatcher.resolve_command(shell, command_name))
+                    .transpose();
+                (arguments, assignments, resolved)
+            }
+            Err(error) => (Vec::new(), Vec::new(), Err(error)),
+        };
+
+        let (command, state) = match resolved {
+            Ok(Some(command)) => (Some(command), StageState::Pending),
+            // * A command made up of only assignments sets the variables in the shell itself,
+            // * and fails if the last command substitution in it failed
+            Ok(None) if !assignments.is_empty() => (None, StageState::Pending),
+            // * A command made up of only redirections just opens the files it refers to
+            Ok(None) => (None, StageState::Finished(Ok(()))),
+            Err(error) => (None, StageState::Finished(Err(error))),
+        };
+
+        Self {
+            arguments,
+            assignments,
+            substitution_status: shell.substitution_status.take(),
+            ..Self::with_state(command, state)
+        }
+    }
+
+    fn argument_refs(&self) -> Vec<&str> {
+        self.arguments.iter().map(String::as_str).collect()
+    }
+
+    /// Checks whether the stage runs inside the shell, rather than as a separate process
+    fn runs_in_shell(&self) -> bool {
+        matches!(
+            self.command,
+            Some(
+                ResolvedCommand::Builtin(_)
+                    | ResolvedCommand::Compound(_)
+                    | ResolvedCommand::Function(_)
+            )
+        )
+    }
+
+    /// Checks whether the stage runs inside the shell and reads from the shell's own standard input
+    fn reads_terminal(&self) -> bool {
+        self.runs_in_shell() && self.capture.is_none() && matches!(self.io.stdin, Input::Inherit)
+    }
+
+    /// Applies the command's redirections to the stage's streams
+    /// If a redirection fails, the stage does not run, and fails with the redirection's error
+    fn redirect(
+        &mut self,
+        redirections: &[Redirection],
+        shell: &mut ShellState,
+        dispatcher: &Dispatcher,
+    ) {
+        if let Err(error) = apply_redirections(redirections, &mut self.io, shell, dispatcher) {
+            self.command = None;
+            self.state = StageState::Finished(Err(error));
+        }
+    }
+
+    /// Starts the stage if it is an executable
+    /// If the stage could not be resolved, its streams are closed so that no other stage waits on it
+    /// If `use_process_group` is set, the executable joins the pipeline's process group,
+    /// which is created by the first executable in the pipeline
+    fn spawn(
+        &mut self,
+        shell: &ShellState,
+        use_process_group: bool,
+        process_group: &mut Option<Pid>,
+    ) {
+        match &self.command {
+            Some(ResolvedCommand::Executable(executable)) => {
+                let io = std::mem::take(&mut self.io);
+                let mut environment = shell.environment.exported_variables();
+                environment.extend(self.assignments.iter().cloned());
+                let group = use_process_group.then(|| process_group.unwrap_or(Pid::from_raw(0)));
+
+                self.started = Instant::now();
+                self.state = match executable.spawn(self.argument_refs(), io, environment, group) {
+                    Ok(process) => {
+                        let pid = Pid::from_raw(process.id() as i32);
+                        if group.is_some() && process_group.is_none() {
+                            *process_group = Some(pid);
+                        }
+
+                        StageState::Running(pid)
+                    }
+                    Err(error) => StageState::Finished(Err(error)),
+                };
+            }
+            None => drop(std::mem::take(&mut self.io)),
+            Some(_) => (),
+        }
+    }
+
+    /// Runs the stage to completion if it runs inside the shell or only assigns variables
+    // * Builtins and functions run inside the shell rather than as a separate process,
+    // * so any assignments before their name are made in the shell until they finish
+    fn run_builtin(&mut self, shell: &mut ShellState, dispatcher: &Dispatcher) {
+        // * Executables have already been started, and stages which could not run have finished
+        if !matches!(self.state, StageState::Pending) {
+            return;
+        }
+
+        self.started = Instant::now();
+        let mut io = std::mem::take(&mut self.io);
+        if let Some(capture) = self.capture.take() {
+            io.stdin = Input::Buffer(Cursor::new(capture.finish()));
+        }
+
+        // * The stage's standard error is kept so that its error can be written there,
+        // * which lets redirections like '2>/dev/null' apply to it
+        let mut stderr = io.stderr.try_clone().ok();
+        let result = match &self.command {
+            Some(ResolvedCommand::Builtin(builtin)) => {
+                let arguments = self.argument_refs();
+                with_assignments(shell, &self.assignments, |shell| {
+                    builtin.run(shell, dispatcher, arguments, io)
+                })
+            }
+            Some(ResolvedCommand::Compound(compound)) => {
+                dispatcher.eval_compound(shell, compound, &mut io)
+            }
+            Some(ResolvedCommand::Function(body)) => {
+                with_assignments(shell, &self.assignments, |shell| {
+                    dispatcher.call_function(shell, &self.arguments, body, &mut io)
+                })
+            }
+            Some(ResolvedCommand::Executable(_)) | None => self
+                .assignments
+                .iter()
+                .try_for_each(|(name, value)| shell.environment.set_variable(name, value))
+                .and_then(|()| match self.substitution_status {
+                    // * The substitution has already reported its own errors
+                    Some(status) => status.into_result().map_err(RushError::set_reported),
+                    None => Ok(()),
+                }),
+        };
+
+        self.state = StageState::Finished(match (result, stderr.as_mut()) {
+            (Err(error), Some(stderr)) if !error.is_output_closed() => {
+                error.report_to(stderr);
+                Err(error.set_reported())
+            }
+            (result, _) => result,
+        });
+        self.duration = self.started.elapsed();
+    }
+
+    /// Gives the result of the stage along with its exit status, once it has finished
+    /// The statuses of the executables in the pipeline are taken from its job, in order
+    fn finish(
+        self,
+        job_statuses: &mut impl Iterator<Item = ExitStatus>,
+    ) -> (Result<()>, ExitStatus) {
+        match self.state {
+            StageState::Running(_) => {
+                let status = job_statuses.next().unwrap_or_default();
+                (status.into_result(), status)
+            }
+            StageState::Finished(result) => {
+                let status = ExitStatus::from_result(&result, self.duration);
+                (result, status)
+            }
+            StageState::Pending => (Ok(()), ExitStatus::default()),
+        }
+    }
+}
 
 /// Represents a collection of builtin commands
 /// Allows for command resolution and execution through aliases
@@ -18,22 +308,41 @@ impl Default for Dispatcher {
     fn default() -> Self {
         let mut dispatcher = Self::new();
 
-        dispatcher.add_builtin("test", vec!["t"], builtin_funcs::test);
-        dispatcher.add_builtin("exit", vec!["quit", "q"], builtin_funcs::exit);
-        dispatcher.add_builtin("working-directory", vec!["pwd", "wd"], builtin_funcs::working_directory);
-        dispatcher.add_builtin("change-directory", vec!["cd"], builtin_funcs::change_directory);
-        dispatcher.add_builtin("list-directory", vec!["directory", "list", "ls", "dir"], builtin_funcs::list_directory);
-        dispatcher.add_builtin("previous-directory", vec!["back", "b", "prev", "pd"], builtin_funcs::previous_directory);
-        dispatcher.add_builtin("next-directory", vec!["forward", "f", "next", "nd"], builtin_funcs::next_directory);
-        dispatcher.add_builtin("clear-terminal", vec!["clear", "cls"], builtin_funcs::clear_terminal);
-        dispatcher.add_builtin("make-file", vec!["create", "touch", "new", "mf"], builtin_funcs::make_file);
-        dispatcher.add_builtin("make-directory", vec!["mkdir", "md"], builtin_funcs::make_directory);
-        dispatcher.add_builtin("delete-file", vec!["delete", "remove", "rm", "del", "df"], builtin_funcs::delete_file);
-        dispatcher.add_builtin("read-file", vec!["read", "cat", "rf"], builtin_funcs::read_file);
-        dispatcher.add_builtin("run-executable", vec!["run", "exec", "re"], builtin_funcs::run_executable);
-        dispatcher.add_builtin("configure", vec!["config", "conf"], builtin_funcs::configure);
-        dispatcher.add_builtin("environment-variable", vec!["environment", "env", "ev"], builtin_funcs::environment_variable);
-        dispatcher.add_builtin("edit-path", vec!["path", "ep"], builtin_funcs::edit_path);
+        dispatcher.add_builtin("test", vec!["t"], builtin_args::TestArgs::command, builtin_funcs::test);
+        dispatcher.add_builtin("exit", vec!["quit", "q"], builtin_args::ExitArgs::command, builtin_funcs::exit);
+        dispatcher.add_builtin("working-directory", vec!["pwd", "wd"], builtin_args::WorkingDirectoryArgs::command, builtin_funcs::working_directory);
+        dispatcher.add_builtin("change-directory", vec!["cd"], builtin_args::ChangeDirectoryArgs::command, builtin_funcs::change_directory);
+        dispatcher.add_builtin("list-directory", vec!["directory", "list", "ls", "dir"], builtin_args::ListDirectoryArgs::command, builtin_funcs::list_directory);
+        dispatcher.add_builtin("previous-directory", vec!["back", "b", "prev", "pd"], builtin_args::PreviousDirectoryArgs::command, builtin_funcs::previous_directory);
+        dispatcher.add_builtin("next-directory", vec!["forward", "f", "next", "nd"], builtin_args::NextDirectoryArgs::command, builtin_funcs::next_directory);
+        dispatcher.add_builtin("clear-terminal", vec!["clear", "cls"], builtin_args::ClearTerminalArgs::command, builtin_funcs::clear_terminal);
+        dispatcher.add_builtin("make-file", vec!["create", "touch", "new", "mf"], builtin_args::MakeFileArgs::command, builtin_funcs::make_file);
+        dispatcher.add_builtin("make-directory", vec!["mkdir", "md"], builtin_args::MakeDirectoryArgs::command, builtin_funcs::make_directory);
+        dispatcher.add_builtin("delete-file", vec!["delete", "remove", "rm", "del", "df"], builtin_args::DeleteFileArgs::command, builtin_funcs::delete_file);
+        dispatcher.add_builtin("read-file", vec!["read", "cat", "rf"], builtin_args::ReadFileArgs::command, builtin_funcs::read_file);
+        dispatcher.add_builtin("run-executable", vec!["run", "exec", "re"], builtin_args::RunExecutableArgs::command, builtin_funcs::run_executable);
+        dispatcher.add_builtin("configure", vec!["config", "conf"], builtin_args::ConfigureArgs::command, builtin_funcs::configure);
+        dispatcher.add_builtin("environment-variable", vec!["environment", "env", "ev"], builtin_args::EnvironmentVariableArgs::command, builtin_funcs::environment_variable);
+        dispatcher.add_builtin("edit-path", vec!["path", "ep"], builtin_args::EditPathArgs::command, builtin_funcs::edit_path);
+        dispatcher.add_builtin("set-variable", vec!["set", "var", "sv"], builtin_args::SetVariableArgs::command, builtin_funcs::set_variable);
+        dispatcher.add_builtin("unset-variable", vec!["unset", "uv"], builtin_args::UnsetVariableArgs::command, builtin_funcs::unset_variable);
+        dispatcher.add_builtin("export-variable", vec!["export", "exp"], builtin_args::ExportVariableArgs::command, builtin_funcs::export_variable);
+        dispatcher.add_builtin("set-alias", vec!["alias", "sa"], builtin_args::SetAliasArgs::command, builtin_funcs::set_alias);
+        dispatcher.add_builtin("unset-alias", vec!["unalias", "ua"], builtin_args::UnsetAliasArgs::command, builtin_funcs::unset_alias);
+        dispatcher.add_builtin("abbreviation", vec!["abbr"], builtin_args::AbbreviationArgs::command, builtin_funcs::abbreviation);
+        dispatcher.add_dispatching_builtin("command", vec![], builtin_args::CommandArgs::command, builtin_funcs::command);
+        dispatcher.add_builtin("calculate", vec!["calc", "math"], builtin_args::CalculateArgs::command, builtin_funcs::calculate);
+        dispatcher.add_dispatching_builtin("source", vec!["."], builtin_args::SourceArgs::command, builtin_funcs::source);
+        dispatcher.add_dispatching_builtin("rhai", vec![], builtin_args::RhaiArgs::command, builtin_funcs::rhai);
+        dispatcher.add_builtin("local-variable", vec!["local", "lv"], builtin_args::LocalVariableArgs::command, builtin_funcs::local_variable);
+        dispatcher.add_builtin("return", vec![], builtin_args::ReturnArgs::command, builtin_funcs::return_from_function);
+        dispatcher.add_builtin("break", vec![], builtin_args::BreakArgs::command, builtin_funcs::break_loop);
+        dispatcher.add_builtin("continue", vec![], builtin_args::ContinueArgs::command, builtin_funcs::continue_loop);
+        dispatcher.add_builtin("list-jobs", vec!["jobs", "lj"], builtin_args::ListJobsArgs::command, builtin_funcs::list_jobs);
+        dispatcher.add_builtin("foreground-job", vec!["fg", "fj"], builtin_args::ForegroundJobArgs::command, builtin_funcs::foreground_job);
+        dispatcher.add_builtin("background-job", vec!["bg", "bj"], builtin_args::BackgroundJobArgs::command, builtin_funcs::background_job);
+        dispatcher.add_builtin("wait-for-job", vec!["wait", "wj"], builtin_args::WaitForJobArgs::command, builtin_funcs::wait_for_job);
+        dispatcher.add_builtin("disown-job", vec!["disown", "dj"], builtin_args::DisownJobArgs::command, builtin_funcs::disown_job);
 
         dispatcher
     }
@@ -47,14 +356,35 @@ impl Dispatcher {
     }
 
     /// Adds a builtin to the `Dispatcher`
-    fn add_builtin<F: Fn(&mut ShellState, Vec<&str>) -> Result<()> + 'static>(
+    fn add_builtin<F: Fn(&mut ShellState, Vec<&str>, &mut IoContext) -> Result<()> + 'static>(
         &mut self,
         true_name: &str,
         aliases: Vec<&str>,
+        arguments: fn() -> clap::Command,
+        function: F,
+    ) {
+        self.add_dispatching_builtin(true_name, aliases, arguments, move |shell, _, args, io| {
+            function(shell, args, io)
+        })
+    }
+
+    /// Adds a builtin which runs other commands, and so is given the `Dispatcher` running it
+    fn add_dispatching_builtin<
+        F: Fn(&mut ShellState, &Dispatcher, Vec<&str>, &mut IoContext) -> Result<()> + 'static,
+    >(
+        &mut self,
+        true_name: &str,
+        aliases: Vec<&str>,
+        arguments: fn() -> clap::Command,
         function: F,
     ) {
         self.commands
-            .push(Builtin::new(true_name, aliases, function))
+            .push(Builtin::new(true_name, aliases, arguments, function))
+    }
+
+    /// Gets the builtins, in the order they were added
+    pub fn builtins(&self) -> impl Iterator<Item = &Builtin> {
+        self.commands.iter()
     }
 
     /// Attempts to locate a builtin command by name or alias
@@ -74,26 +404,597 @@ impl Dispatcher {
 
     /// Evaluates and executes a command from a string
     pub fn eval(&self, shell: &mut ShellState, line: &str) -> Result<()> {
-        let args = tokenize(line);
-        let command_name = args.get(0).unwrap().as_str();
-        let command_args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
-        self.dispatch(shell, command_name, command_args)?;
+        self.eval_to(shell, line, &mut IoContext::default())
+    }
+
+    /// Evaluates and executes a command from a string, using the streams in `io`
+    fn eval_to(&self, shell: &mut ShellState, line: &str, io: &mut IoContext) -> Result<()> {
+        let command_list = match tokenize(line)
+            .and_then(|input| expand_aliases(input, &shell.config.aliases))
+            .and_then(parse)
+        {
+            Ok(command_list) => command_list,
+            Err(error) => {
+                shell.record_status(vec![ExitStatus::from_error(&error, Duration::ZERO)]);
+                return Err(error.with_source(line));
+            }
+        };
+
+        self.eval_list(shell, &command_list, io)
+    }
+
+    /// Evaluates a script, running each of its commands as soon as every line of it has been read
+    // * Errors are reported as they occur, and do not stop the rest of the script from running
+    pub fn eval_script(&self, shell: &mut ShellState, script: &str, io: &mut IoContext) {
+        let mut command = String::new();
+        for line in script.lines() {
+            command.push_str(line);
+            command.push(NEWLINE_CHAR);
+            if !is_complete(&command) {
+                continue;
+            }
+
+            // The rest of the script is skipped once the 'exit' or 'return' builtin has been run
+            if shell.jump.is_some() {
+                return;
+            }
+
+            if let Err(error) = self.eval_to(shell, &command, io) {
+                error.report();
+            }
+
+            command.clear();
+        }
+
+        // Whatever is left at the end of the script is incomplete, which is reported as an error
+        if !command.trim().is_empty() && shell.jump.is_none() {
+            if let Err(error) = self.eval_to(shell, &command, io) {
+                error.report();
+            }
+        }
+    }
+
+    /// Runs the script at the given path within the current shell, so that any variables,
+    /// directory changes and other state that it sets remain afterwards
+    pub fn source(&self, shell: &mut ShellState, path: &StdPath, io: &mut IoContext) -> Result<()> {
+        let script =
+            fs_err::read_to_string(path).replace_err(|| file_err!(CouldNotReadFile: path))?;
+        if shell.call_depth >= MAX_CALL_DEPTH {
+            return Err(dispatch_err!(RecursionLimitReached: path.display().to_string()));
+        }
+
+        // * Rhai scripts are run by the scripting engine rather than as shell commands
+        shell.call_depth += 1;
+        let result = match is_rhai_script(path) {
+            true => self.eval_rhai(shell, &script, &io.stdout),
+            false => {
+                self.eval_script(shell, &script, io);
+                Ok(())
+            }
+        };
+        shell.call_depth -= 1;
+        result?;
+
+        // * 'return' in a sourced script stops the script, which finishes with the status it gave
+        if let Some(Jump::Return(code)) = shell.jump {
+            shell.jump = None;
+            shell.record_status(vec![ExitStatus {
+                code,
+                ..ExitStatus::default()
+            }]);
+        }
 
         Ok(())
     }
 
-    /// Resolves and dispatches a command to the appropriate builtin or executable
-    fn dispatch(
+    /// Runs a command and gives everything it writes to its standard output,
+    /// which is used for command substitutions like '$(pwd)'
+    /// Trailing newlines are removed from the output
+    pub fn capture(&self, shell: &mut ShellState, line: &str) -> Result<String> {
+        let command_list = tokenize(line)
+            .and_then(|input| expand_aliases(input, &shell.config.aliases))
+            .and_then(parse)
+            .map_err(|error| error.with_source(line))?;
+        let (capture, output) = Capture::new()?;
+        let mut io = IoContext {
+            stdout: output,
+            ..IoContext::default()
+        };
+
+        // * The command runs in a copy of the shell's state, so that anything it changes is
+        // * discarded afterwards, and running 'exit', 'break' or 'return' in it only ends the
+        // * substitution rather than the shell, loop or function
+        // * Errors from the command are reported, but do not stop the surrounding command from
+        // * running, since only the output of the command is needed
+        let mut subshell = shell.enter_subshell();
+        if let Err(error) = self.eval_list(&mut subshell, &command_list, &mut io) {
+            error.report_to(&mut io.stderr);
+        }
+
+        // * 'exit' in a substitution ends it with the code it was given
+        if let Some(Jump::Exit(code)) = subshell.jump {
+            subshell.record_status(vec![ExitStatus {
+                code,
+                ..ExitStatus::default()
+            }]);
+        }
+
+        let restored = shell.exit_subshell(subshell);
+
+        // * The output has to be closed before the capture can finish collecting it
+        drop(io);
+        let output = String::from_utf8_lossy(&capture.finish()).into_owned();
+        restored?;
+        Ok(output.trim_end_matches(NEWLINE_CHAR).to_owned())
+    }
+
+    /// Executes each and-or list in a `CommandList` in order, using the streams in `io`
+    // * Only the result of the final and-or list is returned, so any errors from the preceding ones
+    // * are printed as soon as they occur
+    fn eval_list(
         &self,
         shell: &mut ShellState,
+        command_list: &CommandList,
+        io: &mut IoContext,
+    ) -> Result<()> {
+        let mut result: Result<()> = Ok(());
+        for item in &command_list.items {
+            // Nothing else is run once a builtin like 'exit' or 'break' has been run
+            if shell.jump.is_some() {
+                break;
+            }
+
+            if let Err(error) = result {
+                error.report_to(&mut io.stderr);
+            }
+
+            result = match item.background {
+                true => self.eval_in_background(shell, &item.and_or, io),
+                false => self.eval_and_or(shell, &item.and_or, io),
+            };
+        }
+
+        result
+    }
+
+    /// Starts an and-or list as a job which runs alongside the shell, using the streams in `io`
+    // * The job runs in a copy of the shell, so that builtins, functions and compound commands
+    // * can run in the background too, without changing the variables or directory of the shell
+    fn eval_in_background(
+        &self,
+        shell: &mut ShellState,
+        and_or: &AndOrList,
+        io: &mut IoContext,
+    ) -> Result<()> {
+        // * Anything left in the buffer would otherwise be written out by both copies of the shell
+        let _ = io::stdout().flush();
+        // * SAFETY: The copy only runs the shell's own code, and does not rely on other threads
+        let child = match unsafe { unistd::fork() } {
+            Ok(ForkResult::Parent { child }) => child,
+            Ok(ForkResult::Child) => {
+                signals::init_background();
+                shell.interactive = false;
+                shell.jobs = Default::default();
+                if let Err(error) = self.eval_and_or(shell, and_or, io) {
+                    error.report_to(&mut io.stderr);
+                }
+
+                let _ = io::stdout().flush();
+                std::process::exit(match shell.jump {
+                    Some(Jump::Exit(code)) => code,
+                    _ => shell.last_status.code,
+                });
+            }
+            Err(_) => {
+                let error = executable_err!(CouldNotStartJob);
+                shell.record_status(vec![ExitStatus::from_error(&error, Duration::ZERO)]);
+                return Err(error);
+            }
+        };
+
+        // * The copy also moves itself into its own process group, since either may run first
+        let _ = unistd::setpgid(child, child);
+        let mut job = Job::new(describe_and_or(and_or), Some(child));
+        job.add_process(child, Instant::now());
+        let id = shell.jobs.add(job);
+        let _ = io.stderr.println(format_args!("[{}] {}", id, child));
+
+        shell.record_status(vec![ExitStatus::default()]);
+        Ok(())
+    }
+
+    /// Executes the pipelines in an `AndOrList`, short-circuiting based on their results
+    fn eval_and_or(
+        &self,
+        shell: &mut ShellState,
+        and_or: &AndOrList,
+        io: &mut IoContext,
+    ) -> Result<()> {
+        let mut result = self.eval_pipeline(shell, &and_or.first.commands, io);
+        for (operator, pipeline) in &and_or.rest {
+            let should_run = match operator {
+                LogicalOperator::And => result.is_ok(),
+                LogicalOperator::Or => result.is_err(),
+            };
+
+            if should_run && shell.jump.is_none() {
+                result = self.eval_pipeline(shell, &pipeline.commands, io);
+            }
+        }
+
+        result
+    }
+
+    /// Executes the commands of a `Pipeline`, connecting the output of each command to the input
+    /// of the next
+    fn eval_pipeline(
+        &self,
+        shell: &mut ShellState,
+        commands: &[Command],
+        io: &mut IoContext,
+    ) -> Result<()> {
+        // Resolve every command up front, so that it is known how each pair of commands connects
+        let mut stages: Vec<Stage> = commands
+            .iter()
+            .map(|command| Stage::new(self, shell, command))
+            .collect();
+
+        // The first command reads from the input of the pipeline as a whole, the last command
+        // writes to its output, and every command writes to its standard error
+        if let Some(first) = stages.first_mut() {
+            first.io.stdin = io.stdin.try_clone()?;
+        }
+
+        if let Some(last) = stages.last_mut() {
+            last.io.stdout = io
+                .stdout
+                .try_clone()
+                .replace_err(|| executable_err!(CouldNotCreatePipe))?;
+        }
+
+        for stage in &mut stages {
+            stage.io.stderr = io
+                .stderr
+                .try_clone()
+                .replace_err(|| executable_err!(CouldNotCreatePipe))?;
+        }
+
+        for index in 1..stages.len() {
+            let (previous, next) = (&stages[index - 1], &stages[index]);
+            // * Builtins run one after another on the shell's thread, so output from one builtin
+            // * to another has to be collected in full before the next one can start reading it
+            let link = if previous.runs_in_shell() && next.runs_in_shell() {
+                let (capture, output) = Capture::new()?;
+                (output, None, Some(capture))
+            } else {
+                let (reader, writer) =
+                    io::pipe().replace_err(|| executable_err!(CouldNotCreatePipe))?;
+                (Output::Pipe(writer), Some(Input::Pipe(reader)), None)
+            };
+
+            let (output, input, capture) = link;
+            stages[index - 1].io.stdout = output;
+            if let Some(input) = input {
+                stages[index].io.stdin = input;
+            }
+
+            stages[index].capture = capture;
+        }
+
+        // Redirections are applied after the pipes are connected, so that they take precedence
+        for (stage, command) in stages.iter_mut().zip(commands) {
+            stage.redirect(command.redirections(), shell, self);
+        }
+
+        // Start every executable first, so that they are all running by the time builtins
+        // start reading from or writing to them
+        let use_process_group = signals::is_interactive();
+        let mut process_group = None;
+        for stage in &mut stages {
+            stage.spawn(shell, use_process_group, &mut process_group);
+        }
+
+        // The executables are given the terminal while the pipeline runs, so that keypresses
+        // like Ctrl-C interrupt the pipeline rather than the shell
+        // * If a builtin reads from the terminal, this waits until the builtins have run, because
+        // * the shell is stopped if it reads from the terminal while it is not in the foreground
+        let builtins_read_terminal = stages.iter().any(Stage::reads_terminal);
+        let give_terminal = |process_group: Option<Pid>| {
+            if let Some(process_group) = process_group {
+                signals::set_foreground(process_group);
+            }
+        };
+
+        if !builtins_read_terminal {
+            give_terminal(process_group);
+        }
+
+        for stage in &mut stages {
+            stage.run_builtin(shell, self);
+        }
+
+        if builtins_read_terminal {
+            give_terminal(process_group);
+        }
+
+        let command = stages
+            .iter()
+            .map(|stage| stage.arguments.join(" "))
+            .collect::<Vec<String>>()
+            .join(" | ");
+        let mut job = Job::new(command, process_group);
+        for stage in &stages {
+            if let StageState::Running(pid) = stage.state {
+                job.add_process(pid, stage.started);
+            }
+        }
+
+        let wait_result = job.wait();
+        if process_group.is_some() {
+            signals::reclaim_terminal();
+        }
+
+        wait_result?;
+
+        // A pipeline which is stopped (e.g. with Ctrl-Z) is kept as a job, so it can be resumed later
+        if matches!(job.state(), JobState::Stopped) {
+            let error = executable_err!(JobStopped: shell.jobs.add(job));
+            shell.record_status(vec![ExitStatus::from_error(&error, Duration::ZERO)]);
+            return Err(error);
+        }
+
+        // Only the result of the last command is used as the result of the pipeline
+        // * Earlier commands commonly fail when a later command stops reading their output early,
+        // * so only errors from commands that could not run properly are reported
+        let last_index = stages.len() - 1;
+        let mut result = Ok(());
+        let mut statuses = Vec::with_capacity(stages.len());
+        let mut job_statuses = job.statuses().into_iter();
+        for (index, stage) in stages.into_iter().enumerate() {
+            let is_process = matches!(stage.state, StageState::Running(_));
+            let (stage_result, status) = stage.finish(&mut job_statuses);
+            statuses.push(status);
+
+            match stage_result {
+                _ if index == last_index => result = stage_result,
+                Err(error) if !is_process && !error.is_output_closed() => {
+                    error.report_to(&mut io.stderr)
+                }
+                _ => (),
+            }
+        }
+
+        shell.record_status(statuses);
+        result
+    }
+
+    /// Executes a compound command, such as an 'if' or a loop, using the streams in `io`
+    /// The result is that of the last command run inside of it, or success if none were run
+    fn eval_compound(
+        &self,
+        shell: &mut ShellState,
+        compound: &CompoundCommand,
+        io: &mut IoContext,
+    ) -> Result<()> {
+        match compound {
+            CompoundCommand::BraceGroup(list) => self.eval_list(shell, list, io),
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches {
+                    if self.eval_condition(shell, condition, io) {
+                        return self.eval_list(shell, body, io);
+                    }
+
+                    if shell.jump.is_some() {
+                        return Ok(());
+                    }
+                }
+
+                match otherwise {
+                    Some(body) => self.eval_list(shell, body, io),
+                    None => Ok(()),
+                }
+            }
+            CompoundCommand::While {
+                condition,
+                body,
+                until,
+            } => {
+                let mut result = Ok(());
+                self.enter_loop(shell);
+                // * 'until' runs its body for as long as the condition fails instead
+                while self.eval_condition(shell, condition, io) != *until {
+                    if should_stop_loop(shell) {
+                        break;
+                    }
+
+                    result = self.eval_list(shell, body, io);
+                    if should_stop_loop(shell) {
+                        break;
+                    }
+                }
+
+                shell.loop_depth -= 1;
+                result
+            }
+            CompoundCommand::For { name, words, body } => {
+                let values = match words {
+                    Some(words) => {
+                        let mut values = Vec::new();
+                        for word in words {
+                            values.extend(expand_fields(word, shell, self)?);
+                        }
+
+                        values
+                    }
+                    None => shell.arguments.clone(),
+                };
+
+                let mut result = Ok(());
+                self.enter_loop(shell);
+                for value in values {
+                    result = shell.environment.set_variable(name, &value);
+                    if result.is_err() {
+                        break;
+                    }
+
+                    result = self.eval_list(shell, body, io);
+                    if should_stop_loop(shell) {
+                        break;
+                    }
+                }
+
+                shell.loop_depth -= 1;
+                result
+            }
+            CompoundCommand::Case { word, items } => {
+                let text = expand_word(word, shell, self)?;
+                let options = GlobOptions {
+                    dotfiles: true,
+                    extended: shell.config.extglob,
+                };
+
+                for item in items {
+                    for pattern in &item.patterns {
+                        let pattern = expand_pattern_word(pattern, shell, self)?;
+                        if matches_pattern(&pattern, &text, options) {
+                            return self.eval_list(shell, &item.body, io);
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs the condition of an 'if' or a loop, giving whether it succeeded
+    // * A condition failing is expected, so its error is only reported if the command
+    // * could not run properly, rather than just exiting with a non-zero code
+    fn eval_condition(
+        &self,
+        shell: &mut ShellState,
+        condition: &CommandList,
+        io: &mut IoContext,
+    ) -> bool {
+        match self.eval_list(shell, condition, io) {
+            Ok(()) => true,
+            Err(error) => {
+                if !error.is_failed_status() {
+                    error.report_to(&mut io.stderr);
+                }
+
+                false
+            }
+        }
+    }
+
+    /// Records that a loop has started, so that 'break' and 'continue' can be used inside of it
+    fn enter_loop(&self, shell: &mut ShellState) {
+        shell.loop_depth += 1;
+        // * Ctrl-C only stops a loop if it is pressed while the loop is running
+        shell.interrupted.store(false, Ordering::Relaxed);
+    }
+
+    /// Runs a function with the given arguments, where the first is the function's name and the
+    /// rest become its positional parameters
+    /// Once the function returns, the positional parameters and any variables it made local are
+    /// restored to the values they had before it was called
+    fn call_function(
+        &self,
+        shell: &mut ShellState,
+        arguments: &[String],
+        body: &Command,
+        io: &mut IoContext,
+    ) -> Result<()> {
+        let Some((name, arguments)) = arguments.split_first() else {
+            return Ok(());
+        };
+
+        if shell.call_depth >= MAX_CALL_DEPTH {
+            return Err(dispatch_err!(RecursionLimitReached: name));
+        }
+
+        let outer_arguments = std::mem::replace(&mut shell.arguments, arguments.to_vec());
+        // * Loops outside of the function cannot be stopped by 'break' inside of it
+        let outer_loop_depth = std::mem::take(&mut shell.loop_depth);
+        shell.local_scopes.push(HashMap::new());
+        shell.call_depth += 1;
+
+        // * The body is run as a pipeline of its own, so that its redirections are applied
+        let mut result = self.eval_pipeline(shell, std::slice::from_ref(body), io);
+
+        shell.call_depth -= 1;
+        shell.loop_depth = outer_loop_depth;
+        shell.arguments = outer_arguments;
+        for (name, value) in shell.local_scopes.pop().unwrap_or_default() {
+            let restored = match value {
+                Some(value) => shell.environment.set_variable(&name, &value),
+                None => shell.environment.unset_variable(&name),
+            };
+
+            result = result.and(restored);
+        }
+
+        if let Some(Jump::Return(code)) = shell.jump {
+            shell.jump = None;
+            let status = ExitStatus {
+                code,
+                ..ExitStatus::default()
+            };
+            // * 'return' gives the function's status rather than an error, so it is not reported
+            result = status.into_result().map_err(RushError::set_reported);
+        }
+
+        result
+    }
+
+    /// Resolves a command name to the appropriate builtin or executable
+    fn resolve_command(
+        &self,
+        shell: &ShellState,
         command_name: &str,
-        command_args: Vec<&str>,
+    ) -> Result<ResolvedCommand<'_>> {
+        // Functions defined by the user take precedence over builtins and executables
+        match shell.functions.get(command_name) {
+            Some(body) => Ok(ResolvedCommand::Function(body.clone())),
+            None => self.resolve_builtin_or_executable(shell, command_name),
+        }
+    }
+
+    /// Runs a builtin or executable directly, skipping any function with the same name
+    /// This is used by the 'command' builtin, whose arguments are never expanded as aliases either
+    pub fn run_command(
+        &self,
+        shell: &mut ShellState,
+        arguments: Vec<&str>,
+        io: IoContext,
     ) -> Result<()> {
-        // If the command resides in the Dispatcher (generally means it is a builtin) run it
+        let Some(command_name) = arguments.first() else {
+            return Ok(());
+        };
+
+        match self.resolve_builtin_or_executable(shell, command_name)? {
+            ResolvedCommand::Builtin(builtin) => builtin.run(shell, self, arguments, io),
+            ResolvedCommand::Executable(executable) => executable.run(shell, arguments, io),
+            // * Only builtins and executables are resolved here
+            ResolvedCommand::Compound(_) | ResolvedCommand::Function(_) => Ok(()),
+        }
+    }
+
+    /// Resolves a command name to a builtin, or an executable in the PATH
+    fn resolve_builtin_or_executable(
+        &self,
+        shell: &ShellState,
+        command_name: &str,
+    ) -> Result<ResolvedCommand<'_>> {
         if let Some(command) = self.resolve(command_name) {
-            command.run(shell, command_args)
+            // If the command resides in the Dispatcher (generally means it is a builtin) use it
+            Ok(ResolvedCommand::Builtin(command))
         } else {
-            // If the command is not in the Dispatcher, try to run it as an executable from the PATH
+            // If the command is not in the Dispatcher, try to find it as an executable in the PATH
             let path = Path::try_resolve_executable(command_name, shell.environment.PATH());
             if let Ok(path) = path {
                 // Check if the file is executable (has the executable bit set)
@@ -103,7 +1004,7 @@ impl Dispatcher {
                     if permission_code & 0o111 == 0 {
                         Err(dispatch_err!(CommandNotExecutable: permission_code))
                     } else {
-                        Executable::new(path).run(shell, command_args)
+                        Ok(ResolvedCommand::Executable(Executable::new(path)))
                     }
                 } else {
                     // If the file cannot be read, return an error
@@ -115,3 +1016,267 @@ impl Dispatcher {
         }
     }
 }
+
+/// Handles a 'break' or 'continue' at the end of an iteration of a loop, giving whether the loop
+/// should stop, which is also the case for any other jump such as 'return'
+// * A count above one applies to the enclosing loops as well, so the jump is passed on with one
+// * less, and the loop it reaches last handles it like a plain 'break' or 'continue'
+fn should_stop_loop(shell: &mut ShellState) -> bool {
+    match shell.jump {
+        None => loop_interrupted(shell),
+        Some(Jump::Continue(1)) => {
+            shell.jump = None;
+            loop_interrupted(shell)
+        }
+        Some(Jump::Break(1)) => {
+            shell.jump = None;
+            true
+        }
+        Some(Jump::Break(count)) => {
+            shell.jump = Some(Jump::Break(count - 1));
+            true
+        }
+        Some(Jump::Continue(count)) => {
+            shell.jump = Some(Jump::Continue(count - 1));
+            true
+        }
+        Some(_) => true,
+    }
+}
+
+/// Checks whether Ctrl-C was pressed while a loop was running, which stops the loop
+/// The keypress either interrupts the shell itself, or kills the command which was running
+fn loop_interrupted(shell: &ShellState) -> bool {
+    shell.interrupted.swap(false, Ordering::Relaxed)
+        || shell.last_status.signal == Some(Signal::SIGINT as i32)
+}
+
+/// Describes an and-or list for the job table, in the form that it would be written
+fn describe_and_or(and_or: &AndOrList) -> String {
+    let mut description = describe_pipeline(&and_or.first);
+    for (operator, pipeline) in &and_or.rest {
+        description.push_str(match operator {
+            LogicalOperator::And => " && ",
+            LogicalOperator::Or => " || ",
+        });
+        description.push_str(&describe_pipeline(pipeline));
+    }
+
+    description
+}
+
+/// Describes a pipeline for the job table, in the form that it would be written
+// * Only the keyword of a compound command is shown, since its body may span many lines
+fn describe_pipeline(pipeline: &Pipeline) -> String {
+    let describe_command = |command: &Command| match command {
+        Command::Simple(command) => command
+            .assignments
+            .iter()
+            .map(|assignment| format!("{}={}", assignment.name, assignment.value))
+            .chain(command.arguments.iter().map(Word::to_string))
+            .collect::<Vec<String>>()
+            .join(" "),
+        Command::Compound(compound, _) => match compound {
+            CompoundCommand::BraceGroup(_) => "{ ... }".to_owned(),
+            CompoundCommand::If { .. } => "if ... fi".to_owned(),
+            CompoundCommand::While { until: false, .. } => "while ... done".to_owned(),
+            CompoundCommand::While { until: true, .. } => "until ... done".to_owned(),
+            CompoundCommand::For { name, .. } => format!("for {} ... done", name),
+            CompoundCommand::Case { word, .. } => format!("case {} ... esac", word),
+        },
+        Command::FunctionDefinition(name, _) => format!("{}() ...", name),
+    };
+
+    pipeline
+        .commands
+        .iter()
+        .map(describe_command)
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+/// Runs a builtin or function with the variables assigned before its name, restoring the values
+/// they replaced once it has finished, in the same way as local variables
+fn with_assignments(
+    shell: &mut ShellState,
+    assignments: &[(String, String)],
+    run: impl FnOnce(&mut ShellState) -> Result<()>,
+) -> Result<()> {
+    let outer_values: Vec<_> = assignments
+        .iter()
+        .map(|(name, _)| (name, shell.environment.get_variable(name)))
+        .collect();
+
+    let mut result = assignments
+        .iter()
+        .try_for_each(|(name, value)| shell.environment.set_variable(name, value))
+        .and_then(|_| run(shell));
+
+    // * Values are restored in reverse, so that a variable assigned twice gets its original value
+    for (name, value) in outer_values.into_iter().rev() {
+        let restored = match value {
+            Some(value) => shell.environment.set_variable(name, &value),
+            None => shell.environment.unset_variable(name),
+        };
+
+        result = result.and(restored);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_does_not_change_directory() {
+        let mut shell = ShellState::for_tests();
+        let cwd = shell.CWD().clone();
+        let output = Dispatcher::default()
+            .capture(&mut shell, "cd /; pwd")
+            .unwrap();
+
+        assert_eq!(output, "/");
+        assert_eq!(shell.CWD(), &cwd);
+        assert_eq!(std::env::current_dir().unwrap(), *cwd.path());
+    }
+
+    #[test]
+    fn capture_does_not_change_variables() {
+        let mut shell = ShellState::for_tests();
+        let dispatcher = Dispatcher::default();
+        shell.environment.set_variable("x", "1").unwrap();
+        let output = dispatcher.capture(&mut shell, "x=2; echo $x").unwrap();
+
+        assert_eq!(output, "2");
+        assert_eq!(shell.environment.get_variable("x").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn capture_does_not_define_functions_or_aliases() {
+        let mut shell = ShellState::for_tests();
+        let dispatcher = Dispatcher::default();
+        dispatcher
+            .capture(&mut shell, "greet() { echo hi; }; set-alias ll=ls")
+            .unwrap();
+
+        assert!(shell.functions.is_empty());
+        assert!(shell.config.aliases.is_empty());
+    }
+
+    #[test]
+    fn capture_exit_only_ends_substitution() {
+        let mut shell = ShellState::for_tests();
+        let output = Dispatcher::default()
+            .capture(&mut shell, "echo before; exit 3; echo after")
+            .unwrap();
+
+        assert_eq!(output, "before");
+        assert_eq!(shell.jump, None);
+    }
+
+    #[test]
+    fn assignments_take_status_of_last_substitution() {
+        let mut shell = ShellState::for_tests();
+        let output = Dispatcher::default()
+            .capture(
+                &mut shell,
+                "x=$(exit 3); echo $?; x=$(exit 3)$(true); echo $?; false; x=1; echo $?",
+            )
+            .unwrap();
+
+        assert_eq!(output, "3\n0\n0");
+    }
+
+    #[test]
+    fn function_reads_pipeline_input() {
+        let mut shell = ShellState::for_tests();
+        let output = Dispatcher::default()
+            .capture(&mut shell, "f() { cat; }; echo x | f")
+            .unwrap();
+
+        assert_eq!(output, "x");
+    }
+
+    #[test]
+    fn compound_command_reads_redirected_input() {
+        let mut shell = ShellState::for_tests();
+        let path = std::env::temp_dir().join(format!("rush-test-{}", std::process::id()));
+        fs_err::write(&path, "a\nb\n").unwrap();
+        let output = Dispatcher::default()
+            .capture(&mut shell, &format!("{{ cat; cat; }} < {}", path.display()));
+        fs_err::remove_file(&path).unwrap();
+
+        assert_eq!(output.unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn function_errors_follow_its_redirections() {
+        let mut shell = ShellState::for_tests();
+        let dispatcher = Dispatcher::default();
+        dispatcher
+            .eval(&mut shell, "f() { no-such-command; }; g() { return 3; }")
+            .unwrap();
+        let output = dispatcher.capture(&mut shell, "f 2>&1").unwrap();
+        assert!(output.contains("no-such-command"));
+
+        // * 'return' only sets the function's status, so nothing is written for it
+        let output = dispatcher.capture(&mut shell, "g 2>&1").unwrap();
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn assignments_last_for_builtin_or_function() {
+        let mut shell = ShellState::for_tests();
+        let dispatcher = Dispatcher::default();
+        shell.environment.set_variable("x", "5").unwrap();
+        let output = dispatcher
+            .capture(
+                &mut shell,
+                "f() { echo $x $y; }; x=1 y=2 f; x=2 calc x + 1; echo $x \"[$y]\"",
+            )
+            .unwrap();
+
+        assert_eq!(output, "1 2\n3\n5 []");
+    }
+
+    #[test]
+    fn variables_exported_before_being_set_are_passed_on() {
+        let mut shell = ShellState::for_tests();
+        Dispatcher::default()
+            .eval(&mut shell, "export b; b=1")
+            .unwrap();
+
+        let exported = shell.environment.exported_variables();
+        assert_eq!(exported.get("b").map(String::as_str), Some("1"));
+    }
+
+    #[test]
+    fn path_entries_are_exported_as_given() {
+        let mut shell = ShellState::for_tests();
+        let directory = std::env::temp_dir().join(".");
+        Dispatcher::default()
+            .eval(
+                &mut shell,
+                &format!("edit-path append {}", directory.display()),
+            )
+            .unwrap();
+
+        let path = shell.environment.exported_variables()["PATH"].clone();
+        assert!(path.ends_with(&format!(":{}", directory.display())));
+    }
+
+    #[test]
+    fn functions_and_compound_commands_run_in_background() {
+        let mut shell = ShellState::for_tests();
+        let output = Dispatcher::default()
+            .capture(
+                &mut shell,
+                "x=1; f() { echo $1; }; f x & wait; { x=2; } && true & wait; echo $x",
+            )
+            .unwrap();
+
+        assert_eq!(output, "x\n1");
+    }
+}
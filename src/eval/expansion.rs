@@ -0,0 +1,636 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use nix::unistd::User;
+
+use super::arithmetic::evaluate;
+use super::braces::expand_braces;
+use super::dispatcher::Dispatcher;
+use super::glob::{expand_glob, is_pattern_character, GlobOptions};
+use super::symbols::{
+    ALL_ARGUMENTS, BACKSLASH, BACKTICK, DOLLAR, DOUBLE_QUOTE, HASH, LAST_STATUS, LEFT_BRACE,
+    LEFT_PAREN, RIGHT_BRACE, RIGHT_PAREN, SINGLE_QUOTE, SLASH, STAR, TILDE,
+};
+use super::tokenizer::{Word, WordPart};
+use crate::errors::Result;
+use crate::state::{is_valid_variable_name, Jump, NoMatchBehavior, ShellState};
+
+/// Expands a word into the text it represents, removing its quotes and substituting variables and commands
+/// Parts of the word which were single-quoted or escaped are kept exactly as they were written
+// * The word is never split into multiple fields or matched against file names,
+// * which is used for assignments and redirection targets
+pub fn expand_word(word: &Word, shell: &mut ShellState, dispatcher: &Dispatcher) -> Result<String> {
+    let mut fields = Fields::new(false);
+    Expander { shell, dispatcher }.expand_word(word, &mut fields)?;
+    Ok(fields.current.text)
+}
+
+/// Expands a word into a pattern which can be matched against text, such as a 'case' pattern
+/// The word is expanded without splitting, and any characters in it which were quoted are escaped
+/// with a backslash so that they are matched literally
+pub fn expand_pattern_word(
+    word: &Word,
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+) -> Result<String> {
+    let mut fields = Fields::new(false);
+    Expander { shell, dispatcher }.expand_word(word, &mut fields)?;
+    Ok(fields.current.pattern)
+}
+
+/// Expands a word into the fields (separate arguments) it represents
+/// Braces like '{a,b}' are expanded first, producing a separate field for each alternative
+/// The output of a command substitution outside of double quotes is split into separate fields
+/// wherever it contains whitespace, and fields containing unquoted wildcards like '*' are replaced
+/// with the paths of the files they match
+pub fn expand_fields(
+    word: &Word,
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+) -> Result<Vec<String>> {
+    let mut expander = Expander { shell, dispatcher };
+    let mut expanded = Vec::new();
+    for word in expand_braces(word) {
+        let mut fields = Fields::new(true);
+        expander.expand_word(&word, &mut fields)?;
+        for field in fields.finish() {
+            expanded.extend(expander.expand_pattern(field)?);
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// A single field that a word expands into
+#[derive(Default)]
+struct Field {
+    text: String,
+    /// The text of the field as a glob pattern, where quoted characters are escaped with
+    /// a backslash so that they are matched literally
+    pattern: String,
+}
+
+/// The fields that a word expands into, which are built up one piece of text at a time
+struct Fields {
+    /// Whether the output of unquoted command substitutions is split into separate fields
+    split: bool,
+    fields: Vec<Field>,
+    current: Field,
+    /// Whether the current field should be kept, even if it is empty (e.g. after '""')
+    started: bool,
+}
+
+impl Fields {
+    fn new(split: bool) -> Self {
+        Self {
+            split,
+            fields: Vec::new(),
+            current: Field::default(),
+            started: false,
+        }
+    }
+
+    /// Adds text to the current field, where quoted text cannot contain wildcards
+    fn push_str(&mut self, text: &str, quoted: bool) {
+        self.current.text.push_str(text);
+        for character in text.chars() {
+            if quoted && is_pattern_character(character) {
+                self.current.pattern.push(BACKSLASH);
+            }
+
+            self.current.pattern.push(character);
+        }
+
+        self.started = true;
+    }
+
+    /// Adds the output of an unquoted command substitution, splitting it at whitespace
+    // * Whitespace at either end of the output only separates it from the surrounding text,
+    // * so 'a$(echo " b ")c' expands to the three fields 'a', 'b' and 'c'
+    fn push_split(&mut self, text: &str) {
+        if !self.split {
+            return self.push_str(text, false);
+        }
+
+        for (index, piece) in text.split(char::is_whitespace).enumerate() {
+            if index > 0 && self.started {
+                self.fields.push(std::mem::take(&mut self.current));
+                self.started = false;
+            }
+
+            if !piece.is_empty() {
+                self.push_str(piece, false);
+            }
+        }
+    }
+
+    /// Adds each value as a separate quoted field, as '"$@"' does with the positional parameters
+    /// The first and last values are joined to any text before and after them (e.g. '"a$@b"')
+    // * With no values, a field made up of only '"$@"' is removed rather than being left empty
+    fn push_fields(&mut self, values: &[String]) {
+        if !self.split {
+            return self.push_str(&values.join(" "), true);
+        }
+
+        if values.is_empty() && self.current.text.is_empty() {
+            self.started = false;
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                self.fields.push(std::mem::take(&mut self.current));
+            }
+
+            self.push_str(value, true);
+        }
+    }
+
+    fn finish(mut self) -> Vec<Field> {
+        if self.started {
+            self.fields.push(self.current);
+        }
+
+        self.fields
+    }
+}
+
+/// Performs the expansions within words, which may run commands for command substitutions
+struct Expander<'a> {
+    shell: &'a mut ShellState,
+    dispatcher: &'a Dispatcher,
+}
+
+impl Expander<'_> {
+    fn expand_word(&mut self, word: &Word, fields: &mut Fields) -> Result<()> {
+        let mut parts = word.parts.iter();
+        // A '~' at the start of a word is replaced with a directory, such as the home directory
+        if let Some(WordPart::Unquoted(text)) = word.parts.first() {
+            if let Some((directory, rest)) = self.expand_tilde(text, word.parts.len() > 1) {
+                // * The directory is treated as quoted, so that it is not split or globbed
+                fields.push_str(&directory, true);
+                self.expand_text(rest, false, fields)?;
+                parts.next();
+            }
+        }
+
+        for part in parts {
+            match part {
+                WordPart::Unquoted(text) => self.expand_text(text, false, fields)?,
+                // * Quoted text is always kept as a field, even if it expands to nothing
+                WordPart::DoubleQuoted(text) => {
+                    fields.push_str("", true);
+                    self.expand_text(text, true, fields)?
+                }
+                WordPart::Literal(text) => fields.push_str(text, true),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands the '~' prefix of the first part of a word, giving the directory it refers to and
+    /// the rest of the part after the prefix
+    /// The prefix extends up to the first '/', and can be '~' for the home directory, '~user' for
+    /// another user's home directory, '~+' for the current directory, or '~-' for the previous one
+    /// '~-N' and '~+N' refer to the Nth directory back or forward in the directory history
+    // * If the prefix is followed by quoted text (e.g. '~"user"'), it is kept as it is
+    fn expand_tilde<'t>(&self, text: &'t str, has_more_parts: bool) -> Option<(String, &'t str)> {
+        let text = text.strip_prefix(TILDE)?;
+        let (prefix, rest) = match text.find(SLASH) {
+            Some(index) => text.split_at(index),
+            None if has_more_parts => return None,
+            None => (text, ""),
+        };
+
+        let environment = &self.shell.environment;
+        let directory = match prefix {
+            "" => environment.HOME.to_string_lossy().into_owned(),
+            "+" => environment.CWD().to_string(),
+            "-" => environment.backward_directory(1)?.to_string(),
+            _ => {
+                if let Some(count) = prefix.strip_prefix('-').and_then(|n| n.parse().ok()) {
+                    environment.backward_directory(count)?.to_string()
+                } else if let Some(count) = prefix.strip_prefix('+').and_then(|n| n.parse().ok()) {
+                    environment.forward_directory(count)?.to_string()
+                } else {
+                    let user = User::from_name(prefix).ok()??;
+                    user.dir.to_string_lossy().into_owned()
+                }
+            }
+        };
+
+        Some((directory, rest))
+    }
+
+    /// Expands a piece of text without splitting it, such as the default value in '${NAME:-default}'
+    fn expand_string(&mut self, text: &str) -> Result<String> {
+        let mut fields = Fields::new(false);
+        self.expand_text(text, true, &mut fields)?;
+        Ok(fields.current.text)
+    }
+
+    /// Substitutes the values of any variables and the output of any commands referenced in a piece of text
+    /// Unless `quoted` is set, the output of command substitutions may be split into separate fields
+    fn expand_text(&mut self, text: &str, quoted: bool, fields: &mut Fields) -> Result<()> {
+        let mut characters = text.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            let command = match character {
+                BACKTICK => read_backtick_command(&mut characters)?,
+                DOLLAR if characters.peek() == Some(&LEFT_PAREN) => {
+                    characters.next();
+                    let command = read_parenthesized_command(&mut characters)?;
+                    // A '$((...))' expansion is replaced with the result of the arithmetic inside it
+                    if let Some(expression) = arithmetic_expression(&command) {
+                        let result = self.expand_arithmetic(expression)?;
+                        fields.push_str(&result, quoted);
+                        continue;
+                    }
+
+                    command
+                }
+                // '"$@"' keeps each positional parameter as a separate field, whereas '"$*"' joins
+                // them into one, and both are split at whitespace when unquoted
+                DOLLAR if matches!(characters.peek(), Some(&(ALL_ARGUMENTS | STAR))) => {
+                    let arguments = &self.shell.arguments;
+                    match (characters.next(), quoted) {
+                        (Some(ALL_ARGUMENTS), true) => fields.push_fields(arguments),
+                        (_, true) => fields.push_str(&arguments.join(" "), true),
+                        (_, false) => fields.push_split(&arguments.join(" ")),
+                    }
+
+                    continue;
+                }
+                DOLLAR => {
+                    let value = self.expand_dollar(&mut characters)?;
+                    fields.push_str(&value, quoted);
+                    continue;
+                }
+                _ => {
+                    fields.push_str(character.encode_utf8(&mut [0; 4]), quoted);
+                    continue;
+                }
+            };
+
+            let output = self.dispatcher.capture(self.shell, &command)?;
+            match quoted {
+                true => fields.push_str(&output, true),
+                false => fields.push_split(&output),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a field containing a glob pattern with the paths of the files that it matches
+    /// If no files match, the field is kept, removed or reported as an error depending on the configuration
+    fn expand_pattern(&self, field: Field) -> Result<Vec<String>> {
+        let options = GlobOptions {
+            dotfiles: self.shell.config.glob_dotfiles,
+            extended: self.shell.config.extglob,
+        };
+
+        match expand_glob(&field.pattern, options) {
+            None => Ok(vec![field.text]),
+            Some(paths) if !paths.is_empty() => Ok(paths),
+            Some(_) => match self.shell.config.glob_no_match {
+                NoMatchBehavior::Pass => Ok(vec![field.text]),
+                NoMatchBehavior::Empty => Ok(Vec::new()),
+                NoMatchBehavior::Error => Err(dispatch_err!(NoGlobMatches: field.text)),
+            },
+        }
+    }
+
+    /// Evaluates the expression of a '$((...))' expansion, after expanding any variables and
+    /// commands inside of it
+    fn expand_arithmetic(&mut self, expression: &str) -> Result<String> {
+        let expression = self.expand_string(expression)?;
+        let result = evaluate(&expression, |name| get_parameter(name, self.shell))?;
+        Ok(result.to_string())
+    }
+
+    /// Expands a variable reference, after the '$' which begins it
+    fn expand_dollar(&mut self, characters: &mut Peekable<Chars>) -> Result<String> {
+        match characters.peek() {
+            Some(&LEFT_BRACE) => {
+                characters.next();
+                let expression = read_braced_expression(characters)?;
+                self.expand_parameter(&expression)
+            }
+            Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = read_name(characters);
+                Ok(get_parameter(&name, self.shell).unwrap_or_default())
+            }
+            // * Only a single digit is read, so '$10' is '$1' followed by a '0', as in other shells
+            Some(&c) if c.is_ascii_digit() || c == LAST_STATUS || c == HASH => {
+                characters.next();
+                Ok(get_parameter(&c.to_string(), self.shell).unwrap_or_default())
+            }
+            // A '$' which does not begin an expansion is kept as-is
+            _ => Ok(DOLLAR.to_string()),
+        }
+    }
+
+    /// Performs a '${...}' expansion, given the expression between its braces
+    /// Supports '${NAME}', '${?}', '${NAME:-default}', '${NAME:?error}' and '${#NAME}'
+    fn expand_parameter(&mut self, expression: &str) -> Result<String> {
+        let bad_substitution = || dispatch_err!(BadSubstitution: format!("${{{}}}", expression));
+
+        // '${#NAME}' expands to the length of the variable's value, and '${#}' is the same as '$#'
+        if let Some(name) = expression
+            .strip_prefix(HASH)
+            .filter(|name| !name.is_empty())
+        {
+            if !is_parameter_name(name) {
+                return Err(bad_substitution());
+            }
+
+            let value = get_parameter(name, self.shell).unwrap_or_default();
+            return Ok(value.chars().count().to_string());
+        }
+
+        let name_length = match expression.starts_with([LAST_STATUS, HASH, ALL_ARGUMENTS, STAR]) {
+            true => 1,
+            false => expression
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(expression.len()),
+        };
+        let (name, operation) = expression.split_at(name_length);
+        if !is_parameter_name(name) {
+            return Err(bad_substitution());
+        }
+
+        // * Both of the operators treat an empty variable the same as an unset one
+        let value = get_parameter(name, self.shell).filter(|value| !value.is_empty());
+
+        if operation.is_empty() {
+            Ok(value.unwrap_or_default())
+        } else if let Some(default) = operation.strip_prefix(":-") {
+            match value {
+                Some(value) => Ok(value),
+                None => self.expand_string(default),
+            }
+        } else if let Some(message) = operation.strip_prefix(":?") {
+            match value {
+                Some(value) => Ok(value),
+                None => {
+                    // * As in other shells, a non-interactive shell stops running its script
+                    if !self.shell.interactive {
+                        self.shell.jump = Some(Jump::Exit(1));
+                    }
+
+                    let error = dispatch_err!(UnsetVariable: name);
+                    let message = self.expand_string(message)?;
+                    match message.is_empty() {
+                        true => Err(error),
+                        false => Err(error.set_context(&format!("{}: {}", name, message))),
+                    }
+                }
+            }
+        } else {
+            Err(bad_substitution())
+        }
+    }
+}
+
+/// Gets the value of a variable, or of one of the special parameters which the shell provides
+/// '$?' is the exit code of the last command, '$PIPESTATUS' holds the exit codes of each command
+/// in the last pipeline, and '$CMD_DURATION' is how long the last command took in milliseconds
+/// '$0' is the name of the shell or script, '$1', '$2' and so on are the positional parameters,
+/// '$#' is the number of them, and '$@' and '$*' are all of them joined by spaces
+fn get_parameter(name: &str, shell: &ShellState) -> Option<String> {
+    match name {
+        "?" => Some(shell.last_status.code.to_string()),
+        "#" => Some(shell.arguments.len().to_string()),
+        "@" | "*" => Some(shell.arguments.join(" ")),
+        "0" => Some(shell.name.clone()),
+        _ if is_positional_parameter(name) => {
+            let index = name.parse::<usize>().ok()?.checked_sub(1)?;
+            shell.arguments.get(index).cloned()
+        }
+        "PIPESTATUS" => Some(
+            shell
+                .pipeline_status
+                .iter()
+                .map(|status| status.code.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+        ),
+        "CMD_DURATION" => Some(shell.last_status.duration.as_millis().to_string()),
+        _ => shell.environment.get_variable(name),
+    }
+}
+
+/// Checks whether a name can be used in a '${...}' expansion
+fn is_parameter_name(name: &str) -> bool {
+    matches!(name, "?" | "#" | "@" | "*")
+        || is_positional_parameter(name)
+        || is_valid_variable_name(name)
+}
+
+/// Checks whether a name refers to a positional parameter, such as the '1' in '${1}'
+fn is_positional_parameter(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Reads a variable name, consisting of letters, digits and underscores
+fn read_name(characters: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = characters.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '_') {
+            break;
+        }
+
+        name.push(c);
+        characters.next();
+    }
+
+    name
+}
+
+/// Reads the text between the braces of a '${...}' expansion, after the opening brace
+fn read_braced_expression(characters: &mut Peekable<Chars>) -> Result<String> {
+    let mut expression = String::new();
+    let mut depth = 0;
+
+    for character in characters.by_ref() {
+        match character {
+            RIGHT_BRACE if depth == 0 => return Ok(expression),
+            RIGHT_BRACE => depth -= 1,
+            LEFT_BRACE => depth += 1,
+            _ => (),
+        }
+
+        expression.push(character);
+    }
+
+    Err(dispatch_err!(BadSubstitution: format!("${{{}", expression)))
+}
+
+/// Reads the command of a '$(...)' substitution, after the opening parenthesis
+fn read_parenthesized_command(characters: &mut Peekable<Chars>) -> Result<String> {
+    let mut command = String::new();
+    let mut depth = 0;
+    let (mut in_single_quotes, mut in_double_quotes) = (false, false);
+
+    while let Some(character) = characters.next() {
+        match character {
+            RIGHT_PAREN if depth == 0 && !in_single_quotes && !in_double_quotes => {
+                return Ok(command)
+            }
+            BACKSLASH if !in_single_quotes => {
+                command.push(character);
+                if let Some(escaped) = characters.next() {
+                    command.push(escaped);
+                }
+
+                continue;
+            }
+            SINGLE_QUOTE if !in_double_quotes => in_single_quotes = !in_single_quotes,
+            DOUBLE_QUOTE if !in_single_quotes => in_double_quotes = !in_double_quotes,
+            _ if in_single_quotes || in_double_quotes => (),
+            LEFT_PAREN => depth += 1,
+            RIGHT_PAREN => depth -= 1,
+            _ => (),
+        }
+
+        command.push(character);
+    }
+
+    Err(dispatch_err!(BadSubstitution: format!("$({}", command)))
+}
+
+/// Gets the expression of a '$((...))' expansion, given the text between its outer parentheses
+/// Gives `None` if the text is a command in a subshell instead (e.g. '$((cd dir); ls)')
+fn arithmetic_expression(command: &str) -> Option<&str> {
+    let expression = command
+        .strip_prefix(LEFT_PAREN)?
+        .strip_suffix(RIGHT_PAREN)?;
+    // * The inner parentheses must enclose the whole expression, which is not the case for '(a) (b)'
+    let mut depth = 0;
+    for character in expression.chars() {
+        match character {
+            LEFT_PAREN => depth += 1,
+            RIGHT_PAREN if depth == 0 => return None,
+            RIGHT_PAREN => depth -= 1,
+            _ => (),
+        }
+    }
+
+    Some(expression)
+}
+
+/// Reads the command of a '`...`' substitution, after the opening backtick
+// * Within backticks, a backslash only escapes a backtick, a '$' or another backslash,
+// * which allows backticks to be nested as '`echo \`pwd\``'
+fn read_backtick_command(characters: &mut Peekable<Chars>) -> Result<String> {
+    let mut command = String::new();
+
+    while let Some(character) = characters.next() {
+        match character {
+            BACKTICK => return Ok(command),
+            BACKSLASH => match characters.next() {
+                Some(escaped @ (BACKTICK | DOLLAR | BACKSLASH)) => command.push(escaped),
+                Some(other) => {
+                    command.push(BACKSLASH);
+                    command.push(other);
+                }
+                None => command.push(BACKSLASH),
+            },
+            _ => command.push(character),
+        }
+    }
+
+    Err(dispatch_err!(BadSubstitution: format!("`{}", command)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::tokenizer::{tokenize, Token};
+
+    /// Tokenizes a single word and expands it into fields
+    fn fields(shell: &mut ShellState, input: &str) -> Vec<String> {
+        let tokens = tokenize(input).unwrap().tokens;
+        let [(Token::Word(word), _)] = tokens.as_slice() else {
+            panic!("expected a single word, found {:?}", tokens);
+        };
+
+        expand_fields(word, shell, &Dispatcher::default()).unwrap()
+    }
+
+    #[test]
+    fn only_unquoted_substitutions_are_split() {
+        let mut shell = ShellState::for_tests();
+        shell.environment.set_variable("x", "a b").unwrap();
+
+        assert_eq!(fields(&mut shell, "$(echo a b)"), ["a", "b"]);
+        assert_eq!(fields(&mut shell, "`echo a b`"), ["a", "b"]);
+        assert_eq!(fields(&mut shell, "\"$(echo a b)\""), ["a b"]);
+        assert_eq!(fields(&mut shell, "$x"), ["a b"]);
+    }
+
+    #[test]
+    fn empty_substitutions_are_kept_only_when_quoted() {
+        let mut shell = ShellState::for_tests();
+
+        assert_eq!(fields(&mut shell, "$(true)"), Vec::<String>::new());
+        assert_eq!(fields(&mut shell, "\"$(true)\""), [""]);
+        assert_eq!(fields(&mut shell, "''"), [""]);
+    }
+
+    #[test]
+    fn patterns_are_matched_after_expansion() {
+        let mut shell = ShellState::for_tests();
+        let root = std::env::temp_dir().join(format!("rush-expansion-test-{}", std::process::id()));
+        fs_err::create_dir_all(&root).unwrap();
+        for name in ["a.rs", "b.rs"] {
+            fs_err::write(root.join(name), "").unwrap();
+        }
+
+        let root = root.display().to_string();
+        shell.environment.set_variable("dir", &root).unwrap();
+        let unquoted = fields(&mut shell, "$dir/*.rs");
+        let quoted = fields(&mut shell, "\"$dir/*.rs\"");
+        fs_err::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            unquoted,
+            [format!("{}/a.rs", root), format!("{}/b.rs", root)]
+        );
+        assert_eq!(quoted, [format!("{}/*.rs", root)]);
+    }
+
+    #[test]
+    fn tilde_expands_to_home_at_start_of_word() {
+        let mut shell = ShellState::for_tests();
+        let home = shell.environment.HOME.to_string_lossy().into_owned();
+
+        assert_eq!(fields(&mut shell, "~"), [home.as_str()]);
+        assert_eq!(fields(&mut shell, "~/src"), [format!("{}/src", home)]);
+        assert_eq!(fields(&mut shell, "'~'"), ["~"]);
+        assert_eq!(fields(&mut shell, "\\~"), ["~"]);
+        assert_eq!(fields(&mut shell, "a~"), ["a~"]);
+    }
+
+    #[test]
+    fn braces_expand_before_parameters() {
+        let mut shell = ShellState::for_tests();
+        shell.environment.set_variable("x", "1").unwrap();
+        shell.environment.set_variable("list", "{a,b}").unwrap();
+
+        assert_eq!(fields(&mut shell, "{a,b}$x"), ["a1", "b1"]);
+        assert_eq!(fields(&mut shell, "$list"), ["{a,b}"]);
+        assert_eq!(fields(&mut shell, "~/{a,b}").len(), 2);
+    }
+
+    #[test]
+    fn arithmetic_expands_within_word() {
+        let mut shell = ShellState::for_tests();
+        shell.environment.set_variable("x", "4").unwrap();
+
+        assert_eq!(fields(&mut shell, "$((1 + 2))x"), ["3x"]);
+        assert_eq!(fields(&mut shell, "$(($x * 2))"), ["8"]);
+        assert_eq!(fields(&mut shell, "\"$((x / 3))\""), ["1"]);
+    }
+}
@@ -0,0 +1,560 @@
+use std::path::Path;
+
+use super::symbols::{
+    BACKSLASH, DOT, LEFT_BRACKET, LEFT_PAREN, PIPE, QUESTION, RIGHT_BRACKET, RIGHT_PAREN, SLASH,
+    STAR,
+};
+
+// * Extended patterns are written as one of these characters followed by '(...)'
+const EXTENDED_PREFIXES: [char; 5] = ['?', '*', '+', '@', '!'];
+
+/// Settings which change how patterns are matched against file names
+#[derive(Clone, Copy)]
+pub struct GlobOptions {
+    /// Whether wildcards can match file names beginning with a '.'
+    pub dotfiles: bool,
+    /// Whether extended patterns like '@(a|b)' and '!(*.rs)' are recognized
+    pub extended: bool,
+}
+
+/// A single element of a pattern
+#[derive(Debug)]
+enum Token {
+    Literal(char),
+    /// '?', which matches any single character
+    AnyCharacter,
+    /// '*', which matches any sequence of characters
+    AnySequence,
+    /// '[...]', which matches a single character from (or not from) a set
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    /// One of the extended patterns, such as '+(a|b)'
+    Extended(ExtendedKind, Vec<Vec<Token>>),
+}
+
+/// The ways in which an extended pattern can match its alternatives
+#[derive(Debug, Clone, Copy)]
+enum ExtendedKind {
+    /// '?(...)' matches zero or one of the alternatives
+    ZeroOrOne,
+    /// '*(...)' matches zero or more of the alternatives
+    ZeroOrMore,
+    /// '+(...)' matches one or more of the alternatives
+    OneOrMore,
+    /// '@(...)' matches exactly one of the alternatives
+    ExactlyOne,
+    /// '!(...)' matches anything except one of the alternatives
+    NoneOf,
+}
+
+/// A compiled pattern for a single component of a path, such as '*.rs'
+struct Pattern {
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    fn new(pattern: &str, options: GlobOptions) -> Self {
+        let characters: Vec<char> = pattern.chars().collect();
+        let (tokens, _) = compile(&characters, options, false);
+        Self { tokens }
+    }
+
+    /// Checks whether the pattern contains any wildcards, rather than only literal characters
+    fn is_wildcard(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| !matches!(token, Token::Literal(_)))
+    }
+
+    /// Gives the text that the pattern matches, if it only contains literal characters
+    fn literal(&self) -> String {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Literal(character) => Some(*character),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Checks whether the pattern can match hidden files, which is only the case
+    /// when it begins with a literal '.'
+    fn matches_hidden(&self) -> bool {
+        matches!(self.tokens.first(), Some(Token::Literal(DOT)))
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let characters: Vec<char> = name.chars().collect();
+        matches_tokens(&self.tokens, &characters)
+    }
+}
+
+/// Checks whether a piece of text contains any characters which would make it a pattern
+pub fn is_pattern_character(character: char) -> bool {
+    matches!(
+        character,
+        STAR | QUESTION
+            | LEFT_BRACKET
+            | RIGHT_BRACKET
+            | BACKSLASH
+            | LEFT_PAREN
+            | RIGHT_PAREN
+            | PIPE
+    ) || EXTENDED_PREFIXES.contains(&character)
+}
+
+/// Expands a pattern into the paths of the files that it matches, in sorted order
+/// Quoted characters in the pattern must be escaped with a backslash so they are matched literally
+/// A '**' component matches any number of directories, including none
+/// Gives `None` if the pattern does not contain any wildcards
+pub fn expand_glob(pattern: &str, options: GlobOptions) -> Option<Vec<String>> {
+    let (prefix, rest) = match pattern.strip_prefix(SLASH) {
+        Some(rest) => (SLASH.to_string(), rest),
+        None => (String::new(), pattern),
+    };
+
+    // * A trailing slash only matches directories, which keep the slash in the results
+    let trailing_slash = rest.ends_with(SLASH);
+    let components: Vec<&str> = rest
+        .split(SLASH)
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    let has_wildcards = components
+        .iter()
+        .any(|component| *component == "**" || Pattern::new(component, options).is_wildcard());
+    if !has_wildcards {
+        return None;
+    }
+
+    // Each path is a directory to search in, which ends with a slash unless it is the current one
+    let mut paths = vec![prefix];
+    for (index, component) in components.iter().enumerate() {
+        let is_last = index == components.len() - 1 && !trailing_slash;
+        let mut matches = Vec::new();
+        for path in &paths {
+            expand_component(path, component, is_last, options, &mut matches);
+        }
+
+        paths = matches;
+    }
+
+    paths.sort();
+    paths.dedup();
+    Some(paths)
+}
+
+/// Finds the entries of a directory which match one component of a pattern
+/// Directories are given with a trailing slash unless this is the last component
+fn expand_component(
+    directory: &str,
+    component: &str,
+    is_last: bool,
+    options: GlobOptions,
+    matches: &mut Vec<String>,
+) {
+    if component == "**" {
+        if !is_last {
+            matches.push(directory.to_owned());
+        }
+
+        return walk_directory(directory, is_last, options, matches);
+    }
+
+    let pattern = Pattern::new(component, options);
+    if !pattern.is_wildcard() {
+        let path = format!("{}{}", directory, pattern.literal());
+        match is_last {
+            true if Path::new(&path).symlink_metadata().is_ok() => matches.push(path),
+            false if Path::new(&path).is_dir() => matches.push(format!("{}{}", path, SLASH)),
+            _ => (),
+        }
+
+        return;
+    }
+
+    for (name, is_directory) in read_directory(directory) {
+        let is_hidden = name.starts_with(DOT);
+        if (is_hidden && !options.dotfiles && !pattern.matches_hidden()) || !pattern.matches(&name)
+        {
+            continue;
+        }
+
+        match is_last {
+            true => matches.push(format!("{}{}", directory, name)),
+            false if is_directory => matches.push(format!("{}{}{}", directory, name, SLASH)),
+            false => (),
+        }
+    }
+}
+
+/// Adds every entry below a directory for a '**' component, recursively
+/// If this is the last component, files are included as well as directories
+fn walk_directory(directory: &str, is_last: bool, options: GlobOptions, matches: &mut Vec<String>) {
+    for (name, is_directory) in read_directory(directory) {
+        if name.starts_with(DOT) && !options.dotfiles {
+            continue;
+        }
+
+        let path = format!("{}{}", directory, name);
+        if is_last {
+            matches.push(path.clone());
+        }
+
+        if is_directory {
+            let subdirectory = format!("{}{}", path, SLASH);
+            if !is_last {
+                matches.push(subdirectory.clone());
+            }
+
+            walk_directory(&subdirectory, is_last, options, matches);
+        }
+    }
+}
+
+/// Lists the names of the entries in a directory, along with whether each one is a directory
+/// Entries which cannot be read, or whose names are not valid UTF-8, are skipped
+// * Symbolic links to directories count as directories, but are not followed by '**'
+// * in a way that could loop, since 'DirEntry::file_type' does not follow links
+fn read_directory(directory: &str) -> Vec<(String, bool)> {
+    let directory = match directory.is_empty() {
+        true => ".",
+        false => directory,
+    };
+
+    let Ok(entries) = fs_err::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let is_directory = entry.file_type().ok()?.is_dir();
+            Some((name, is_directory))
+        })
+        .collect()
+}
+
+/// Checks whether a piece of text matches a pattern as a whole, such as a 'case' pattern
+/// Quoted characters in the pattern must be escaped with a backslash so they are matched literally
+// * Unlike file names, the text is not split at '/', and can begin with a '.' matched by a wildcard
+pub fn matches_pattern(pattern: &str, text: &str, options: GlobOptions) -> bool {
+    Pattern::new(pattern, options).matches(text)
+}
+
+/// Compiles the characters of a pattern into tokens
+/// If `in_group` is set, compiling stops at the '|' or ')' which ends the current alternative,
+/// and the number of characters consumed is returned along with the tokens
+fn compile(characters: &[char], options: GlobOptions, in_group: bool) -> (Vec<Token>, usize) {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let next = characters.get(index + 1).copied();
+
+        if in_group && (character == PIPE || character == RIGHT_PAREN) {
+            break;
+        }
+
+        if options.extended && EXTENDED_PREFIXES.contains(&character) && next == Some(LEFT_PAREN) {
+            if let Some((token, length)) = compile_extended(&characters[index..], options) {
+                tokens.push(token);
+                index += length;
+                continue;
+            }
+        }
+
+        index += 1;
+        tokens.push(match character {
+            BACKSLASH => match next {
+                Some(escaped) => {
+                    index += 1;
+                    Token::Literal(escaped)
+                }
+                None => Token::Literal(BACKSLASH),
+            },
+            STAR => Token::AnySequence,
+            QUESTION => Token::AnyCharacter,
+            LEFT_BRACKET => match compile_class(&characters[index..]) {
+                Some((token, length)) => {
+                    index += length;
+                    token
+                }
+                // * A '[' without a closing ']' is matched literally
+                None => Token::Literal(LEFT_BRACKET),
+            },
+            _ => Token::Literal(character),
+        });
+    }
+
+    (tokens, index)
+}
+
+/// Compiles an extended pattern such as '@(a|b)', starting at its prefix character
+/// Gives `None` if the pattern is never closed, in which case it is matched literally
+fn compile_extended(characters: &[char], options: GlobOptions) -> Option<(Token, usize)> {
+    let kind = match characters.first()? {
+        '?' => ExtendedKind::ZeroOrOne,
+        '*' => ExtendedKind::ZeroOrMore,
+        '+' => ExtendedKind::OneOrMore,
+        '@' => ExtendedKind::ExactlyOne,
+        _ => ExtendedKind::NoneOf,
+    };
+
+    // Skip over the prefix and the opening parenthesis
+    let mut index = 2;
+    let mut alternatives = Vec::new();
+    loop {
+        let (tokens, length) = compile(characters.get(index..)?, options, true);
+        alternatives.push(tokens);
+        index += length;
+
+        match characters.get(index)? {
+            &RIGHT_PAREN => return Some((Token::Extended(kind, alternatives), index + 1)),
+            // * The only other character which can end an alternative is '|'
+            _ => index += 1,
+        }
+    }
+}
+
+/// Compiles a bracket expression such as '[a-z]' or '[!0-9]', starting after the '['
+/// Gives `None` if there is no closing ']'
+fn compile_class(characters: &[char]) -> Option<(Token, usize)> {
+    let mut index = 0;
+    let negated = matches!(characters.first(), Some('!' | '^'));
+    if negated {
+        index += 1;
+    }
+
+    let mut ranges = Vec::new();
+    // * A ']' straight after the '[' (or the negation) is part of the set rather than closing it
+    let start = index;
+    loop {
+        let character = *characters.get(index)?;
+        if character == RIGHT_BRACKET && index > start {
+            return Some((Token::Class { negated, ranges }, index + 1));
+        }
+
+        // Named classes like '[:alpha:]' stand for every character of that kind
+        if character == LEFT_BRACKET && characters.get(index + 1) == Some(&':') {
+            let rest: String = characters[index + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                ranges.extend(named_class(&rest[..end]));
+                index += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+
+        let (start_character, length) = match character {
+            BACKSLASH => (*characters.get(index + 1)?, 2),
+            _ => (character, 1),
+        };
+        index += length;
+
+        // A '-' between two characters makes a range, unless it is the last character in the set
+        match (characters.get(index), characters.get(index + 1)) {
+            (Some('-'), Some(&end)) if end != RIGHT_BRACKET => {
+                ranges.push((start_character, end));
+                index += 2;
+            }
+            _ => ranges.push((start_character, start_character)),
+        }
+    }
+}
+
+/// Gives the character ranges of a named class, such as 'alpha' in '[[:alpha:]]'
+fn named_class(name: &str) -> Vec<(char, char)> {
+    match name {
+        "alpha" => vec![('a', 'z'), ('A', 'Z')],
+        "digit" => vec![('0', '9')],
+        "alnum" => vec![('a', 'z'), ('A', 'Z'), ('0', '9')],
+        "upper" => vec![('A', 'Z')],
+        "lower" => vec![('a', 'z')],
+        "space" => vec![(' ', ' '), ('\t', '\r')],
+        "xdigit" => vec![('0', '9'), ('a', 'f'), ('A', 'F')],
+        "punct" => vec![('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+        _ => Vec::new(),
+    }
+}
+
+/// Checks whether a sequence of tokens matches the whole of a piece of text
+fn matches_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+
+    match token {
+        Token::Literal(character) => {
+            text.first() == Some(character) && matches_tokens(rest, &text[1..])
+        }
+        Token::AnyCharacter => !text.is_empty() && matches_tokens(rest, &text[1..]),
+        Token::AnySequence => (0..=text.len()).any(|length| matches_tokens(rest, &text[length..])),
+        Token::Class { negated, ranges } => match text.first() {
+            Some(character) => {
+                let in_class = ranges
+                    .iter()
+                    .any(|(start, end)| (start..=end).contains(&character));
+                in_class != *negated && matches_tokens(rest, &text[1..])
+            }
+            None => false,
+        },
+        // * Every way of splitting the text is tried, since the extended pattern
+        // * could match any amount of it
+        Token::Extended(kind, alternatives) => (0..=text.len()).any(|length| {
+            matches_extended(*kind, alternatives, &text[..length])
+                && matches_tokens(rest, &text[length..])
+        }),
+    }
+}
+
+/// Checks whether an extended pattern matches the whole of a piece of text
+fn matches_extended(kind: ExtendedKind, alternatives: &[Vec<Token>], text: &[char]) -> bool {
+    let matches_one = |text: &[char]| {
+        alternatives
+            .iter()
+            .any(|alternative| matches_tokens(alternative, text))
+    };
+
+    match kind {
+        ExtendedKind::ExactlyOne => matches_one(text),
+        ExtendedKind::ZeroOrOne => text.is_empty() || matches_one(text),
+        ExtendedKind::NoneOf => !matches_one(text),
+        ExtendedKind::ZeroOrMore | ExtendedKind::OneOrMore if text.is_empty() => {
+            matches!(kind, ExtendedKind::ZeroOrMore) || matches_one(text)
+        }
+        // * Each repetition has to consume at least one character, so that this always ends
+        ExtendedKind::ZeroOrMore | ExtendedKind::OneOrMore => (1..=text.len()).any(|length| {
+            matches_one(&text[..length])
+                && (length == text.len()
+                    || matches_extended(ExtendedKind::ZeroOrMore, alternatives, &text[length..]))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: GlobOptions = GlobOptions {
+        dotfiles: false,
+        extended: false,
+    };
+
+    const EXTENDED: GlobOptions = GlobOptions {
+        dotfiles: false,
+        extended: true,
+    };
+
+    #[test]
+    fn wildcards_match_characters() {
+        assert!(matches_pattern("*.rs", "main.rs", BASIC));
+        assert!(!matches_pattern("*.rs", "main.rs.bak", BASIC));
+        assert!(matches_pattern("a?c", "abc", BASIC));
+        assert!(!matches_pattern("a?c", "ac", BASIC));
+        assert!(matches_pattern("*", "", BASIC));
+        assert!(matches_pattern("a*b*c", "aXbYbZc", BASIC));
+    }
+
+    #[test]
+    fn classes_match_single_characters() {
+        assert!(matches_pattern("[a-c]x", "bx", BASIC));
+        assert!(!matches_pattern("[a-c]x", "dx", BASIC));
+        assert!(matches_pattern("[!a-c]x", "dx", BASIC));
+        assert!(matches_pattern("[^a-c]x", "dx", BASIC));
+        assert!(matches_pattern("[[:digit:]][[:alpha:]]", "1a", BASIC));
+        assert!(!matches_pattern("[[:digit:]]", "a", BASIC));
+        assert!(matches_pattern("[]]", "]", BASIC));
+    }
+
+    #[test]
+    fn escaped_characters_match_literally() {
+        assert!(matches_pattern("\\*", "*", BASIC));
+        assert!(!matches_pattern("\\*", "a", BASIC));
+        assert!(matches_pattern("a\\?", "a?", BASIC));
+    }
+
+    #[test]
+    fn extended_patterns_match_alternatives() {
+        assert!(matches_pattern("@(ab|cd).txt", "cd.txt", EXTENDED));
+        assert!(!matches_pattern("@(ab|cd).txt", "abcd.txt", EXTENDED));
+        assert!(matches_pattern("?(x)y", "y", EXTENDED));
+        assert!(matches_pattern("?(x)y", "xy", EXTENDED));
+        assert!(!matches_pattern("?(x)y", "xxy", EXTENDED));
+        assert!(matches_pattern("*(ab)", "", EXTENDED));
+        assert!(matches_pattern("*(ab)", "abab", EXTENDED));
+        assert!(!matches_pattern("+(ab)", "", EXTENDED));
+        assert!(matches_pattern("+(ab|c)", "abcab", EXTENDED));
+        assert!(matches_pattern("!(*.rs)", "main.c", EXTENDED));
+        assert!(!matches_pattern("!(*.rs)", "main.rs", EXTENDED));
+        assert!(matches_pattern("@(a|*(b))c", "bbc", EXTENDED));
+    }
+
+    #[test]
+    fn extended_patterns_need_the_option() {
+        assert!(!matches_pattern("@(a|b)", "a", BASIC));
+        assert!(matches_pattern("@(a|b)", "@(a|b)", BASIC));
+    }
+
+    #[test]
+    fn words_without_wildcards_are_not_globbed() {
+        assert_eq!(expand_glob("plain/path.txt", BASIC), None);
+        assert_eq!(expand_glob("@(a|b)", BASIC), None);
+    }
+
+    #[test]
+    fn files_are_expanded_in_sorted_order() {
+        let root = std::env::temp_dir().join(format!("rush-glob-test-{}", std::process::id()));
+        for path in [
+            "b.rs",
+            "a.rs",
+            "c.txt",
+            ".hidden.rs",
+            "sub/d.rs",
+            "sub/deeper/e.rs",
+        ] {
+            let path = root.join(path);
+            fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+            fs_err::write(path, "").unwrap();
+        }
+
+        let root_text = format!("{}/", root.display());
+        let expand = |pattern: &str, options: GlobOptions| {
+            expand_glob(&format!("{}{}", root_text, pattern), options).map(|paths| {
+                paths
+                    .iter()
+                    .map(|path| path.strip_prefix(&root_text).unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let with_dotfiles = GlobOptions {
+            dotfiles: true,
+            extended: false,
+        };
+        let results = (
+            expand("*.rs", BASIC),
+            expand("*.rs", with_dotfiles),
+            expand(".*.rs", BASIC),
+            expand("*/", BASIC),
+            expand("**/*.rs", BASIC),
+            expand("!(*.rs)", EXTENDED),
+            expand("*.none", BASIC),
+        );
+        fs_err::remove_dir_all(&root).unwrap();
+
+        assert_eq!(results.0.unwrap(), ["a.rs", "b.rs"]);
+        assert_eq!(results.1.unwrap(), [".hidden.rs", "a.rs", "b.rs"]);
+        assert_eq!(results.2.unwrap(), [".hidden.rs"]);
+        assert_eq!(results.3.unwrap(), ["sub/"]);
+        assert_eq!(
+            results.4.unwrap(),
+            ["a.rs", "b.rs", "sub/d.rs", "sub/deeper/e.rs"]
+        );
+        assert_eq!(results.5.unwrap(), ["c.txt", "sub"]);
+        assert_eq!(results.6.unwrap(), Vec::<String>::new());
+    }
+}
@@ -1,7 +1,18 @@
+mod alias;
+pub mod arithmetic;
+mod braces;
+mod completion;
 pub mod dispatcher;
+mod expansion;
+mod glob;
+mod parser;
 pub mod readline;
+mod redirection;
+mod scripting;
 mod symbols;
 mod tokenizer;
 
 pub use dispatcher::Dispatcher;
+pub use parser::Command;
 pub use readline::LineEditor;
+pub use scripting::{is_rhai_script, ScriptEngine};
@@ -0,0 +1,943 @@
+use std::iter::Peekable;
+use std::sync::Arc;
+use std::vec::IntoIter;
+
+use super::symbols::{
+    Symbols, ANDDGREAT, ANDGREAT, AND_IF, BACKGROUND, BEGIN_GROUP, CASE, CLOBBER, DGREAT, DLESS,
+    DLESSDASH, DO, DONE, DSEMI, ELIF, ELSE, END_GROUP, ESAC, FI, FOR, FUNCTION, GREAT, GREATAND,
+    IF, IN, LEFT_PAREN, LESS, LESSAND, LESSGREAT, NEWLINE, OR_IF, PIPELINE, RESERVED_WORDS,
+    RIGHT_PAREN, SEQUENCE, THEN, TLESS, UNTIL, WHILE,
+};
+use super::tokenizer::{tokenize, HereDocument, Token, TokenizedInput, Word, WordPart};
+use crate::errors::{Result, Span};
+use crate::state::is_valid_variable_name;
+
+/// A complete line of input, or the body of a compound command,
+/// made up of and-or lists separated by ';', '&' or newlines
+#[derive(Debug, Clone, Default)]
+pub struct CommandList {
+    pub items: Vec<ListItem>,
+}
+
+/// An and-or list, along with whether it should be run in the background
+#[derive(Debug, Clone)]
+pub struct ListItem {
+    pub and_or: AndOrList,
+    pub background: bool,
+}
+
+/// A chain of pipelines joined by '&&' or '||'
+/// Each pipeline after the first is only run depending on the result of the previous ones
+#[derive(Debug, Clone)]
+pub struct AndOrList {
+    pub first: Pipeline,
+    pub rest: Vec<(LogicalOperator, Pipeline)>,
+}
+
+/// The operator joining two pipelines in an `AndOrList`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOperator {
+    /// '&&': Run the next pipeline only if the previous one succeeded
+    And,
+    /// '||': Run the next pipeline only if the previous one failed
+    Or,
+}
+
+/// A sequence of commands joined by '|', where each command's output is the next one's input
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+}
+
+/// A single command within a pipeline
+#[derive(Debug, Clone)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// A compound command, along with redirections which apply to every command inside of it
+    Compound(CompoundCommand, Vec<Redirection>),
+    /// 'name() compound_command': Defines a function with the given name and body
+    // * The body is shared with the shell's function table, so it is not copied on each call
+    FunctionDefinition(String, Arc<Command>),
+}
+
+impl Command {
+    /// Gets the redirections which apply to the command as a whole
+    pub fn redirections(&self) -> &[Redirection] {
+        match self {
+            Self::Simple(command) => &command.redirections,
+            Self::Compound(_, redirections) => redirections,
+            Self::FunctionDefinition(..) => &[],
+        }
+    }
+}
+
+/// A command which contains other commands, such as a conditional or a loop
+#[derive(Debug, Clone)]
+pub enum CompoundCommand {
+    /// '{ list; }': Runs a list of commands as a group
+    BraceGroup(CommandList),
+    /// 'if list; then list; [elif list; then list;]... [else list;] fi'
+    /// Runs the body of the first branch whose condition succeeds, or the 'else' body if none do
+    If {
+        branches: Vec<(CommandList, CommandList)>,
+        otherwise: Option<CommandList>,
+    },
+    /// 'while list; do list; done' or 'until list; do list; done'
+    /// Runs the body for as long as the condition succeeds, or until it succeeds for 'until'
+    While {
+        condition: CommandList,
+        body: CommandList,
+        until: bool,
+    },
+    /// 'for name [in word...]; do list; done'
+    /// Runs the body once for each field that the words expand to, with the variable set to it
+    /// If there are no words, the body is run for each of the positional parameters instead
+    For {
+        name: String,
+        words: Option<Vec<Word>>,
+        body: CommandList,
+    },
+    /// 'case word in [pattern [| pattern]...) list ;;]... esac'
+    /// Runs the body of the first item with a pattern matching the word
+    Case { word: Word, items: Vec<CaseItem> },
+}
+
+/// A single item of a 'case' command, which is run if any of its patterns match
+#[derive(Debug, Clone)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: CommandList,
+}
+
+/// A single command invocation, consisting of the command name, its arguments and redirections
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
+    /// Variables written before the command name (e.g. 'NAME=value command'),
+    /// which are only set for this command
+    pub assignments: Vec<Assignment>,
+    /// The command name followed by its arguments
+    pub arguments: Vec<Word>,
+    pub redirections: Vec<Redirection>,
+}
+
+impl SimpleCommand {
+    /// Gets the name of the function being defined, if the command so far followed by the given
+    /// word begins a function definition, either as 'name()' or as 'name ()'
+    fn function_name(&self, word: &Word) -> Option<String> {
+        if !self.assignments.is_empty() || !self.redirections.is_empty() {
+            return None;
+        }
+
+        let name = match self.arguments.as_slice() {
+            [] => word.as_unquoted()?.strip_suffix("()")?,
+            [name] if word.as_unquoted() == Some("()") => name.as_unquoted()?,
+            _ => return None,
+        };
+
+        is_valid_function_name(name).then(|| name.to_owned())
+    }
+}
+
+/// A variable assignment of the form 'NAME=value'
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
+}
+
+impl Assignment {
+    /// Interprets a word as an assignment, if it begins with an unquoted valid variable name and '='
+    pub fn from_word(word: &Word) -> Option<Self> {
+        let (first, rest) = word.parts.split_first()?;
+        let WordPart::Unquoted(text) = first else {
+            return None;
+        };
+
+        let (name, value) = text.split_once('=')?;
+        if !is_valid_variable_name(name) {
+            return None;
+        }
+
+        let mut value_parts = vec![WordPart::Unquoted(value.to_owned())];
+        value_parts.extend_from_slice(rest);
+        Some(Self {
+            name: name.to_owned(),
+            value: Word { parts: value_parts },
+        })
+    }
+}
+
+/// A redirection of one of a command's file descriptors
+#[derive(Debug, Clone)]
+pub struct Redirection {
+    /// The file descriptor being redirected, if one was explicitly given (e.g. the '2' in '2>')
+    pub fd: Option<u32>,
+    pub kind: RedirectionKind,
+    /// The file or file descriptor that the redirection refers to,
+    /// or the text to be read for here-documents and here-strings
+    pub target: Word,
+}
+
+/// The type of a `Redirection`, identified by its operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectionKind {
+    /// '<': Read input from a file
+    Input,
+    /// '>': Write output to a file, truncating it
+    Output,
+    /// '>>': Write output to the end of a file
+    Append,
+    /// '>|': Write output to a file, truncating it even if overwriting files is disabled
+    Clobber,
+    /// '<>': Open a file for both reading and writing
+    ReadWrite,
+    /// '<&': Duplicate an input file descriptor
+    DuplicateInput,
+    /// '>&': Duplicate an output file descriptor
+    DuplicateOutput,
+    /// '&>': Write both output and errors to a file, truncating it
+    OutputAndError,
+    /// '&>>': Write both output and errors to the end of a file
+    AppendOutputAndError,
+    /// '<<' and '<<-': Read input from the following lines, up until a delimiter line
+    HereDocument,
+    /// '<<<': Read input from a single word, followed by a newline
+    HereString,
+}
+
+impl RedirectionKind {
+    /// Gets the redirection kind for an operator, without its file descriptor prefix
+    fn from_operator(operator: &str) -> Option<Self> {
+        match operator {
+            LESS => Some(Self::Input),
+            GREAT => Some(Self::Output),
+            DGREAT => Some(Self::Append),
+            CLOBBER => Some(Self::Clobber),
+            LESSGREAT => Some(Self::ReadWrite),
+            LESSAND => Some(Self::DuplicateInput),
+            GREATAND => Some(Self::DuplicateOutput),
+            ANDGREAT => Some(Self::OutputAndError),
+            ANDDGREAT => Some(Self::AppendOutputAndError),
+            DLESS | DLESSDASH => Some(Self::HereDocument),
+            TLESS => Some(Self::HereString),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a tokenized line of input into a `CommandList`
+pub fn parse(input: TokenizedInput) -> Result<CommandList> {
+    let mut parser = Parser::new(input);
+    let list = parser.parse_compound_list(&[])?;
+    // * A list only stops early at a token which cannot follow it, such as a stray ';;'
+    match parser.tokens.next() {
+        Some((token, span)) => Err(parse_err!(UnexpectedToken: token.to_string(), span)),
+        None => Ok(list),
+    }
+}
+
+/// Checks whether a line of input is complete, or whether it needs more lines to be added to it,
+/// such as when a quote has not been closed or the line ends with a '|'
+// * Input with any other error is complete, so that the error is reported when it is run
+pub fn is_complete(line: &str) -> bool {
+    match tokenize(line) {
+        // Brackets and here-documents continue until they are closed by a later line
+        Ok(input)
+            if input.unclosed > 0
+                || input
+                    .here_documents
+                    .iter()
+                    .any(|document| !document.terminated) =>
+        {
+            false
+        }
+        Ok(input) => !parse(input).is_err_and(|error| error.is_incomplete_input()),
+        Err(error) => !error.is_incomplete_input(),
+    }
+}
+
+/// Checks whether a word can be used as the name of a function
+// * Function names are more permissive than variable names, so that names like 'git-log' work
+fn is_valid_function_name(name: &str) -> bool {
+    !name.is_empty()
+        && !RESERVED_WORDS.contains(&name)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+}
+
+/// Recursive descent parser over a stream of tokens
+struct Parser {
+    tokens: Peekable<IntoIter<(Token, Span)>>,
+    /// Here-document bodies, which are taken in order as each here-document operator is parsed
+    here_documents: IntoIter<HereDocument>,
+    /// The most recent operator joining two commands, which is reported if the second is missing
+    last_operator: Option<(String, Span)>,
+}
+
+impl Parser {
+    fn new(input: TokenizedInput) -> Self {
+        Self {
+            tokens: input.tokens.into_iter().peekable(),
+            here_documents: input.here_documents.into_iter(),
+            last_operator: None,
+        }
+    }
+
+    /// Consumes the next token if it is equal to the given operator
+    fn accept(&mut self, operator: &str) -> bool {
+        match self
+            .tokens
+            .next_if(|(token, _)| matches!(token, Token::Operator(token) if token == operator))
+        {
+            Some((_, span)) => {
+                self.last_operator = Some((operator.to_owned(), span));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consumes any newlines, which may appear between commands and after binary operators
+    fn skip_newlines(&mut self) {
+        while self.accept(NEWLINE) {}
+    }
+
+    /// Gets the next token if it is a reserved word, along with its span
+    // * Only unquoted words are reserved, so that a word like 'if' can be passed as "if"
+    fn peek_reserved(&mut self) -> Option<(&'static str, Span)> {
+        match self.tokens.peek()? {
+            (Token::Word(word), span) => {
+                let text = word.as_unquoted()?;
+                let reserved = RESERVED_WORDS.iter().find(|reserved| **reserved == text)?;
+                Some((reserved, span.clone()))
+            }
+            (Token::Operator(_), _) => None,
+        }
+    }
+
+    /// Consumes the next token if it is the given unquoted word, giving its span
+    fn accept_word(&mut self, expected: &str) -> Option<Span> {
+        self.tokens
+            .next_if(|(token, _)| match token {
+                Token::Word(word) => word.as_unquoted() == Some(expected),
+                Token::Operator(_) => false,
+            })
+            .map(|(_, span)| span)
+    }
+
+    /// Consumes a reserved word which must come next in a compound command
+    /// `opener` is the reserved word which began the compound command, and is reported along with
+    /// its span if the input ends first
+    fn expect_word(&mut self, expected: &str, opener: &str, opener_span: &Span) -> Result<Span> {
+        if let Some(span) = self.accept_word(expected) {
+            return Ok(span);
+        }
+
+        match self.tokens.next() {
+            Some((token, span)) => Err(parse_err!(UnexpectedToken: token.to_string(), span)),
+            None => Err(parse_err!(MissingKeyword: expected, opener, opener_span)),
+        }
+    }
+
+    /// Checks whether a compound list ends at the next token, which is the case for one of the
+    /// given reserved words, a ';;' which ends a case item, or the end of the input
+    fn at_list_end(&mut self, terminators: &[&str]) -> bool {
+        match self.tokens.peek() {
+            None => true,
+            Some((Token::Operator(operator), _)) => operator == DSEMI,
+            Some((Token::Word(word), _)) => word
+                .as_unquoted()
+                .is_some_and(|text| terminators.contains(&text)),
+        }
+    }
+
+    /// compound_list: and_or ((';' | '&' | newline) and_or)* (';' | '&' | newline)?
+    /// The list ends before one of the given reserved words, such as the 'fi' after an 'if' body
+    fn parse_compound_list(&mut self, terminators: &[&str]) -> Result<CommandList> {
+        let mut list = CommandList::default();
+
+        self.skip_newlines();
+        while !self.at_list_end(terminators) {
+            let and_or = self.parse_and_or()?;
+            let background = if self.accept(BACKGROUND) {
+                true
+            } else if self.accept(SEQUENCE) || self.accept(NEWLINE) || self.at_list_end(terminators)
+            {
+                false
+            } else if let Some((token, span)) = self.tokens.next() {
+                // Anything other than a separator after a complete and-or list is out of place
+                return Err(parse_err!(UnexpectedToken: token.to_string(), span));
+            } else {
+                false
+            };
+
+            list.items.push(ListItem { and_or, background });
+            self.skip_newlines();
+        }
+
+        Ok(list)
+    }
+
+    /// Parses a compound list which must contain at least one command, such as a loop's body
+    fn parse_required_list(&mut self, terminators: &[&str]) -> Result<CommandList> {
+        let list = self.parse_compound_list(terminators)?;
+        // * If the input has ended instead, the missing reserved word after the list is reported
+        if list.items.is_empty() {
+            if let Some((token, span)) = self.tokens.next() {
+                return Err(parse_err!(UnexpectedToken: token.to_string(), span));
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// and_or: pipeline (('&&' | '||') pipeline)*
+    fn parse_and_or(&mut self) -> Result<AndOrList> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let operator = if self.accept(AND_IF) {
+                LogicalOperator::And
+            } else if self.accept(OR_IF) {
+                LogicalOperator::Or
+            } else {
+                break;
+            };
+
+            self.skip_newlines();
+            rest.push((operator, self.parse_pipeline()?));
+        }
+
+        Ok(AndOrList { first, rest })
+    }
+
+    /// pipeline: command ('|' command)*
+    fn parse_pipeline(&mut self) -> Result<Pipeline> {
+        let mut commands = vec![self.parse_command()?];
+        while self.accept(PIPELINE) {
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline { commands })
+    }
+
+    /// command: compound_command redirection* | function_definition | simple_command
+    fn parse_command(&mut self) -> Result<Command> {
+        let Some((reserved, span)) = self.peek_reserved() else {
+            return self.parse_simple_command();
+        };
+
+        self.tokens.next();
+        let compound = match reserved {
+            IF => self.parse_if(span)?,
+            WHILE => self.parse_while(false, span)?,
+            UNTIL => self.parse_while(true, span)?,
+            FOR => self.parse_for(span)?,
+            CASE => self.parse_case(span)?,
+            BEGIN_GROUP => {
+                let list = self.parse_required_list(&[END_GROUP])?;
+                self.expect_word(END_GROUP, BEGIN_GROUP, &span)?;
+                CompoundCommand::BraceGroup(list)
+            }
+            FUNCTION => return self.parse_function_keyword(span),
+            // Reserved words which continue or end a compound command cannot begin a command
+            _ => return Err(parse_err!(UnexpectedToken: reserved, span)),
+        };
+
+        let mut redirections = Vec::new();
+        while let Some((token, span)) = self.tokens.next_if(|(token, _)| match token {
+            Token::Operator(operator) => Symbols::is_redirection(operator),
+            Token::Word(_) => false,
+        }) {
+            redirections.push(self.parse_redirection(&token.to_string(), span)?);
+        }
+
+        Ok(Command::Compound(compound, redirections))
+    }
+
+    /// simple_command: (assignment | redirection)* (word | redirection)*
+    fn parse_simple_command(&mut self) -> Result<Command> {
+        let mut command = SimpleCommand::default();
+
+        while let Some((token, span)) = self.tokens.next_if(|(token, _)| match token {
+            Token::Operator(operator) => Symbols::is_redirection(operator),
+            Token::Word(_) => true,
+        }) {
+            match token {
+                Token::Operator(operator) => command
+                    .redirections
+                    .push(self.parse_redirection(&operator, span)?),
+                Token::Word(word) => {
+                    if let Some(name) = command.function_name(&word) {
+                        return self.parse_function_body(name, span);
+                    }
+
+                    // Assignments are only recognized before the command name
+                    match Assignment::from_word(&word) {
+                        Some(assignment) if command.arguments.is_empty() => {
+                            command.assignments.push(assignment)
+                        }
+                        _ => command.arguments.push(word),
+                    }
+                }
+            }
+        }
+
+        // A command must contain at least one word, assignment or redirection
+        if command.arguments.is_empty()
+            && command.assignments.is_empty()
+            && command.redirections.is_empty()
+        {
+            return match (self.tokens.next(), self.last_operator.take()) {
+                (Some((token, span)), _) => {
+                    Err(parse_err!(UnexpectedToken: token.to_string(), span))
+                }
+                (None, Some((operator, span))) => Err(parse_err!(MissingCommand: operator, span)),
+                (None, None) => Err(parse_err!(UnexpectedEndOfInput: Span::default())),
+            };
+        }
+
+        Ok(Command::Simple(command))
+    }
+
+    /// if_clause: 'if' list 'then' list ('elif' list 'then' list)* ('else' list)? 'fi'
+    fn parse_if(&mut self, span: Span) -> Result<CompoundCommand> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.parse_required_list(&[THEN])?;
+            self.expect_word(THEN, IF, &span)?;
+            let body = self.parse_required_list(&[ELIF, ELSE, FI])?;
+            branches.push((condition, body));
+
+            if self.accept_word(ELIF).is_none() {
+                break;
+            }
+        }
+
+        let otherwise = match self.accept_word(ELSE) {
+            Some(_) => Some(self.parse_required_list(&[FI])?),
+            None => None,
+        };
+
+        self.expect_word(FI, IF, &span)?;
+        Ok(CompoundCommand::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// while_clause: ('while' | 'until') list do_group
+    fn parse_while(&mut self, until: bool, span: Span) -> Result<CompoundCommand> {
+        let opener = if until { UNTIL } else { WHILE };
+        let condition = self.parse_required_list(&[DO])?;
+        let body = self.parse_do_group(opener, &span)?;
+        Ok(CompoundCommand::While {
+            condition,
+            body,
+            until,
+        })
+    }
+
+    /// for_clause: 'for' name (newline* 'in' word* (';' | newline))? newline* do_group
+    fn parse_for(&mut self, span: Span) -> Result<CompoundCommand> {
+        let name = match self.tokens.next() {
+            Some((Token::Word(word), _))
+                if word.as_unquoted().is_some_and(is_valid_variable_name) =>
+            {
+                word.to_string()
+            }
+            Some((token, span)) => {
+                return Err(parse_err!(UnexpectedToken: token.to_string(), span))
+            }
+            None => return Err(parse_err!(MissingKeyword: DO, FOR, span)),
+        };
+
+        self.skip_newlines();
+        let words = match self.accept_word(IN) {
+            Some(_) => {
+                let mut words = Vec::new();
+                while let Some((Token::Word(word), _)) = self
+                    .tokens
+                    .next_if(|(token, _)| matches!(token, Token::Word(_)))
+                {
+                    words.push(word);
+                }
+
+                Some(words)
+            }
+            None => None,
+        };
+
+        self.accept(SEQUENCE);
+        self.skip_newlines();
+        let body = self.parse_do_group(FOR, &span)?;
+        Ok(CompoundCommand::For { name, words, body })
+    }
+
+    /// do_group: 'do' list 'done'
+    fn parse_do_group(&mut self, opener: &str, span: &Span) -> Result<CommandList> {
+        self.expect_word(DO, opener, span)?;
+        let body = self.parse_required_list(&[DONE])?;
+        self.expect_word(DONE, opener, span)?;
+        Ok(body)
+    }
+
+    /// case_clause: 'case' word newline* 'in' newline* (case_item ';;' newline*)* case_item? 'esac'
+    /// case_item: case_patterns list
+    fn parse_case(&mut self, span: Span) -> Result<CompoundCommand> {
+        let word = match self.tokens.next() {
+            Some((Token::Word(word), _)) => word,
+            Some((token, span)) => {
+                return Err(parse_err!(UnexpectedToken: token.to_string(), span))
+            }
+            None => return Err(parse_err!(MissingKeyword: IN, CASE, span)),
+        };
+
+        self.skip_newlines();
+        self.expect_word(IN, CASE, &span)?;
+        self.skip_newlines();
+
+        let mut items = Vec::new();
+        while self.accept_word(ESAC).is_none() {
+            let patterns = self.parse_case_patterns(&span)?;
+            let body = self.parse_compound_list(&[ESAC])?;
+            items.push(CaseItem { patterns, body });
+
+            // * Every item ends with ';;', except for the last one, which can end with 'esac'
+            if !self.accept(DSEMI) {
+                self.expect_word(ESAC, CASE, &span)?;
+                break;
+            }
+
+            self.skip_newlines();
+        }
+
+        Ok(CompoundCommand::Case { word, items })
+    }
+
+    /// case_patterns: '('? word ('|' word)* ')'
+    // * Parentheses are not operators, so the ')' is usually part of the last pattern (e.g. 'a|b)')
+    fn parse_case_patterns(&mut self, case_span: &Span) -> Result<Vec<Word>> {
+        let mut patterns = Vec::new();
+        loop {
+            let (mut pattern, span) = match self.tokens.next() {
+                Some((Token::Word(word), span)) => (word, span),
+                Some((token, span)) => {
+                    return Err(parse_err!(UnexpectedToken: token.to_string(), span))
+                }
+                None => return Err(parse_err!(MissingKeyword: ESAC, CASE, case_span)),
+            };
+
+            if patterns.is_empty() {
+                pattern.strip_unquoted_prefix(LEFT_PAREN);
+            }
+
+            let closed = pattern.strip_unquoted_suffix(RIGHT_PAREN);
+            if pattern.is_empty() {
+                return Err(parse_err!(UnexpectedToken: RIGHT_PAREN.to_string(), span));
+            }
+
+            patterns.push(pattern);
+            if closed || self.accept_word(&RIGHT_PAREN.to_string()).is_some() {
+                return Ok(patterns);
+            }
+
+            if !self.accept(PIPELINE) {
+                return match self.tokens.next() {
+                    Some((token, span)) => {
+                        Err(parse_err!(UnexpectedToken: token.to_string(), span))
+                    }
+                    None => Err(parse_err!(MissingKeyword: ESAC, CASE, case_span)),
+                };
+            }
+        }
+    }
+
+    /// function_definition: 'function' name ('()')? function_body
+    fn parse_function_keyword(&mut self, span: Span) -> Result<Command> {
+        let name = match self.tokens.next() {
+            Some((Token::Word(word), _)) => word
+                .as_unquoted()
+                .map(|name| name.strip_suffix("()").unwrap_or(name))
+                .filter(|name| is_valid_function_name(name))
+                .map(str::to_owned)
+                .ok_or_else(|| parse_err!(UnexpectedToken: word.to_string(), span.clone()))?,
+            Some((token, span)) => {
+                return Err(parse_err!(UnexpectedToken: token.to_string(), span))
+            }
+            None => return Err(parse_err!(MissingCommand: FUNCTION, span)),
+        };
+
+        let span = self.accept_word("()").unwrap_or(span);
+        self.parse_function_body(name, span)
+    }
+
+    /// function_body: newline* compound_command redirection*
+    /// `span` is the span of the word before the body, which is reported if the body is missing
+    fn parse_function_body(&mut self, name: String, span: Span) -> Result<Command> {
+        self.skip_newlines();
+        match self.peek_reserved() {
+            Some((IF | WHILE | UNTIL | FOR | CASE | BEGIN_GROUP, _)) => (),
+            _ => {
+                return match self.tokens.next() {
+                    Some((token, span)) => {
+                        Err(parse_err!(UnexpectedToken: token.to_string(), span))
+                    }
+                    None => Err(parse_err!(MissingCommand: "()", span)),
+                }
+            }
+        }
+
+        let body = self.parse_command()?;
+        Ok(Command::FunctionDefinition(name, Arc::new(body)))
+    }
+
+    /// redirection: fd? operator word
+    fn parse_redirection(&mut self, token: &str, span: Span) -> Result<Redirection> {
+        let operator = token.trim_start_matches(|c: char| c.is_ascii_digit());
+        let fd = token[..token.len() - operator.len()].parse::<u32>().ok();
+        let kind = RedirectionKind::from_operator(operator)
+            .ok_or_else(|| parse_err!(UnexpectedToken: token, span))?;
+
+        // The redirection target must be a word, not another operator
+        let target = match self
+            .tokens
+            .next_if(|(token, _)| matches!(token, Token::Word(_)))
+        {
+            Some((Token::Word(target), _)) => target,
+            _ => return Err(parse_err!(MissingRedirectionTarget: token, span)),
+        };
+
+        // * The target of a here-document operator is its delimiter,
+        // * so the body that the tokenizer read for it is used instead
+        let target = match kind {
+            RedirectionKind::HereDocument => {
+                self.here_documents
+                    .next()
+                    .ok_or_else(|| parse_err!(UnexpectedEndOfInput: span))?
+                    .body
+            }
+            _ => target,
+        };
+
+        Ok(Redirection { fd, kind, target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::tokenizer::tokenize;
+
+    fn parse_line(line: &str) -> Result<CommandList> {
+        parse(tokenize(line)?)
+    }
+
+    /// Parses a line which should fail, returning its error message along with the diagnostic
+    fn error_with_source(line: &str) -> String {
+        parse_line(line).unwrap_err().with_source(line).to_string()
+    }
+
+    fn simple(command: &Command) -> &SimpleCommand {
+        match command {
+            Command::Simple(command) => command,
+            _ => panic!("expected a simple command, found {:?}", command),
+        }
+    }
+
+    fn arguments(command: &SimpleCommand) -> Vec<String> {
+        command.arguments.iter().map(Word::to_string).collect()
+    }
+
+    #[test]
+    fn list_is_split_into_and_or_lists_and_pipelines() {
+        let list = parse_line("a | b && c || d; e &").unwrap();
+        assert_eq!(list.items.len(), 2);
+
+        let first = &list.items[0];
+        assert!(!first.background);
+        assert_eq!(first.and_or.first.commands.len(), 2);
+        assert_eq!(arguments(simple(&first.and_or.first.commands[1])), ["b"]);
+        let operators: Vec<_> = first.and_or.rest.iter().map(|(op, _)| *op).collect();
+        assert_eq!(operators, [LogicalOperator::And, LogicalOperator::Or]);
+
+        let second = &list.items[1];
+        assert!(second.background);
+        assert_eq!(arguments(simple(&second.and_or.first.commands[0])), ["e"]);
+    }
+
+    #[test]
+    fn assignments_and_redirections_are_separated_from_arguments() {
+        let list = parse_line("A=1 cmd x 2>err >>out y").unwrap();
+        let command = simple(&list.items[0].and_or.first.commands[0]);
+        assert_eq!(command.assignments.len(), 1);
+        assert_eq!(command.assignments[0].name, "A");
+        assert_eq!(arguments(command), ["cmd", "x", "y"]);
+
+        let redirections: Vec<_> = command
+            .redirections
+            .iter()
+            .map(|redirection| (redirection.fd, redirection.kind))
+            .collect();
+        assert_eq!(
+            redirections,
+            [
+                (Some(2), RedirectionKind::Output),
+                (None, RedirectionKind::Append)
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_commands_are_errors() {
+        for line in ["| wc", "ls | | wc", "ls &&", "cat >"] {
+            assert!(parse_line(line).is_err(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn error_spans_point_at_the_offending_token() {
+        assert_eq!(
+            error_with_source("ls | | wc"),
+            "Unexpected token '|'\n  ls | | wc\n       ^"
+        );
+        assert_eq!(
+            error_with_source("echo a;; echo b"),
+            "Unexpected token ';;'\n  echo a;; echo b\n        ^^"
+        );
+        assert_eq!(
+            error_with_source("cat >"),
+            "Expected a file name after '>'\n  cat >\n      ^"
+        );
+    }
+
+    #[test]
+    fn error_spans_point_at_the_line_containing_them() {
+        assert_eq!(
+            error_with_source("echo a\n\tls && ;"),
+            "Unexpected token ';'\n  \tls && ;\n  \t      ^"
+        );
+    }
+
+    #[test]
+    fn unterminated_quote_points_at_the_opening_quote() {
+        assert_eq!(
+            error_with_source("echo 'abc"),
+            "Unterminated quote (')\n  echo 'abc\n       ^"
+        );
+    }
+
+    #[test]
+    fn trailing_operators_are_incomplete_input() {
+        for line in ["ls |", "true &&", "false ||", "ls |\n"] {
+            let error = parse_line(line).unwrap_err();
+            assert!(error.is_incomplete_input(), "{:?}", line);
+        }
+
+        // Errors which more input cannot fix are reported rather than waited on
+        for line in ["ls | | wc", "; ls", "cat > | wc"] {
+            let error = parse_line(line).unwrap_err();
+            assert!(!error.is_incomplete_input(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn comments_do_not_become_commands() {
+        let list = parse_line("# only a comment").unwrap();
+        assert!(list.items.is_empty());
+
+        let list = parse_line("echo a # ; echo b\necho c").unwrap();
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(
+            arguments(simple(&list.items[0].and_or.first.commands[0])),
+            ["echo", "a"]
+        );
+
+        // * An operator inside of a comment does not leave the line incomplete
+        assert!(parse_line("ls # |").is_ok());
+    }
+
+    #[test]
+    fn unfinished_lines_are_incomplete() {
+        for line in [
+            "echo 'abc",
+            "echo \"abc",
+            "echo abc \\",
+            "ls |",
+            "true &&",
+            "if true; then",
+            "while true; do echo",
+            "for x in a b",
+            "case x in",
+            "{ echo",
+            "echo $(ls",
+            "cat <<EOF\nline",
+        ] {
+            assert!(!is_complete(line), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn finished_or_invalid_lines_are_complete() {
+        for line in [
+            "echo abc",
+            "echo 'a|b'",
+            "ls | wc",
+            "if true; then echo; fi",
+            "cat <<EOF\nline\nEOF",
+            "# trailing comment |",
+            // Errors which more input cannot fix are reported rather than waited on
+            "ls | | wc",
+            "fi",
+        ] {
+            assert!(is_complete(line), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn compound_commands_and_functions_are_parsed() {
+        let list = parse_line("if a; then b; elif c; then d; else e; fi").unwrap();
+        let Command::Compound(
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            },
+            _,
+        ) = &list.items[0].and_or.first.commands[0]
+        else {
+            panic!("expected an if command");
+        };
+        assert_eq!(branches.len(), 2);
+        assert!(otherwise.is_some());
+
+        let list = parse_line("greet() { echo hi; }").unwrap();
+        let Command::FunctionDefinition(name, body) = &list.items[0].and_or.first.commands[0]
+        else {
+            panic!("expected a function definition");
+        };
+        assert_eq!(name, "greet");
+        assert!(matches!(
+            body.as_ref(),
+            Command::Compound(CompoundCommand::BraceGroup(_), _)
+        ));
+    }
+
+    #[test]
+    fn unfinished_compound_commands_are_incomplete_input() {
+        for line in [
+            "if true; then",
+            "while true; do echo",
+            "for x in a b",
+            "case x in",
+            "{ echo",
+        ] {
+            let error = parse_line(line).unwrap_err();
+            assert!(error.is_incomplete_input(), "{:?}", line);
+        }
+
+        assert!(!parse_line("fi").unwrap_err().is_incomplete_input());
+    }
+}
@@ -1,13 +1,21 @@
-use rustyline::completion::FilenameCompleter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard};
+
 use rustyline::error::ReadlineError;
 use rustyline::highlight::MatchingBracketHighlighter;
 use rustyline::hint::HistoryHinter;
 use rustyline::history::DefaultHistory;
-use rustyline::validate::MatchingBracketValidator;
+use rustyline::validate::{ValidationContext, ValidationResult};
 use rustyline::{
-    Completer, CompletionType, Config, Editor, Helper, Highlighter, Hinter, Validator,
+    Cmd, Completer, CompletionType, ConditionalEventHandler, Config, Editor, Event, EventContext,
+    EventHandler, Helper, Highlighter, Hinter, KeyCode, KeyEvent, Modifiers, Movement, RepeatCount,
+    Validator,
 };
 
+use super::alias::command_names;
+use super::completion::CommandCompleter;
+use super::parser::is_complete;
+use super::Dispatcher;
 use crate::errors::{Handle, Result};
 use crate::state::ShellState;
 
@@ -15,44 +23,144 @@ use crate::state::ShellState;
 #[derive(Helper, Completer, Hinter, Validator, Highlighter)]
 struct LineEditorHelper {
     #[rustyline(Completer)]
-    completer: FilenameCompleter,
+    completer: CommandCompleter,
     #[rustyline(Highlighter)]
     highlighter: MatchingBracketHighlighter,
     #[rustyline(Validator)]
-    validator: MatchingBracketValidator,
+    validator: InputValidator,
     #[rustyline(Hinter)]
     hinter: HistoryHinter,
 }
 
 impl LineEditorHelper {
-    fn new() -> Self {
+    fn new(completer: CommandCompleter) -> Self {
         Self {
-            completer: FilenameCompleter::new(),
+            completer,
             highlighter: MatchingBracketHighlighter::new(),
-            validator: MatchingBracketValidator::new(),
+            validator: InputValidator {},
             hinter: HistoryHinter {},
         }
     }
 }
 
+/// Validator which keeps the prompt open while the input is incomplete, such as when a quote
+/// has not been closed or the line ends with a '|'
+// * The input is checked using the same tokenizer and parser that are used to run it, so that
+// * brackets inside of quotes (e.g. "$(echo ")")") do not affect whether it is complete
+// $ The lines after the first are shown without a continuation prompt (like '> ' in other shells),
+// $ since the editor places the cursor using the text of the input rather than the highlighted
+// $ text, so a prompt drawn by the `Highlighter` would put the cursor in the wrong column
+struct InputValidator {}
+
+impl rustyline::validate::Validator for InputValidator {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match is_complete(ctx.input()) {
+            true => Ok(ValidationResult::Valid(None)),
+            false => Ok(ValidationResult::Incomplete),
+        }
+    }
+}
+
+/// Abbreviations shared between the `LineEditor` and its event handler
+// * The event handler cannot borrow the shell, so the abbreviations are copied from the shell's
+// * configuration before each prompt instead
+#[derive(Default)]
+struct SharedAbbreviations {
+    definitions: RwLock<HashMap<String, String>>,
+    /// The line which the event handler last expanded the abbreviations in, once expanded
+    expanded: Mutex<Option<String>>,
+}
+
+impl SharedAbbreviations {
+    fn definitions(&self) -> RwLockReadGuard<'_, HashMap<String, String>> {
+        self.definitions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn expanded(&self) -> MutexGuard<'_, Option<String>> {
+        self.expanded.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Event handler which expands the abbreviations in a line when Enter is pressed, so that the
+/// expanded command is shown before it is run, and is recorded in the history
+/// The line is only entered once Enter is pressed again
+// * A command returned by an event handler cannot move the cursor past the text which it inserts,
+// * so abbreviations are not expanded as they are typed
+struct AbbreviationExpander {
+    abbreviations: Arc<SharedAbbreviations>,
+}
+
+impl ConditionalEventHandler for AbbreviationExpander {
+    fn handle(
+        &self,
+        _event: &Event,
+        _count: RepeatCount,
+        _positive: bool,
+        context: &EventContext,
+    ) -> Option<Cmd> {
+        // * A line which has just been expanded is entered as it is, so that an abbreviation
+        // * which expands to a command starting with itself (e.g. 'ls -a') is only expanded once
+        let mut expanded = self.abbreviations.expanded();
+        if expanded.as_deref() == Some(context.line()) {
+            return None;
+        }
+
+        let line = expand_abbreviations(context.line(), &self.abbreviations.definitions())?;
+        *expanded = Some(line.clone());
+        Some(Cmd::Replace(Movement::WholeBuffer, Some(line)))
+    }
+}
+
+/// Replaces each abbreviation at the start of a command in a line with its expansion,
+/// or gives `None` if there are none
+// * Like aliases, abbreviations are only expanded when they are unquoted, so '\gc' or "gc" is
+// * entered as it is
+fn expand_abbreviations(line: &str, abbreviations: &HashMap<String, String>) -> Option<String> {
+    let mut expanded = line.to_owned();
+    let mut found = false;
+    // * Later words are replaced first, so that the spans of earlier ones stay the same
+    for (name, span) in command_names(line).into_iter().rev() {
+        if let Some(expansion) = abbreviations.get(&name) {
+            expanded.replace_range(span, expansion);
+            found = true;
+        }
+    }
+
+    found.then_some(expanded)
+}
+
 /// Editor for reading lines of input from the user
 pub struct LineEditor {
     editor: Editor<LineEditorHelper, DefaultHistory>,
+    abbreviations: Arc<SharedAbbreviations>,
 }
 
 impl LineEditor {
     /// Creates a `LineEditor` with the default configuration and given history file
-    pub fn new(history_file: &str) -> Result<Self> {
+    /// The dispatcher's builtins are used to complete commands and their arguments
+    pub fn new(history_file: &str, dispatcher: &Dispatcher) -> Result<Self> {
         let config = Config::builder()
             .history_ignore_space(true)
             .completion_type(CompletionType::Fuzzy)
             .build();
 
-        let helper = LineEditorHelper::new();
+        let helper = LineEditorHelper::new(CommandCompleter::new(dispatcher));
+        let abbreviations = Arc::new(SharedAbbreviations::default());
 
         let mut editor =
             Editor::with_config(config).replace_err(|| state_err!(UnsupportedTerminal))?;
         editor.set_helper(Some(helper));
+
+        // Abbreviations are expanded when the line is entered, before it is run
+        editor.bind_sequence(
+            KeyEvent(KeyCode::Enter, Modifiers::NONE),
+            EventHandler::Conditional(Box::new(AbbreviationExpander {
+                abbreviations: abbreviations.clone(),
+            })),
+        );
+
         if editor.load_history(history_file).is_err() {
             println!("No existing history file found, attempting to create one...");
             if fs_err::File::create(history_file).is_err() {
@@ -65,11 +173,26 @@ impl LineEditor {
             }
         }
 
-        Ok(Self { editor })
+        Ok(Self {
+            editor,
+            abbreviations,
+        })
     }
 
     /// Prints the shell prompt and reads a line of input from the user
-    pub fn prompt_and_read_line(&mut self, shell: &ShellState) -> String {
+    /// Gives `None` once the user ends their input, such as by pressing Ctrl-D
+    pub fn prompt_and_read_line(&mut self, shell: &ShellState) -> Option<String> {
+        self.abbreviations
+            .definitions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone_from(&shell.config.abbreviations);
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.completer.refresh(shell);
+        }
+
+        *self.abbreviations.expanded() = None;
+
         loop {
             let input = self.editor.readline(&shell.generate_prompt());
             match input {
@@ -83,7 +206,7 @@ impl LineEditor {
                             println!("Failed to save history file.");
                         }
 
-                        return line;
+                        return Some(line);
                     } else {
                         // TODO: Do not reprompt on a blank line
                         continue;
@@ -91,8 +214,9 @@ impl LineEditor {
                 }
                 Err(e) => match e {
                     // TODO: Propagate error?
-                    ReadlineError::Interrupted => std::process::exit(1),
-                    ReadlineError::Eof => std::process::exit(0),
+                    // Ctrl-C discards the current line and starts a new one
+                    ReadlineError::Interrupted => continue,
+                    ReadlineError::Eof => return None,
                     _ => {
                         println!("Unhandled error occurred while line-editing: {}", e);
                         std::process::exit(2);
@@ -0,0 +1,140 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use fs_err::{File, OpenOptions};
+
+use super::dispatcher::Dispatcher;
+use super::expansion::expand_word;
+use super::parser::{Redirection, RedirectionKind};
+use crate::errors::{Handle, Result};
+use crate::exec::{Input, IoContext, Output};
+use crate::state::ShellState;
+
+/// The ways in which a redirection can open a file for writing
+#[derive(Clone, Copy)]
+enum WriteMode {
+    /// Replace the file's contents, unless overwriting files is disabled
+    Truncate,
+    /// Replace the file's contents, even if overwriting files is disabled
+    Clobber,
+    /// Add to the end of the file's contents
+    Append,
+}
+
+/// Applies a command's redirections to its streams, in the order they were written
+// * The order matters: 'cmd > log 2>&1' sends both streams to the log file,
+// * while 'cmd 2>&1 > log' sends errors to the original output and only the output to the log file
+pub fn apply_redirections(
+    redirections: &[Redirection],
+    io: &mut IoContext,
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+) -> Result<()> {
+    let noclobber = shell.config.noclobber;
+    for redirection in redirections {
+        let target = expand_word(&redirection.target, shell, dispatcher)?;
+        let path = PathBuf::from(&target);
+        let open_output = |mode| open_for_writing(&path, mode, noclobber);
+
+        match redirection.kind {
+            RedirectionKind::Input => {
+                io.set_input(redirection.fd.unwrap_or(0), open_for_reading(&path)?)?
+            }
+            RedirectionKind::Output => io.set_output(
+                redirection.fd.unwrap_or(1),
+                open_output(WriteMode::Truncate)?,
+            )?,
+            RedirectionKind::Append => {
+                io.set_output(redirection.fd.unwrap_or(1), open_output(WriteMode::Append)?)?
+            }
+            RedirectionKind::Clobber => io.set_output(
+                redirection.fd.unwrap_or(1),
+                open_output(WriteMode::Clobber)?,
+            )?,
+            RedirectionKind::ReadWrite => {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(&path)
+                    .replace_err(|| file_err!(CouldNotOpenFile: path))?
+                    .into_parts()
+                    .0;
+
+                match redirection.fd.unwrap_or(0) {
+                    0 => io.set_input(0, Input::File(file))?,
+                    fd => io.set_output(fd, Output::File(file))?,
+                }
+            }
+            RedirectionKind::DuplicateInput => {
+                let target_fd = parse_fd(&target)?;
+                io.duplicate(redirection.fd.unwrap_or(0), target_fd)?;
+            }
+            RedirectionKind::DuplicateOutput => {
+                match (redirection.fd, target.parse::<u32>()) {
+                    (fd, Ok(target_fd)) => io.duplicate(fd.unwrap_or(1), target_fd)?,
+                    // * '>&file' without a file descriptor is an older spelling of '&>file'
+                    (None, Err(_)) => redirect_both(io, open_output(WriteMode::Truncate)?)?,
+                    (Some(_), Err(_)) => return Err(dispatch_err!(InvalidFileDescriptor: target)),
+                }
+            }
+            RedirectionKind::OutputAndError => {
+                redirect_both(io, open_output(WriteMode::Truncate)?)?
+            }
+            RedirectionKind::AppendOutputAndError => {
+                redirect_both(io, open_output(WriteMode::Append)?)?
+            }
+            RedirectionKind::HereDocument => io.set_input(
+                redirection.fd.unwrap_or(0),
+                Input::Buffer(Cursor::new(target.into_bytes())),
+            )?,
+            RedirectionKind::HereString => io.set_input(
+                redirection.fd.unwrap_or(0),
+                Input::Buffer(Cursor::new(format!("{}\n", target).into_bytes())),
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Points both the output and error streams at the same file
+fn redirect_both(io: &mut IoContext, output: Output) -> Result<()> {
+    io.set_output(1, output)?;
+    io.duplicate(2, 1)
+}
+
+/// Parses the target of a file descriptor duplication
+fn parse_fd(target: &str) -> Result<u32> {
+    target
+        .parse::<u32>()
+        .replace_err(|| dispatch_err!(InvalidFileDescriptor: target))
+}
+
+/// Opens a file to be used as a command's input
+fn open_for_reading(path: &PathBuf) -> Result<Input> {
+    let file = File::open(path).replace_err(|| file_err!(CouldNotOpenFile: path))?;
+    Ok(Input::File(file.into_parts().0))
+}
+
+/// Opens a file to be used as one of a command's outputs, creating it if it does not exist
+fn open_for_writing(path: &PathBuf, mode: WriteMode, noclobber: bool) -> Result<Output> {
+    // * Only regular files are protected from being overwritten,
+    // * so that special files like '/dev/null' can always be written to
+    if noclobber && matches!(mode, WriteMode::Truncate) && path.is_file() {
+        return Err(file_err!(OverwriteDisallowed: path));
+    }
+
+    let mut options = OpenOptions::new();
+    options.create(true);
+    match mode {
+        WriteMode::Truncate | WriteMode::Clobber => options.write(true).truncate(true),
+        WriteMode::Append => options.append(true),
+    };
+
+    let file = options
+        .open(path)
+        .replace_err(|| file_err!(CouldNotCreateFile: path))?;
+    Ok(Output::File(file.into_parts().0))
+}
@@ -0,0 +1,310 @@
+use std::path::Path as StdPath;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+
+use super::Dispatcher;
+use crate::errors::{Handle, Result, RushError};
+use crate::exec::Output;
+use crate::state::{ExitStatus, ShellState};
+
+/// The file extension which marks a script as a Rhai script rather than a shell script
+const RHAI_EXTENSION: &str = "rhai";
+
+/// How often the shell checks whether a script's thread has stopped without finishing
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The result of a function called by a Rhai script
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// An operation which a Rhai script needs the shell's state for
+type Request = Box<dyn FnOnce(&mut ShellState, &Dispatcher, &mut Output) + Send>;
+
+/// A message sent from the thread running a Rhai script to the shell's thread
+enum Message {
+    /// An operation to run on the shell's thread, which sends its result back once finished
+    Request(Request),
+    /// Sent once the script has finished running, whether or not it succeeded
+    Finished,
+}
+
+/// Runs Rhai scripts, which can run commands and change the shell's variables, directory and PATH
+/// Variables defined by one script remain available to the next
+// * Scripts run on a separate thread, since the functions registered with the engine cannot
+// * borrow the shell's state; they send requests back to the shell's thread instead, which
+// * runs them while it waits for the script to finish
+pub struct ScriptEngine {
+    engine: Engine,
+    scope: Scope<'static>,
+    sender: Sender<Message>,
+    // * A receiver cannot be shared between threads by itself, which the shell's state must be,
+    // * although it is only ever used by the thread running the script
+    receiver: Mutex<Receiver<Message>>,
+}
+
+impl ScriptEngine {
+    /// Creates a new engine with the shell's API registered
+    /// Scripts are stopped when `interrupted` is set
+    pub fn new(interrupted: Arc<AtomicBool>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let mut engine = Engine::new();
+
+        let requests = sender.clone();
+        engine.on_print(move |text| {
+            let text = text.to_owned();
+            // * A script which cannot print has nowhere to report that it cannot print
+            let _ = call(&requests, move |_, _, stdout| stdout.println(text));
+        });
+        engine.on_debug(|text, _, position| match position.is_none() {
+            true => eprintln!("{}", text),
+            false => eprintln!("{} {}", position, text),
+        });
+        engine.on_progress(move |_| match interrupted.load(Ordering::Relaxed) {
+            true => Some(Dynamic::UNIT),
+            false => None,
+        });
+
+        register_api(&mut engine, &sender);
+        Self {
+            engine,
+            scope: Scope::new(),
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// Runs a script, writing anything it prints to `stdout`
+    pub fn run(
+        &mut self,
+        script: &str,
+        shell: &mut ShellState,
+        dispatcher: &Dispatcher,
+        stdout: &mut Output,
+    ) -> Result<()> {
+        let Self {
+            engine,
+            scope,
+            sender,
+            receiver,
+        } = self;
+        let receiver = receiver.get_mut().unwrap_or_else(PoisonError::into_inner);
+
+        // * A Ctrl-C from before the script started should not stop it
+        shell.interrupted.store(false, Ordering::Relaxed);
+        let result = thread::scope(|threads| {
+            let worker = threads.spawn(|| {
+                let result = engine.run_with_scope(scope, script);
+                let _ = sender.send(Message::Finished);
+                result
+            });
+
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(Message::Request(request)) => request(shell, dispatcher, stdout),
+                    Ok(Message::Finished) => break,
+                    // * The script's thread only stops without finishing if it panicked
+                    Err(RecvTimeoutError::Timeout) if worker.is_finished() => break,
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+
+            worker.join()
+        });
+
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(convert_error(*error)),
+            Err(_) => Err(dispatch_err!(ScriptFailed: "the script stopped unexpectedly")),
+        }
+    }
+}
+
+/// Checks whether the file at the given path is a Rhai script, based on its extension
+pub fn is_rhai_script(path: &StdPath) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == RHAI_EXTENSION)
+}
+
+impl Dispatcher {
+    /// Runs a Rhai script within the current shell, writing anything it prints to `stdout`
+    pub fn eval_rhai(&self, shell: &mut ShellState, script: &str, stdout: &Output) -> Result<()> {
+        let mut stdout = stdout
+            .try_clone()
+            .replace_err(|| executable_err!(CouldNotCreatePipe))?;
+
+        // * The engine is taken out of the shell while the script runs, since the script needs
+        // * the rest of the shell; a script run by another script gets an engine of its own
+        let mut engine = shell
+            .scripting
+            .take()
+            .unwrap_or_else(|| ScriptEngine::new(shell.interrupted.clone()));
+        let result = engine.run(script, shell, self, &mut stdout);
+        shell.scripting = Some(engine);
+
+        // * The status of the last command that the script ran does not become the script's own
+        let status = match &result {
+            Ok(()) => ExitStatus::default(),
+            Err(error) => ExitStatus::from_error(error, Duration::ZERO),
+        };
+        shell.record_status(vec![status]);
+        result
+    }
+}
+
+/// Registers the functions which scripts use to interact with the shell
+fn register_api(engine: &mut Engine, sender: &Sender<Message>) {
+    // Runs a command, giving its status and everything it wrote to its standard output
+    let requests = sender.clone();
+    engine.register_fn("run", move |command: &str| -> ScriptResult<Map> {
+        let command = command.to_owned();
+        try_call(&requests, move |shell, dispatcher, _| {
+            let output = dispatcher.capture(shell, &command)?;
+            let mut result = Map::new();
+            result.insert(
+                "status".into(),
+                Dynamic::from(shell.last_status.code as i64),
+            );
+            result.insert("output".into(), Dynamic::from(output));
+            Ok(result)
+        })
+    });
+
+    // Variables which are unset are given as '()'
+    let requests = sender.clone();
+    engine.register_fn("get_var", move |name: &str| -> ScriptResult<Dynamic> {
+        let name = name.to_owned();
+        let value = call(&requests, move |shell, _, _| {
+            shell.environment.get_variable(&name)
+        })?;
+        Ok(value.map(Dynamic::from).unwrap_or(Dynamic::UNIT))
+    });
+
+    let requests = sender.clone();
+    engine.register_fn(
+        "set_var",
+        move |name: &str, value: &str| -> ScriptResult<()> {
+            let (name, value) = (name.to_owned(), value.to_owned());
+            try_call(&requests, move |shell, _, _| {
+                shell.environment.set_variable(&name, &value)
+            })
+        },
+    );
+
+    let requests = sender.clone();
+    engine.register_fn(
+        "export_var",
+        move |name: &str, value: &str| -> ScriptResult<()> {
+            let (name, value) = (name.to_owned(), value.to_owned());
+            try_call(&requests, move |shell, _, _| {
+                shell.environment.export_variable(&name, Some(&value))
+            })
+        },
+    );
+
+    let requests = sender.clone();
+    engine.register_fn("unset_var", move |name: &str| -> ScriptResult<()> {
+        let name = name.to_owned();
+        try_call(&requests, move |shell, _, _| {
+            shell.environment.unset_variable(&name)
+        })
+    });
+
+    let requests = sender.clone();
+    engine.register_fn("cd", move |path: &str| -> ScriptResult<()> {
+        let path = path.to_owned();
+        try_call(&requests, move |shell, _, _| {
+            let history_limit = shell.config.history_limit;
+            shell
+                .environment
+                .set_CWD(StdPath::new(&path), history_limit)
+        })
+    });
+
+    let requests = sender.clone();
+    engine.register_fn("cwd", move || -> ScriptResult<String> {
+        call(&requests, |shell, _, _| shell.CWD().to_string())
+    });
+
+    // The directories in the PATH, in the order they are searched
+    let requests = sender.clone();
+    engine.register_fn("path", move || -> ScriptResult<Array> {
+        call(&requests, |shell, _, _| {
+            shell
+                .environment
+                .PATH()
+                .iter()
+                .map(|path| Dynamic::from(path.to_string()))
+                .collect()
+        })
+    });
+
+    let requests = sender.clone();
+    engine.register_fn("path_append", move |path: &str| -> ScriptResult<()> {
+        let path = path.to_owned();
+        try_call(&requests, move |shell, _, _| {
+            shell.environment.PATH_append(StdPath::new(&path))
+        })
+    });
+
+    let requests = sender.clone();
+    engine.register_fn("path_prepend", move |path: &str| -> ScriptResult<()> {
+        let path = path.to_owned();
+        try_call(&requests, move |shell, _, _| {
+            shell.environment.PATH_prepend(StdPath::new(&path))
+        })
+    });
+
+    let requests = sender.clone();
+    engine.register_fn("path_remove", move |index: i64| -> ScriptResult<()> {
+        let index = usize::try_from(index).map_err(|_| format!("Invalid PATH index {}", index))?;
+        try_call(&requests, move |shell, _, _| {
+            shell.environment.PATH_delete(index)
+        })
+    });
+}
+
+/// Runs an operation on the shell's thread and waits for its result
+fn call<T: Send + 'static>(
+    requests: &Sender<Message>,
+    operation: impl FnOnce(&mut ShellState, &Dispatcher, &mut Output) -> T + Send + 'static,
+) -> ScriptResult<T> {
+    let (reply, response) = mpsc::channel();
+    let request: Request = Box::new(move |shell, dispatcher, stdout| {
+        let _ = reply.send(operation(shell, dispatcher, stdout));
+    });
+
+    requests
+        .send(Message::Request(request))
+        .map_err(|_| "The shell is no longer running this script")?;
+    Ok(response
+        .recv()
+        .map_err(|_| "The shell stopped before finishing the request")?)
+}
+
+/// Runs an operation which can fail on the shell's thread, giving any error to the script
+fn try_call<T: Send + 'static>(
+    requests: &Sender<Message>,
+    operation: impl FnOnce(&mut ShellState, &Dispatcher, &mut Output) -> Result<T> + Send + 'static,
+) -> ScriptResult<T> {
+    Ok(call(requests, operation)??)
+}
+
+/// Converts an error from a Rhai script into a shell error
+fn convert_error(error: EvalAltResult) -> RushError {
+    match error {
+        EvalAltResult::ErrorTerminated(..) => builtin_err!(Interrupted),
+        error => dispatch_err!(ScriptFailed: error.to_string()),
+    }
+}
+
+// * Errors from the shell become runtime errors in the script, so that scripts can catch them
+impl From<RushError> for Box<EvalAltResult> {
+    fn from(error: RushError) -> Self {
+        error.to_string().into()
+    }
+}
@@ -1,5 +1,6 @@
 // Separator tokens
 pub const WHITESPACE: char = ' ';
+pub const NEWLINE_CHAR: char = '\n';
 pub const SEMICOLON: char = ';';
 pub const AMPERSAND: char = '&';
 pub const GREATER_THAN: char = '>';
@@ -9,6 +10,49 @@ pub const SINGLE_QUOTE: char = '\'';
 pub const DOUBLE_QUOTE: char = '"';
 pub const BACKSLASH: char = '\\';
 pub const DOLLAR: char = '$';
+pub const LEFT_BRACE: char = '{';
+pub const RIGHT_BRACE: char = '}';
+pub const LEFT_PAREN: char = '(';
+pub const RIGHT_PAREN: char = ')';
+pub const BACKTICK: char = '`';
+// * Special parameter for the exit code of the last command ('$?')
+pub const LAST_STATUS: char = '?';
+// * Shorthand for the home directory at the start of a word ('~')
+pub const TILDE: char = '~';
+// * Begins a comment which continues to the end of the line, when at the start of a word
+// * Also the special parameter for the number of positional parameters ('$#')
+pub const HASH: char = '#';
+// * Special parameter for all of the positional parameters as separate fields ('$@')
+pub const ALL_ARGUMENTS: char = '@';
+
+// Pattern characters, used for filename globbing
+pub const STAR: char = '*';
+pub const QUESTION: char = '?';
+pub const LEFT_BRACKET: char = '[';
+pub const RIGHT_BRACKET: char = ']';
+pub const DOT: char = '.';
+pub const SLASH: char = '/';
+
+// Letters which stand for control characters when escaped with a backslash (e.g. '\n')
+pub const CONTROL_ESCAPES: [(char, char); 9] = [
+    ('n', '\n'),
+    ('t', '\t'),
+    ('r', '\r'),
+    ('0', '\0'),
+    ('a', '\x07'),
+    ('b', '\x08'),
+    ('v', '\x0b'),
+    ('f', '\x0c'),
+    ('e', '\x1b'),
+];
+
+// Single-character operator tokens
+pub const PIPELINE: &str = "|";
+pub const BACKGROUND: &str = "&";
+pub const SEQUENCE: &str = ";";
+pub const LESS: &str = "<";
+pub const GREAT: &str = ">";
+pub const NEWLINE: &str = "\n";
 
 // Operator tokens
 pub const AND_IF: &str = "&&";
@@ -20,23 +64,114 @@ pub const LESSAND: &str = "<&";
 pub const GREATAND: &str = ">&";
 pub const LESSGREAT: &str = "<>";
 pub const CLOBBER: &str = ">|";
+pub const ANDGREAT: &str = "&>";
+pub const ANDDGREAT: &str = "&>>";
+pub const DLESSDASH: &str = "<<-";
+pub const TLESS: &str = "<<<";
+
+// Reserved words, which are only recognized as the first word of a command
+pub const IF: &str = "if";
+pub const THEN: &str = "then";
+pub const ELIF: &str = "elif";
+pub const ELSE: &str = "else";
+pub const FI: &str = "fi";
+pub const WHILE: &str = "while";
+pub const UNTIL: &str = "until";
+pub const FOR: &str = "for";
+pub const DO: &str = "do";
+pub const DONE: &str = "done";
+pub const CASE: &str = "case";
+pub const ESAC: &str = "esac";
+pub const BEGIN_GROUP: &str = "{";
+pub const END_GROUP: &str = "}";
+pub const FUNCTION: &str = "function";
+// * 'in' is only reserved after the name in a 'for' loop or the word in a 'case' command
+pub const IN: &str = "in";
+
+pub const RESERVED_WORDS: [&str; 15] = [
+    IF,
+    THEN,
+    ELIF,
+    ELSE,
+    FI,
+    WHILE,
+    UNTIL,
+    FOR,
+    DO,
+    DONE,
+    CASE,
+    ESAC,
+    BEGIN_GROUP,
+    END_GROUP,
+    FUNCTION,
+];
 
 pub struct Symbols<'a> {
     pub operators: Vec<&'a str>,
-    pub separators: Vec<char>,
 }
 
 impl Symbols<'_> {
     pub fn new() -> Self {
+        // * Operators are listed longest-first so that the tokenizer can use the first match
         let operators = vec![
-            AND_IF, OR_IF, DSEMI, DLESS, DGREAT, LESSAND, GREATAND, LESSGREAT, CLOBBER,
+            ANDDGREAT, DLESSDASH, TLESS, AND_IF, OR_IF, DSEMI, DLESS, DGREAT, LESSAND, GREATAND,
+            LESSGREAT, CLOBBER, ANDGREAT, PIPELINE, BACKGROUND, SEQUENCE, LESS, GREAT, NEWLINE,
         ];
 
-        let separators = vec![WHITESPACE, AMPERSAND, SEMICOLON];
+        Symbols { operators }
+    }
+
+    /// Checks whether a character can begin an operator
+    // * Newlines are handled separately by the tokenizer, since they also begin here-document bodies
+    pub fn is_operator_start(character: char) -> bool {
+        matches!(
+            character,
+            PIPE | AMPERSAND | SEMICOLON | LESS_THAN | GREATER_THAN
+        )
+    }
+
+    /// Checks whether a character has a special meaning outside of quotes which stops it from
+    /// being read as part of a word, which escaping it with a backslash removes (e.g. '\;')
+    pub fn is_syntax_character(character: char) -> bool {
+        Self::is_operator_start(character)
+            || matches!(
+                character,
+                WHITESPACE | LEFT_PAREN | RIGHT_PAREN | LEFT_BRACE | RIGHT_BRACE | TILDE
+            )
+    }
 
-        Symbols {
-            operators,
-            separators,
-        }
+    /// Checks whether a character separates one command from the next (e.g. ';' or '|')
+    pub fn is_command_separator(character: char) -> bool {
+        matches!(
+            character,
+            SEMICOLON | PIPE | AMPERSAND | LEFT_PAREN | NEWLINE_CHAR
+        )
     }
+
+    /// Checks whether a token is a redirection operator, with or without a file descriptor prefix
+    pub fn is_redirection(token: &str) -> bool {
+        let operator = token.trim_start_matches(|c: char| c.is_ascii_digit());
+        matches!(
+            operator,
+            LESS | GREAT
+                | DLESS
+                | DLESSDASH
+                | TLESS
+                | DGREAT
+                | LESSAND
+                | GREATAND
+                | LESSGREAT
+                | CLOBBER
+                | ANDGREAT
+                | ANDDGREAT
+        )
+    }
+}
+
+/// Gets the control character that a letter stands for when it is escaped with a backslash
+pub fn control_escape(letter: char) -> Option<char> {
+    CONTROL_ESCAPES
+        .iter()
+        .find(|(escape, _)| *escape == letter)
+        .map(|(_, control)| *control)
 }
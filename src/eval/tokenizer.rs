@@ -1,143 +1,799 @@
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+
 use super::symbols::{
-    Symbols, AMPERSAND, BACKSLASH, DOLLAR, DOUBLE_QUOTE, GREATER_THAN, LESS_THAN, PIPE, SEMICOLON,
-    SINGLE_QUOTE, WHITESPACE,
+    control_escape, Symbols, BACKSLASH, BACKTICK, DLESS, DLESSDASH, DOLLAR, DOUBLE_QUOTE, HASH,
+    LEFT_BRACE, LEFT_BRACKET, LEFT_PAREN, NEWLINE, NEWLINE_CHAR, QUESTION, RIGHT_BRACE,
+    RIGHT_PAREN, SINGLE_QUOTE, STAR, WHITESPACE,
 };
+use crate::errors::{Result, Span};
+
+/// A line of input which has been separated into tokens
+pub struct TokenizedInput {
+    /// Each token, along with the span of the input that it was read from
+    pub tokens: Vec<(Token, Span)>,
+    /// The bodies of the line's here-documents, in the order that their operators appear
+    pub here_documents: Vec<HereDocument>,
+    /// The number of braces, substitutions and expansions which were still open at the end of the line
+    pub unclosed: usize,
+}
+
+/// A single token of input, which is either a word or an operator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A command name, argument or redirection target, which may be quoted
+    Word(Word),
+    /// A control or redirection operator, including any file descriptor prefix (e.g. '2>')
+    // * A quoted operator such as "&&" is read as a word instead, so it is never mistaken for one
+    Operator(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "{}", word),
+            // * A newline would be confusing to display on its own, so it is written out instead
+            Self::Operator(operator) if operator == NEWLINE => write!(f, "newline"),
+            Self::Operator(operator) => write!(f, "{}", operator),
+        }
+    }
+}
+
+/// A word made up of parts which may each be quoted differently (e.g. 'abc'"$HOME"def)
+/// Empty quotes are kept as an empty part, so that a word like "" is still an argument
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+/// A section of a `Word`, which determines how the text inside of it is expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    /// Text outside of any quotes, which is subject to expansion
+    Unquoted(String),
+    /// Text inside double quotes, which is subject to expansion
+    DoubleQuoted(String),
+    /// Text inside single quotes or escaped with a backslash, which is never expanded
+    Literal(String),
+}
+
+impl WordPart {
+    /// Gets the text of the part, without any quotes
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Unquoted(text) | Self::DoubleQuoted(text) | Self::Literal(text) => text,
+        }
+    }
+
+    /// Creates a part which is quoted the same way as this one, but contains different text
+    pub fn with_text(&self, text: String) -> Self {
+        match self {
+            Self::Unquoted(_) => Self::Unquoted(text),
+            Self::DoubleQuoted(_) => Self::DoubleQuoted(text),
+            Self::Literal(_) => Self::Literal(text),
+        }
+    }
+}
+
+impl Display for Word {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for part in &self.parts {
+            write!(f, "{}", part.text())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Word {
+    /// Gets the text of the word if no part of it was quoted or escaped
+    pub fn as_unquoted(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Unquoted(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Removes a character from the start of the word, if the word begins with it unquoted
+    pub fn strip_unquoted_prefix(&mut self, character: char) -> bool {
+        let Some(WordPart::Unquoted(text)) = self.parts.first_mut() else {
+            return false;
+        };
+
+        let Some(rest) = text.strip_prefix(character) else {
+            return false;
+        };
+
+        *text = rest.to_owned();
+        if text.is_empty() {
+            self.parts.remove(0);
+        }
+
+        true
+    }
+
+    /// Removes a character from the end of the word, if the word ends with it unquoted
+    pub fn strip_unquoted_suffix(&mut self, character: char) -> bool {
+        let Some(WordPart::Unquoted(text)) = self.parts.last_mut() else {
+            return false;
+        };
+
+        if !text.ends_with(character) {
+            return false;
+        }
+
+        text.pop();
+        if text.is_empty() {
+            self.parts.pop();
+        }
+
+        true
+    }
+
+    /// Adds text to the end of the word, merging it into the last part if both are quoted the same way
+    pub fn push(&mut self, part: WordPart) {
+        match (self.parts.last_mut(), part) {
+            (Some(WordPart::Unquoted(last)), WordPart::Unquoted(text))
+            | (Some(WordPart::DoubleQuoted(last)), WordPart::DoubleQuoted(text))
+            | (Some(WordPart::Literal(last)), WordPart::Literal(text)) => last.push_str(&text),
+            (_, part) => self.parts.push(part),
+        }
+    }
+}
+
+/// The body of a here-document, read from the lines following the one containing its operator
+#[derive(Debug, Clone)]
+pub struct HereDocument {
+    /// The text of the body, which is only subject to expansion if the delimiter was not quoted
+    pub body: Word,
+    /// Whether the delimiter line was found before the end of the input
+    pub terminated: bool,
+}
+
+/// A here-document whose delimiter has been read, but whose body has not
+struct PendingHereDocument {
+    delimiter: String,
+    /// Whether leading tabs are removed from each line of the body ('<<-')
+    strip_tabs: bool,
+    /// Whether expansions are performed on the body, which is the case unless the delimiter was quoted
+    expand: bool,
+}
 
 /// Separates a line of input into tokens, such as arguments, separators, and operators
-pub fn tokenize(input: &str) -> Vec<String> {
-    let symbols = Symbols::new();
-
-    let mut curr_token = String::new();
-    let mut tokens: Vec<String> = Vec::new();
-
-    let mut characters = input.trim().chars().peekable();
-    let mut in_double_quotes = false;
-    let mut in_single_quotes = false;
-
-    loop {
-        let character = characters.next();
-
-        match character {
-            Some(v) => {
-                match v {
-                    WHITESPACE | AMPERSAND | SEMICOLON => {
-                        if in_single_quotes || in_double_quotes {
-                            curr_token.push(v);
-                            continue;
-                        }
-
-                        match characters.peek() {
-                            Some(peeked_char) => {
-                                if (peeked_char == &SEMICOLON && v == SEMICOLON)
-                                    || (peeked_char == &AMPERSAND && v == AMPERSAND)
-                                {
-                                    // clear token, push the operator into the token and advance to the next character
-                                    delimit_token(&mut tokens, &mut curr_token);
-
-                                    curr_token.push(v);
-                                    curr_token.push(*peeked_char);
-                                    characters.next();
-
-                                    delimit_token(&mut tokens, &mut curr_token);
-                                } else {
-                                    delimit_token(&mut tokens, &mut curr_token);
-                                }
-                            }
-                            None => {
-                                continue;
-                            }
-                        }
+pub fn tokenize(input: &str) -> Result<TokenizedInput> {
+    Tokenizer::new(input).run()
+}
+
+/// State of the tokenizer as it moves through a line of input
+struct Tokenizer<'a> {
+    characters: Peekable<Chars<'a>>,
+    /// The byte offset in the input of the next character
+    position: usize,
+    symbols: Symbols<'static>,
+    tokens: Vec<(Token, Span)>,
+    curr_token: Word,
+    /// The byte offset in the input where the current token begins
+    curr_token_start: usize,
+    /// Whether any part of the current token was quoted or escaped
+    curr_token_quoted: bool,
+    in_double_quotes: bool,
+    in_single_quotes: bool,
+    /// The byte offset of the quote which opened the current quoted section
+    quote_start: usize,
+    /// Set after a here-document operator until its delimiter is read, holding whether tabs are stripped
+    awaiting_delimiter: Option<bool>,
+    /// The number of unquoted '{' which have not been closed by a '}'
+    open_braces: usize,
+    /// The number of expansions, substitutions and patterns which were never closed
+    unclosed: usize,
+    /// Here-documents whose bodies begin after the end of the current line
+    pending_here_documents: Vec<PendingHereDocument>,
+    here_documents: Vec<HereDocument>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str) -> Self {
+        let trimmed = input.trim();
+        Self {
+            characters: trimmed.chars().peekable(),
+            // * Spans refer to the untrimmed input, so that they line up with what was entered
+            position: input.len() - input.trim_start().len(),
+            symbols: Symbols::new(),
+            tokens: Vec::new(),
+            curr_token: Word::default(),
+            curr_token_start: 0,
+            curr_token_quoted: false,
+            in_double_quotes: false,
+            in_single_quotes: false,
+            quote_start: 0,
+            awaiting_delimiter: None,
+            open_braces: 0,
+            unclosed: 0,
+            pending_here_documents: Vec::new(),
+            here_documents: Vec::new(),
+        }
+    }
+
+    fn in_quotes(&self) -> bool {
+        self.in_single_quotes || self.in_double_quotes
+    }
+
+    /// Moves to the next character of the input, keeping track of its position
+    fn next_char(&mut self) -> Option<char> {
+        let character = self.characters.next()?;
+        self.position += character.len_utf8();
+        Some(character)
+    }
+
+    fn run(mut self) -> Result<TokenizedInput> {
+        loop {
+            let start = self.position;
+            let Some(v) = self.next_char() else {
+                break;
+            };
+
+            if self.curr_token.is_empty() {
+                self.curr_token_start = start;
+            }
+
+            match v {
+                WHITESPACE if !self.in_quotes() => self.delimit_token(start),
+                NEWLINE_CHAR if !self.in_quotes() => {
+                    self.delimit_token(start);
+                    self.tokens
+                        .push((Token::Operator(NEWLINE.to_owned()), start..self.position));
+                    self.read_here_document_bodies();
+                }
+                _ if Symbols::is_operator_start(v) && !self.in_quotes() => {
+                    self.read_operator(v, start)
+                }
+                HASH if !self.in_quotes() && self.curr_token.is_empty() => self.skip_comment(),
+                SINGLE_QUOTE if !self.in_double_quotes => {
+                    self.in_single_quotes = !self.in_single_quotes;
+                    self.curr_token_quoted = true;
+                    // Opening quotes begin an empty part, so that '' is kept even with nothing inside
+                    if self.in_single_quotes {
+                        self.quote_start = start;
+                        self.curr_token.push(WordPart::Literal(String::new()));
                     }
-                    PIPE | LESS_THAN | GREATER_THAN => {
-                        if in_single_quotes || in_double_quotes {
-                            curr_token.push(v);
-                            continue;
-                        }
-
-                        match characters.peek() {
-                            Some(peeked_char) => {
-                                if symbols
-                                    .operators
-                                    .iter()
-                                    .any(|&i| i == format!("{v}{peeked_char}"))
-                                {
-                                    // clear token, push the operator into the token and advance to the next character
-                                    delimit_token(&mut tokens, &mut curr_token);
-
-                                    curr_token.push(v);
-                                    curr_token.push(*peeked_char);
-                                    characters.next();
-
-                                    delimit_token(&mut tokens, &mut curr_token);
-                                }
-                            }
-                            None => {
-                                continue;
-                            }
-                        }
+                }
+                DOUBLE_QUOTE if !self.in_single_quotes => {
+                    self.in_double_quotes = !self.in_double_quotes;
+                    self.curr_token_quoted = true;
+                    if self.in_double_quotes {
+                        self.quote_start = start;
+                        self.curr_token.push(WordPart::DoubleQuoted(String::new()));
                     }
-                    SINGLE_QUOTE => {
-                        if !in_double_quotes {
-                            in_single_quotes = !in_single_quotes;
-                        } else {
-                            curr_token.push(v);
-                        }
+                }
+                BACKSLASH if !self.in_single_quotes => self.read_escape(v, start)?,
+                DOLLAR if !self.in_single_quotes && self.characters.peek() == Some(&LEFT_BRACE) => {
+                    self.read_braced_expansion(v)
+                }
+                DOLLAR if !self.in_single_quotes && self.characters.peek() == Some(&LEFT_PAREN) => {
+                    self.read_command_substitution(v)
+                }
+                BACKTICK if !self.in_single_quotes => self.read_backtick_substitution(v),
+                LEFT_PAREN if !self.in_quotes() && self.follows_extended_prefix() => {
+                    self.read_extended_pattern(v)
+                }
+                LEFT_BRACE if !self.in_quotes() => {
+                    self.open_braces += 1;
+                    self.push_char(v);
+                }
+                RIGHT_BRACE if !self.in_quotes() => {
+                    self.open_braces = self.open_braces.saturating_sub(1);
+                    self.push_char(v);
+                }
+                _ => self.push_char(v),
+            }
+        }
+
+        if self.in_quotes() {
+            let quote = match self.in_single_quotes {
+                true => SINGLE_QUOTE,
+                false => DOUBLE_QUOTE,
+            };
+            let span = self.quote_start..self.quote_start + 1;
+            return Err(parse_err!(UnterminatedQuote: quote, span));
+        }
+
+        self.delimit_token(self.position);
+        // Here-documents on the final line have no body, and are left unterminated
+        self.read_here_document_bodies();
+
+        Ok(TokenizedInput {
+            tokens: self.tokens,
+            here_documents: self.here_documents,
+            unclosed: self.open_braces + self.unclosed,
+        })
+    }
+
+    /// Adds a character to the current token, quoted according to the current quoting state
+    fn push_char(&mut self, character: char) {
+        let text = character.to_string();
+        self.curr_token.push(match self.in_quotes() {
+            true if self.in_single_quotes => WordPart::Literal(text),
+            true => WordPart::DoubleQuoted(text),
+            false => WordPart::Unquoted(text),
+        });
+    }
+
+    /// Skips over a comment, up to but not including the newline which ends it
+    fn skip_comment(&mut self) {
+        while self.characters.peek().is_some_and(|c| *c != NEWLINE_CHAR) {
+            self.next_char();
+        }
+    }
+
+    /// Reads the longest operator that begins at the given character, which is at the given position
+    fn read_operator(&mut self, v: char, start: usize) {
+        // * Every operator-start character is an operator on its own,
+        // * so a match is always found
+        let operator = self
+            .symbols
+            .operators
+            .iter()
+            .find(|operator| {
+                let mut lookahead = std::iter::once(v).chain(self.characters.clone());
+                operator.chars().all(|c| lookahead.next() == Some(c))
+            })
+            .copied()
+            .unwrap_or_default();
+
+        // Skip over the rest of the operator's characters
+        for _ in 1..operator.chars().count() {
+            self.next_char();
+        }
+
+        // A number directly before a redirection operator is the file descriptor
+        // that the redirection applies to (e.g. '2>'), so it is kept with the operator
+        let fd_prefix = match self.curr_token.as_unquoted() {
+            Some(token)
+                if Symbols::is_redirection(operator)
+                    && token.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                token.to_owned()
+            }
+            _ => String::new(),
+        };
+
+        let start = if fd_prefix.is_empty() {
+            self.delimit_token(start);
+            start
+        } else {
+            self.curr_token = Word::default();
+            self.curr_token_start
+        };
+
+        self.tokens.push((
+            Token::Operator(format!("{}{}", fd_prefix, operator)),
+            start..self.position,
+        ));
+
+        // The next word is the delimiter which ends the here-document
+        if operator == DLESS || operator == DLESSDASH {
+            self.awaiting_delimiter = Some(operator == DLESSDASH);
+        }
+    }
+
+    /// Reads the character following a backslash, pushing the character that the escape represents
+    fn read_escape(&mut self, v: char, start: usize) -> Result<()> {
+        let control = self
+            .characters
+            .peek()
+            .and_then(|&letter| control_escape(letter));
+        let escaped = match (control, self.characters.peek()) {
+            (Some(control), _) => control,
+            (
+                None,
+                Some(
+                    &c @ (SINGLE_QUOTE | DOUBLE_QUOTE | DOLLAR | BACKTICK | BACKSLASH | STAR
+                    | QUESTION | LEFT_BRACKET | HASH),
+                ),
+            ) => c,
+            // * Outside of quotes, an escaped operator or space is part of the word (e.g. 'a\;b')
+            (None, Some(&c)) if !self.in_double_quotes && Symbols::is_syntax_character(c) => c,
+            // A backslash must be followed by the character that it escapes
+            (None, None) => return Err(parse_err!(TrailingBackslash: start..self.position)),
+            // * A backslash at the end of a line joins it to the next line, so both are removed
+            (None, Some(&NEWLINE_CHAR)) => {
+                self.next_char();
+                return Ok(());
+            }
+            (None, _) => {
+                self.push_char(v);
+                return Ok(());
+            }
+        };
+
+        self.next_char();
+        self.curr_token.push(WordPart::Literal(escaped.to_string()));
+        self.curr_token_quoted = true;
+        Ok(())
+    }
+
+    /// Reads a '${...}' expansion up to its closing brace, so that it is kept within a single token
+    // * The expansion itself is performed later, when the command is evaluated
+    fn read_braced_expansion(&mut self, v: char) {
+        self.push_char(v);
+
+        let mut depth = 0;
+        while let Some(character) = self.next_char() {
+            self.push_char(character);
+            match character {
+                LEFT_BRACE => depth += 1,
+                RIGHT_BRACE if depth == 1 => return,
+                RIGHT_BRACE => depth -= 1,
+                _ => (),
+            }
+        }
+
+        self.unclosed += 1;
+    }
+
+    /// Reads a '$(...)' substitution up to its closing parenthesis, so that it is kept within a single token
+    // * Quotes and escapes inside the substitution are kept as they were written,
+    // * since the command is tokenized again when it is run
+    fn read_command_substitution(&mut self, v: char) {
+        self.push_char(v);
+
+        let mut depth = 0;
+        let (mut in_single_quotes, mut in_double_quotes) = (false, false);
+        while let Some(character) = self.next_char() {
+            self.push_char(character);
+            match character {
+                BACKSLASH if !in_single_quotes => {
+                    if let Some(escaped) = self.next_char() {
+                        self.push_char(escaped);
                     }
-                    DOUBLE_QUOTE => {
-                        if !in_single_quotes {
-                            in_double_quotes = !in_double_quotes;
-                        } else {
-                            curr_token.push(v);
-                        }
+                }
+                SINGLE_QUOTE if !in_double_quotes => in_single_quotes = !in_single_quotes,
+                DOUBLE_QUOTE if !in_single_quotes => in_double_quotes = !in_double_quotes,
+                _ if in_single_quotes || in_double_quotes => (),
+                LEFT_PAREN => depth += 1,
+                RIGHT_PAREN if depth == 1 => return,
+                RIGHT_PAREN => depth -= 1,
+                _ => (),
+            }
+        }
+
+        self.unclosed += 1;
+    }
+
+    /// Reads a '`...`' substitution up to its closing backtick, so that it is kept within a single token
+    fn read_backtick_substitution(&mut self, v: char) {
+        self.push_char(v);
+
+        while let Some(character) = self.next_char() {
+            self.push_char(character);
+            match character {
+                BACKSLASH => {
+                    if let Some(escaped) = self.next_char() {
+                        self.push_char(escaped);
                     }
-                    BACKSLASH => {
-                        if in_single_quotes {
-                            curr_token.push(v);
-                            continue;
-                        }
-
-                        'dont_skip: {
-                            if let Some(peeked_char) = characters.peek() {
-                                match *peeked_char {
-                                    'n' => curr_token.push('\n'),
-                                    't' => curr_token.push('\t'),
-                                    'r' => curr_token.push('\r'),
-                                    '0' => curr_token.push('\0'),
-                                    'a' => curr_token.push('\x07'),
-                                    'b' => curr_token.push('\x08'),
-                                    'v' => curr_token.push('\x0b'),
-                                    'f' => curr_token.push('\x0c'),
-                                    'e' => curr_token.push('\x1b'),
-                                    SINGLE_QUOTE | DOUBLE_QUOTE | DOLLAR | BACKSLASH => {
-                                        curr_token.push(*peeked_char)
-                                    }
-                                    _ => {
-                                        curr_token.push(v);
-                                        break 'dont_skip;
-                                    }
-                                }
-                            }
-
-                            characters.next();
-                        }
+                }
+                BACKTICK => return,
+                _ => (),
+            }
+        }
+
+        self.unclosed += 1;
+    }
+
+    /// Checks whether the current token ends with an unquoted character which can begin an
+    /// extended glob pattern, such as the '@' in '@(a|b)'
+    fn follows_extended_prefix(&self) -> bool {
+        match self.curr_token.parts.last() {
+            Some(WordPart::Unquoted(text)) => {
+                matches!(text.chars().last(), Some('?' | '*' | '+' | '@' | '!'))
+            }
+            _ => false,
+        }
+    }
+
+    /// Reads an extended glob pattern such as '@(a|b)' up to its closing parenthesis, so that
+    /// the '|' between its alternatives is not read as a pipe
+    fn read_extended_pattern(&mut self, v: char) {
+        self.push_char(v);
+
+        let mut depth = 1;
+        while let Some(character) = self.next_char() {
+            self.push_char(character);
+            match character {
+                LEFT_PAREN => depth += 1,
+                RIGHT_PAREN if depth == 1 => return,
+                RIGHT_PAREN => depth -= 1,
+                _ => (),
+            }
+        }
+
+        self.unclosed += 1;
+    }
+
+    /// Pushes the current token to the tokenized list and clears the token buffer
+    /// The token is taken to end at the given position
+    fn delimit_token(&mut self, end: usize) {
+        if !self.curr_token.is_empty() {
+            let token = std::mem::take(&mut self.curr_token);
+            if let Some(strip_tabs) = self.awaiting_delimiter.take() {
+                self.pending_here_documents.push(PendingHereDocument {
+                    delimiter: token.to_string(),
+                    strip_tabs,
+                    // * Quoting any part of the delimiter (e.g. <<'EOF') disables expansions in the body
+                    expand: !self.curr_token_quoted,
+                });
+            }
+
+            self.tokens
+                .push((Token::Word(token), self.curr_token_start..end));
+        }
+
+        self.curr_token_quoted = false;
+    }
+
+    /// Reads the bodies of any here-documents begun on the previous line
+    /// Each body continues up until a line consisting of only its delimiter
+    fn read_here_document_bodies(&mut self) {
+        for pending in std::mem::take(&mut self.pending_here_documents) {
+            let mut body = String::new();
+            let mut terminated = false;
+
+            while self.characters.peek().is_some() {
+                let mut line = String::new();
+                while let Some(character) = self.next_char() {
+                    if character == NEWLINE_CHAR {
+                        break;
                     }
-                    _ => curr_token.push(v),
+
+                    line.push(character);
+                }
+
+                let line = match pending.strip_tabs {
+                    true => line.trim_start_matches('\t'),
+                    false => line.as_str(),
+                };
+
+                if line == pending.delimiter {
+                    terminated = true;
+                    break;
                 }
+
+                body.push_str(line);
+                body.push(NEWLINE_CHAR);
             }
-            None => {
-                delimit_token(&mut tokens, &mut curr_token);
-                break;
+
+            let body = match pending.expand {
+                true => here_document_word(&body),
+                false => Word {
+                    parts: vec![WordPart::Literal(body)],
+                },
+            };
+
+            self.here_documents.push(HereDocument { body, terminated });
+        }
+    }
+}
+
+/// Converts the body of a here-document into a word which is expanded like double-quoted text
+/// Quotes have no special meaning in a here-document, but a backslash still escapes '$', '`' and itself
+fn here_document_word(body: &str) -> Word {
+    let mut word = Word::default();
+    let mut characters = body.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match (character, characters.peek()) {
+            (BACKSLASH, Some(&escaped @ (DOLLAR | BACKTICK | BACKSLASH))) => {
+                characters.next();
+                word.push(WordPart::Literal(escaped.to_string()));
             }
+            _ => word.push(WordPart::DoubleQuoted(character.to_string())),
         }
     }
 
-    tokens
+    word
 }
 
-/// Pushes the given token to the tokenized list and clears the token buffer
-fn delimit_token(tokens: &mut Vec<String>, curr_token: &mut String) {
-    if !curr_token.is_empty() {
-        tokens.push(curr_token.clone());
-        curr_token.clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokenizes the input, keeping only the tokens
+    fn tokens(input: &str) -> Vec<Token> {
+        tokenize(input)
+            .unwrap()
+            .tokens
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    fn word(parts: &[WordPart]) -> Token {
+        Token::Word(Word {
+            parts: parts.to_vec(),
+        })
+    }
+
+    fn unquoted(text: &str) -> Token {
+        word(&[WordPart::Unquoted(text.to_owned())])
+    }
+
+    fn operator(text: &str) -> Token {
+        Token::Operator(text.to_owned())
+    }
+
+    #[test]
+    fn operators_are_read_greedily() {
+        assert_eq!(
+            tokens("a&&b||c;d&"),
+            vec![
+                unquoted("a"),
+                operator("&&"),
+                unquoted("b"),
+                operator("||"),
+                unquoted("c"),
+                operator(";"),
+                unquoted("d"),
+                operator("&"),
+            ]
+        );
+    }
+
+    #[test]
+    fn redirection_operators_keep_file_descriptor() {
+        assert_eq!(
+            tokens("cmd 2>&1 >>out <in"),
+            vec![
+                unquoted("cmd"),
+                operator("2>&"),
+                unquoted("1"),
+                operator(">>"),
+                unquoted("out"),
+                operator("<"),
+                unquoted("in"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_operators_are_words() {
+        assert_eq!(
+            tokens("echo '&&' \"|\""),
+            vec![
+                unquoted("echo"),
+                word(&[WordPart::Literal("&&".to_owned())]),
+                word(&[WordPart::DoubleQuoted("|".to_owned())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_quotes_are_kept_as_words() {
+        assert_eq!(
+            tokens("echo '' \"\""),
+            vec![
+                unquoted("echo"),
+                word(&[WordPart::Literal(String::new())]),
+                word(&[WordPart::DoubleQuoted(String::new())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_have_spans_of_input() {
+        let spans: Vec<Span> = tokenize("ls  -a|wc")
+            .unwrap()
+            .tokens
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+        assert_eq!(spans, vec![0..2, 4..6, 6..7, 7..9]);
+    }
+
+    #[test]
+    fn unterminated_quotes_are_incomplete_input() {
+        for input in ["echo 'abc", "echo \"abc", "echo abc\\"] {
+            let error = tokenize(input).err().unwrap();
+            assert!(error.is_incomplete_input(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn unclosed_brackets_are_counted() {
+        assert_eq!(tokenize("echo $(ls").unwrap().unclosed, 1);
+        assert_eq!(tokenize("echo ${HOME").unwrap().unclosed, 1);
+        assert_eq!(tokenize("echo $(ls $(pwd)").unwrap().unclosed, 1);
+        assert_eq!(tokenize("echo $(ls)").unwrap().unclosed, 0);
+    }
+
+    #[test]
+    fn comments_are_skipped_up_to_newline() {
+        assert_eq!(
+            tokens("echo a # comment ; not run\necho b"),
+            vec![
+                unquoted("echo"),
+                unquoted("a"),
+                operator(NEWLINE),
+                unquoted("echo"),
+                unquoted("b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn hash_inside_word_or_quotes_is_not_comment() {
+        assert_eq!(
+            tokens("echo a#b '#c'"),
+            vec![
+                unquoted("echo"),
+                unquoted("a#b"),
+                word(&[WordPart::Literal("#c".to_owned())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_operators_are_part_of_word() {
+        let expected = word(&[
+            WordPart::Unquoted("a".to_owned()),
+            WordPart::Literal(";".to_owned()),
+            WordPart::Unquoted("b".to_owned()),
+        ]);
+        assert_eq!(tokens("echo a\\;b"), vec![unquoted("echo"), expected]);
+
+        for escaped in ["|", "&", "<", ">", "(", ")"] {
+            let input = format!("echo \\{}", escaped);
+            let expected = word(&[WordPart::Literal(escaped.to_owned())]);
+            assert_eq!(tokens(&input), vec![unquoted("echo"), expected]);
+        }
+    }
+
+    #[test]
+    fn escaped_semicolon_ends_find_exec() {
+        assert_eq!(
+            tokens("find . -exec rm {} \\;"),
+            vec![
+                unquoted("find"),
+                unquoted("."),
+                unquoted("-exec"),
+                unquoted("rm"),
+                unquoted("{}"),
+                word(&[WordPart::Literal(";".to_owned())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_space_does_not_split_word() {
+        let expected = word(&[
+            WordPart::Unquoted("a".to_owned()),
+            WordPart::Literal(" ".to_owned()),
+            WordPart::Unquoted("b".to_owned()),
+        ]);
+        assert_eq!(tokens("cat a\\ b"), vec![unquoted("cat"), expected]);
+    }
+
+    #[test]
+    fn escaped_operators_keep_backslash_in_double_quotes() {
+        let expected = word(&[WordPart::DoubleQuoted("a\\;b".to_owned())]);
+        assert_eq!(tokens("echo \"a\\;b\""), vec![unquoted("echo"), expected]);
+    }
+
+    #[test]
+    fn unescaped_operators_are_separate_tokens() {
+        assert_eq!(
+            tokens("a;b|c"),
+            vec![
+                unquoted("a"),
+                operator(";"),
+                unquoted("b"),
+                operator("|"),
+                unquoted("c"),
+            ]
+        );
     }
 }
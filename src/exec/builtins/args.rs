@@ -2,7 +2,7 @@ use std::{path::PathBuf, str::FromStr};
 
 use clap::{Args, Parser, Subcommand};
 
-use crate::state::EnvVariable;
+use crate::state::{EnvVariable, NoMatchBehavior};
 
 const TRUE_ARGS: [&str; 9] = [
     "true", "t", "enable", "enabled", "yes", "y", "on", "some", "1",
@@ -15,7 +15,13 @@ const FALSE_ARGS: [&str; 9] = [
 pub struct TestArgs {}
 
 #[derive(Parser, Debug)]
-pub struct ExitArgs {}
+pub struct ExitArgs {
+    #[arg(
+        allow_negative_numbers = true,
+        help = "The exit code to use, which defaults to the status of the last command"
+    )]
+    pub code: Option<i32>,
+}
 
 #[derive(Parser, Debug)]
 pub struct WorkingDirectoryArgs {}
@@ -30,11 +36,22 @@ pub struct ChangeDirectoryArgs {
 pub struct ListDirectoryArgs {
     #[arg(short = 'a', long = "all", help = "Show hidden files and directories")]
     pub show_hidden: bool,
-    #[arg(short = 'l', long = "long", help = "Show files with details as a table")]
+    #[arg(
+        short = 'l',
+        long = "long",
+        help = "Show files with details as a table"
+    )]
     pub long_view: bool,
-    #[arg(short = 'o', long = "octal-permissions", help = "Use octal permissions instead of string representation")]
+    #[arg(
+        short = 'o',
+        long = "octal-permissions",
+        help = "Use octal permissions instead of string representation"
+    )]
     pub octal_permissions: bool,
-    #[arg(long = "permission-seperator", help = "Show sperators for each permission group (eg. Current user, current group, and other users and groups)")]
+    #[arg(
+        long = "permission-seperator",
+        help = "Show sperators for each permission group (eg. Current user, current group, and other users and groups)"
+    )]
     pub permission_seperator: bool,
     #[arg(help = "The path of the directory to read")]
     pub path: Option<PathBuf>,
@@ -57,20 +74,22 @@ pub struct MakeFileArgs {
 
 #[derive(Parser, Debug)]
 pub struct MakeDirectoryArgs {
-    #[arg(help = "The path of the directory to create")]
-    pub path: PathBuf,
+    #[arg(required = true, help = "The paths of the directories to create")]
+    pub paths: Vec<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
 pub struct DeleteFileArgs {
-    #[arg(help = "The path of the file to delete")]
-    pub path: PathBuf,
+    #[arg(required = true, help = "The paths of the files to delete")]
+    pub paths: Vec<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
 pub struct ReadFileArgs {
-    #[arg(help = "The path of the file to read")]
-    pub path: PathBuf,
+    #[arg(
+        help = "The paths of the files to read, in order (reads from the command's input if omitted)"
+    )]
+    pub paths: Vec<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -101,6 +120,31 @@ pub struct ConfigureArgs {
     pub multiline_prompt: Option<Bool>,
     #[arg(long = "show-errors", help = "Whether to display error messages")]
     pub show_errors: Option<Bool>,
+    #[arg(
+        long = "noclobber",
+        help = "Whether to prevent output redirections from overwriting existing files"
+    )]
+    pub noclobber: Option<Bool>,
+    #[arg(
+        long = "glob-no-match",
+        help = "What to do with glob patterns that do not match any files ('error', 'pass' or 'empty')"
+    )]
+    pub glob_no_match: Option<NoMatchBehavior>,
+    #[arg(
+        long = "glob-dotfiles",
+        help = "Whether glob patterns can match files whose names begin with a '.'"
+    )]
+    pub glob_dotfiles: Option<Bool>,
+    #[arg(
+        long = "extglob",
+        help = "Whether to recognize extended glob patterns like '@(a|b)' and '!(*.rs)'"
+    )]
+    pub extglob: Option<Bool>,
+    #[arg(
+        long = "calc-precision",
+        help = "The number of decimal places that the 'calculate' builtin rounds floats to"
+    )]
+    pub calc_precision: Option<MaybeUsize>,
 }
 
 #[derive(Debug, Clone)]
@@ -165,10 +209,230 @@ pub struct EnvironmentVariableArgs {
     pub variable: EnvVariable,
 }
 
+#[derive(Parser, Debug)]
+pub struct SetVariableArgs {
+    #[arg(help = "The name of the variable to set (lists all variables if omitted)")]
+    pub name: Option<String>,
+    #[arg(help = "The value to give the variable", default_value = "")]
+    pub value: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct UnsetVariableArgs {
+    #[arg(required = true, help = "The names of the variables to remove")]
+    pub names: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportVariableArgs {
+    #[arg(
+        short = 'u',
+        long = "unset",
+        help = "Remove the variables from the environment instead of setting them"
+    )]
+    pub unset: bool,
+    #[arg(
+        help = "The variables to export, as NAME=VALUE, or as NAME to export an existing variable (lists all variables if omitted)"
+    )]
+    pub variables: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CalculateArgs {
+    #[arg(
+        short = 'p',
+        long = "precision",
+        help = "The number of decimal places to round floats to (overrides the configured precision)"
+    )]
+    pub precision: Option<usize>,
+    #[arg(
+        required = true,
+        allow_hyphen_values = true,
+        trailing_var_arg = true,
+        help = "The expression to calculate, such as '2 * (x + 1.5)' (multiplication must be quoted, or '*' matches file names)"
+    )]
+    pub expression: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SourceArgs {
+    #[arg(help = "The path of the script to run in the current shell")]
+    pub path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct RhaiArgs {
+    #[arg(short = 'f', long = "file", help = "Run the Rhai script at this path")]
+    pub file: Option<PathBuf>,
+    #[arg(
+        required_unless_present = "file",
+        conflicts_with = "file",
+        help = "The Rhai code to run, such as 'print(run(\"pwd\").output)'"
+    )]
+    pub code: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetAliasArgs {
+    #[arg(
+        short = 'p',
+        long = "persist",
+        help = "Save the aliases to the config file so that they are defined in future sessions"
+    )]
+    pub persist: bool,
+    #[arg(
+        help = "The aliases to define, as NAME=COMMAND, or as NAME to show an existing alias (lists all aliases if omitted)"
+    )]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct UnsetAliasArgs {
+    #[arg(short = 'a', long = "all", help = "Remove every alias")]
+    pub all: bool,
+    #[arg(
+        short = 'p',
+        long = "persist",
+        help = "Also remove the aliases from the config file, so that they are not defined in future sessions"
+    )]
+    pub persist: bool,
+    #[arg(
+        required_unless_present = "all",
+        conflicts_with = "all",
+        help = "The names of the aliases to remove"
+    )]
+    pub names: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct AbbreviationArgs {
+    #[arg(
+        short = 'e',
+        long = "erase",
+        num_args = 1..,
+        value_name = "NAME",
+        conflicts_with_all = ["name", "expansion"],
+        help = "Remove the abbreviations with these names"
+    )]
+    pub erase: Vec<String>,
+    #[arg(
+        short = 'p',
+        long = "persist",
+        help = "Save the change to the config file so that it applies to future sessions"
+    )]
+    pub persist: bool,
+    #[arg(help = "The word to abbreviate, such as 'gco' (lists all abbreviations if omitted)")]
+    pub name: Option<String>,
+    #[arg(
+        allow_hyphen_values = true,
+        trailing_var_arg = true,
+        help = "The text that the abbreviation expands to, such as 'git checkout' (shows the abbreviation if omitted)"
+    )]
+    pub expansion: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CommandArgs {
+    #[arg(
+        required = true,
+        allow_hyphen_values = true,
+        trailing_var_arg = true,
+        help = "The builtin or executable to run, followed by its arguments"
+    )]
+    pub command: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct LocalVariableArgs {
+    #[arg(
+        required = true,
+        help = "The variables to make local, with optional values (e.g. 'NAME=value')"
+    )]
+    pub variables: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ReturnArgs {
+    #[arg(
+        allow_negative_numbers = true,
+        help = "The status to return with, which defaults to the status of the last command"
+    )]
+    pub code: Option<i32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BreakArgs {
+    #[arg(
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "The number of enclosing loops to stop"
+    )]
+    pub count: u32,
+}
+
+#[derive(Parser, Debug)]
+pub struct ContinueArgs {
+    #[arg(
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "The number of enclosing loops to go up before starting the next iteration"
+    )]
+    pub count: u32,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListJobsArgs {
+    #[arg(short = 'l', long = "long", help = "Show the process IDs of each job")]
+    pub long: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ForegroundJobArgs {
+    #[arg(
+        help = "The job to bring to the foreground, such as '%1' or '1' (defaults to the current job)"
+    )]
+    pub job: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BackgroundJobArgs {
+    #[arg(
+        help = "The stopped job to continue in the background, such as '%1' or '1' (defaults to the current job)"
+    )]
+    pub job: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct WaitForJobArgs {
+    #[arg(help = "The jobs to wait for, such as '%1' or '1' (waits for every job if omitted)")]
+    pub jobs: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct DisownJobArgs {
+    #[arg(
+        short = 'a',
+        long = "all",
+        help = "Remove every job from the job table"
+    )]
+    pub all: bool,
+    #[arg(
+        help = "The jobs to remove from the job table, such as '%1' or '1' (defaults to the current job)"
+    )]
+    pub jobs: Vec<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct EditPathArgs {
     #[clap(subcommand)]
     pub subcommand: EditPathSubcommand,
+    #[arg(
+        short = 'p',
+        long = "persist",
+        global = true,
+        help = "Save the resulting PATH to the config file so that it is used in future sessions"
+    )]
+    pub persist: bool,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -1,19 +1,28 @@
 use crate::errors::Result;
-use crate::exec::runnable::{Aliases, Runnable};
+use crate::eval::Dispatcher;
+use crate::exec::runnable::Aliases;
+use crate::exec::IoContext;
 use crate::state::ShellState;
 
 /// Represents a builtin function, its name and its aliases
 pub struct Builtin {
     pub true_name: String,
     pub aliases: Aliases,
+    /// Creates the parser for the builtin's arguments, which describes its flags and subcommands
+    arguments: fn() -> clap::Command,
+    /// Runs the builtin, given the `Dispatcher` which is running it, so that builtins like
+    /// 'source' can run other commands the same way
     #[allow(clippy::type_complexity)]
-    function: Box<dyn Fn(&mut ShellState, Vec<&str>) -> Result<()>>,
+    function: Box<dyn Fn(&mut ShellState, &Dispatcher, Vec<&str>, &mut IoContext) -> Result<()>>,
 }
 
 impl Builtin {
-    pub fn new<F: Fn(&mut ShellState, Vec<&str>) -> Result<()> + 'static>(
+    pub fn new<
+        F: Fn(&mut ShellState, &Dispatcher, Vec<&str>, &mut IoContext) -> Result<()> + 'static,
+    >(
         true_name: &str,
         aliases: Vec<&str>,
+        arguments: fn() -> clap::Command,
         function: F,
     ) -> Self {
         let true_name = true_name.to_string();
@@ -23,13 +32,25 @@ impl Builtin {
         Self {
             true_name,
             aliases,
+            arguments,
             function,
         }
     }
-}
 
-impl Runnable for Builtin {
-    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>) -> Result<()> {
-        (self.function)(shell, arguments)
+    /// Gets the parser for the builtin's arguments
+    pub fn arguments(&self) -> clap::Command {
+        (self.arguments)()
+    }
+
+    /// Runs the builtin within the given `Dispatcher`
+    /// The `IoContext` is dropped when the builtin finishes, so that any pipes it holds are closed
+    pub fn run(
+        &self,
+        shell: &mut ShellState,
+        dispatcher: &Dispatcher,
+        arguments: Vec<&str>,
+        mut io: IoContext,
+    ) -> Result<()> {
+        (self.function)(shell, dispatcher, arguments, &mut io)
     }
 }
@@ -8,58 +8,113 @@ Users are free to create their own builtins if they wish to modify the source co
 An executable will only have access to its arguments and environment variables, but not the shell's state, mostly for security reasons.
  */
 
-use std::io::{stderr, BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::os::unix::fs::PermissionsExt;
+use std::io::{stderr, BufRead, BufReader, Read};
 use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 
+use chrono::offset::Local;
+use chrono::DateTime;
 use clap::Parser;
 use crossterm::cursor::MoveTo;
 use crossterm::execute;
-use crossterm::style::{Stylize, style};
+use crossterm::style::Stylize;
 use crossterm::terminal::{self, Clear, ClearType};
 use file_owner::PathExt;
-use chrono::offset::Local;
-use chrono::DateTime;
-use size::{Size, Style};
-
+use size::Size;
 
 use super::args::{
-    ChangeDirectoryArgs, ClearTerminalArgs, ConfigureArgs, DeleteFileArgs, EditPathArgs,
-    EditPathSubcommand, EnvironmentVariableArgs, ExitArgs, ListDirectoryArgs, MakeDirectoryArgs,
-    MakeFileArgs, NextDirectoryArgs, PreviousDirectoryArgs, ReadFileArgs, RunExecutableArgs,
-    WorkingDirectoryArgs,
+    AbbreviationArgs, BackgroundJobArgs, BreakArgs, CalculateArgs, ChangeDirectoryArgs,
+    ClearTerminalArgs, CommandArgs, ConfigureArgs, ContinueArgs, DeleteFileArgs, DisownJobArgs,
+    EditPathArgs, EditPathSubcommand, EnvironmentVariableArgs, ExitArgs, ExportVariableArgs,
+    ForegroundJobArgs, ListDirectoryArgs, ListJobsArgs, LocalVariableArgs, MakeDirectoryArgs,
+    MakeFileArgs, NextDirectoryArgs, PreviousDirectoryArgs, ReadFileArgs, ReturnArgs, RhaiArgs,
+    RunExecutableArgs, SetAliasArgs, SetVariableArgs, SourceArgs, UnsetAliasArgs,
+    UnsetVariableArgs, WaitForJobArgs, WorkingDirectoryArgs,
 };
 use crate::errors::{Handle, Result};
+use crate::eval::{arithmetic, Dispatcher};
 use crate::exec::builtins::args::{
     AppendPathCommand, DeletePathCommand, InsertPathCommand, PrependPathCommand, TestArgs,
 };
-use crate::exec::{Executable, Runnable};
-use crate::state::{EnvVariable, Path, ShellState};
+use crate::exec::{signals, Executable, IoContext, Output, Runnable};
+use crate::state::{
+    is_valid_alias_name, is_valid_variable_name, EnvVariable, JobState, Jump, Path, ShellState,
+    ABBREVIATION_KEY_PREFIX, ALIAS_KEY_PREFIX,
+};
 
 enum DirectoryListPermissionMode {
     Octal,
     String,
 }
 
-pub fn test(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn test(_shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     clap_handle!(TestArgs::try_parse_from(args));
-    println!("{}", "Test command!".yellow());
+    io.stdout.println("Test command!".yellow())
+}
+
+pub fn exit(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(ExitArgs::try_parse_from(args));
+    // * The shell exits once the current command has finished, rather than immediately
+    shell.jump = Some(Jump::Exit(arguments.code.unwrap_or(shell.last_status.code)));
     Ok(())
 }
 
-pub fn exit(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
-    clap_handle!(ExitArgs::try_parse_from(args));
-    std::process::exit(0);
+pub fn return_from_function(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _io: &mut IoContext,
+) -> Result<()> {
+    let arguments = clap_handle!(ReturnArgs::try_parse_from(args));
+    // * A sourced script can also be stopped with 'return', like a function
+    if shell.call_depth == 0 {
+        return Err(builtin_err!(UsedOutsideOfFunction: "return"));
+    }
+
+    shell.jump = Some(Jump::Return(
+        arguments.code.unwrap_or(shell.last_status.code),
+    ));
+    Ok(())
 }
 
-pub fn working_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
-    clap_handle!(WorkingDirectoryArgs::try_parse_from(args));
-    println!("{}", shell.CWD());
+pub fn break_loop(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(BreakArgs::try_parse_from(args));
+    if shell.loop_depth == 0 {
+        return Err(builtin_err!(UsedOutsideOfLoop: "break"));
+    }
+
+    // * A count larger than the number of enclosing loops stops all of them, as in other shells
+    let count = (arguments.count as usize).min(shell.loop_depth);
+    shell.jump = Some(Jump::Break(count));
+    Ok(())
+}
+
+pub fn continue_loop(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(ContinueArgs::try_parse_from(args));
+    if shell.loop_depth == 0 {
+        return Err(builtin_err!(UsedOutsideOfLoop: "continue"));
+    }
+
+    let count = (arguments.count as usize).min(shell.loop_depth);
+    shell.jump = Some(Jump::Continue(count));
     Ok(())
 }
 
-pub fn change_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn working_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    io: &mut IoContext,
+) -> Result<()> {
+    clap_handle!(WorkingDirectoryArgs::try_parse_from(args));
+    io.stdout.println(shell.CWD())
+}
+
+pub fn change_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _io: &mut IoContext,
+) -> Result<()> {
     let arguments = clap_handle!(ChangeDirectoryArgs::try_parse_from(args));
     let history_limit = shell.config.history_limit;
     shell
@@ -70,7 +125,7 @@ pub fn change_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     Ok(())
 }
 
-pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn list_directory(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(ListDirectoryArgs::try_parse_from(&args));
     let show_hidden = arguments.show_hidden;
     let long_view = arguments.long_view;
@@ -112,24 +167,22 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     if !long_view {
         for i in &directories {
             if i.starts_with('.') {
-                print!("{}  ", i.clone().dark_green())
+                io.stdout
+                    .print(format_args!("{}  ", i.clone().dark_green()))?;
             } else {
-                print!("{}  ", i.clone().green())
+                io.stdout.print(format_args!("{}  ", i.clone().green()))?;
             }
         }
 
         for i in &files {
             if i.starts_with('.') {
-                print!("{}  ", i.clone().grey())
+                io.stdout.print(format_args!("{}  ", i.clone().grey()))?;
             } else {
-                print!("{}  ", i.clone().white())
+                io.stdout.print(format_args!("{}  ", i.clone().white()))?;
             }
-
         }
 
-        println!();
-
-        return Ok(());
+        return io.stdout.println("");
     }
 
     directories.append(&mut files);
@@ -139,34 +192,42 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         false => DirectoryListPermissionMode::String,
     };
 
-    list_directory_long(directories, permission_view, path_to_read.clone(), permission_seperator);
-
-    Ok(())
+    list_directory_long(
+        &mut io.stdout,
+        directories,
+        permission_view,
+        path_to_read.clone(),
+        permission_seperator,
+    )
 }
 
-fn list_directory_long(item: Vec<String>, permission_format: DirectoryListPermissionMode, cwd: PathBuf, permission_seperator: bool) {
+fn list_directory_long(
+    output: &mut Output,
+    item: Vec<String>,
+    permission_format: DirectoryListPermissionMode,
+    cwd: PathBuf,
+    permission_seperator: bool,
+) -> Result<()> {
     let path_to_read = cwd;
     let mut file_size_len: usize = 0;
-    let mut file_size_len_last: usize = 0;
     let mut username_len: usize = 0;
-    let mut username_len_last: usize = 0;
 
     for i in &item {
         let path = path_to_read.join(i);
-        let file_size = std::fs::metadata(path.to_path_buf()).unwrap().size();
+        let file_size = std::fs::metadata(&path).unwrap().size();
         let formatted_fsize = Size::from_bytes(file_size).to_string();
 
-        file_size_len_last = formatted_fsize.len();
+        let file_size_len_last = formatted_fsize.len();
 
         if file_size_len_last > file_size_len {
-                file_size_len = file_size_len_last;
+            file_size_len = file_size_len_last;
         }
     }
 
     for i in &item {
         let path = path_to_read.join(i);
-        
-        username_len_last = path.owner().unwrap().to_string().len();
+
+        let username_len_last = path.owner().unwrap().to_string().len();
 
         if username_len_last > username_len {
             username_len = username_len_last;
@@ -175,16 +236,17 @@ fn list_directory_long(item: Vec<String>, permission_format: DirectoryListPermis
 
     for i in &item {
         let path = path_to_read.join(i);
-        let permission_octal =  { 
-            let x = format!("{:o}", std::fs::metadata(path.to_path_buf()).unwrap().permissions().mode()); 
-            x[x.len() - 3..].to_string() 
+        let permission_octal = {
+            let x = format!(
+                "{:o}",
+                std::fs::metadata(&path).unwrap().permissions().mode()
+            );
+            x[x.len() - 3..].to_string()
         };
 
         let permissions = match permission_format {
-            DirectoryListPermissionMode::Octal => {
-                permission_octal.white()
-            },
-                
+            DirectoryListPermissionMode::Octal => permission_octal.white(),
+
             DirectoryListPermissionMode::String => {
                 let permission_str = permission_octal;
                 let mut result = String::new();
@@ -194,15 +256,15 @@ fn list_directory_long(item: Vec<String>, permission_format: DirectoryListPermis
                 let w = "w".white();
                 let x = "x".white();
 
-                let mapping = vec![
+                let mapping = [
                     format!("{dash}{dash}{dash}"),
-                    format!("{dash}{dash}{x}"), 
-                    format!("{dash}{w}{dash}"), 
+                    format!("{dash}{dash}{x}"),
+                    format!("{dash}{w}{dash}"),
                     format!("{dash}{w}{x}"),
                     format!("{r}{dash}{dash}"),
                     format!("{r}{dash}{x}"),
                     format!("{r}{w}{dash}"),
-                    format!("{r}{w}{x}")
+                    format!("{r}{w}{x}"),
                 ];
 
                 for (i, c) in permission_str.chars().enumerate() {
@@ -226,38 +288,62 @@ fn list_directory_long(item: Vec<String>, permission_format: DirectoryListPermis
                 }
 
                 result.white()
-            },
+            }
         };
 
-        let file_size = std::fs::metadata(path.to_path_buf()).unwrap().size();
+        let file_size = std::fs::metadata(&path).unwrap().size();
         let formatted_fsize = Size::from_bytes(file_size);
 
-        println!("{4} {3} {2} {1} {0}", 
-            if i.starts_with('.') { 
-                i.as_str().dark_grey() 
-            } else if i.chars().last().unwrap() == '/' {
+        let owner = path.owner().unwrap().to_string();
+        let owner_column = format!(
+            "{}{}",
+            " ".repeat(username_len - owner.len()),
+            owner.yellow()
+        );
+
+        output.println(format_args!(
+            "{4} {3} {2} {1} {0}",
+            if i.starts_with('.') {
+                i.as_str().dark_grey()
+            } else if i.ends_with('/') {
                 if i.starts_with('.') {
                     i.as_str().dark_green()
                 } else {
-                    i.as_str().green() 
+                    i.as_str().green()
                 }
-            } else { 
-                i.as_str().white() 
-            }, 
-            format!("{}", DateTime::<Local>::from(std::fs::metadata(path.to_path_buf()).unwrap().modified().unwrap()).format("%b %d %Y %T")).dark_cyan(),
-            format!("{}{}", " ".repeat(username_len - path.owner().unwrap().to_string().len()), path.owner().unwrap().to_string().yellow()),
-            if i.chars().last().unwrap() == '/' {
+            } else {
+                i.as_str().white()
+            },
+            format!(
+                "{}",
+                DateTime::<Local>::from(std::fs::metadata(&path).unwrap().modified().unwrap())
+                    .format("%b %d %Y %T")
+            )
+            .dark_cyan(),
+            owner_column,
+            if i.ends_with('/') {
                 let spacing = " ".repeat(file_size_len - "-".len());
                 format!("{}{}", spacing, "-".to_string().dark_grey()).white()
-            } else { 
-                format!("{}{}", formatted_fsize, " ".repeat(file_size_len - formatted_fsize.to_string().len())).green()
+            } else {
+                format!(
+                    "{}{}",
+                    formatted_fsize,
+                    " ".repeat(file_size_len - formatted_fsize.to_string().len())
+                )
+                .green()
             },
             permissions
-        );
+        ))?;
     }
+
+    Ok(())
 }
 
-pub fn previous_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn previous_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _io: &mut IoContext,
+) -> Result<()> {
     clap_handle!(PreviousDirectoryArgs::try_parse_from(args));
     shell
         .environment
@@ -265,7 +351,7 @@ pub fn previous_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()>
         .replace_err(|| state_err!(NoPreviousDirectory))
 }
 
-pub fn next_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn next_directory(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
     clap_handle!(NextDirectoryArgs::try_parse_from(args));
     shell
         .environment
@@ -273,7 +359,7 @@ pub fn next_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         .replace_err(|| state_err!(NoNextDirectory))
 }
 
-pub fn clear_terminal(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn clear_terminal(_shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
     clap_handle!(ClearTerminalArgs::try_parse_from(args));
     let y_size = terminal::size()
         .replace_err_with_msg(
@@ -294,41 +380,79 @@ pub fn clear_terminal(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
 }
 
 // TODO: Add prompt to confirm file overwrite
-pub fn make_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn make_file(_shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(MakeFileArgs::try_parse_from(args));
     fs_err::File::create(&arguments.path)
         .replace_err(|| file_err!(CouldNotCreateFile: arguments.path))?;
     Ok(())
 }
 
-pub fn make_directory(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn make_directory(_shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(MakeDirectoryArgs::try_parse_from(args));
-    fs_err::create_dir(&arguments.path)
-        .replace_err(|| file_err!(CouldNotCreateDirectory: arguments.path))
+    for_each_path(arguments.paths, &mut io.stderr, |path| {
+        fs_err::create_dir(path).replace_err(|| file_err!(CouldNotCreateDirectory: path))
+    })
 }
 
-pub fn delete_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn delete_file(_shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(DeleteFileArgs::try_parse_from(args));
-    fs_err::remove_file(&arguments.path)
-        .replace_err(|| file_err!(CouldNotDeleteFile: arguments.path))
+    for_each_path(arguments.paths, &mut io.stderr, |path| {
+        fs_err::remove_file(path).replace_err(|| file_err!(CouldNotDeleteFile: path))
+    })
 }
 
-pub fn read_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn read_file(_shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(ReadFileArgs::try_parse_from(args));
-    let file_name = arguments.path;
-    let file =
-        fs_err::File::open(&file_name).replace_err(|| file_err!(CouldNotOpenFile: file_name))?;
+    // If no file is provided, the builtin reads from its input instead, such as a pipe
+    if arguments.paths.is_empty() {
+        return copy_lines(&mut io.stdin, &mut io.stdout, "<stdin>".as_ref());
+    }
 
-    let reader = BufReader::new(file);
+    for_each_path(arguments.paths, &mut io.stderr, |file_name| {
+        let mut file =
+            fs_err::File::open(file_name).replace_err(|| file_err!(CouldNotOpenFile: file_name))?;
+        copy_lines(&mut file, &mut io.stdout, file_name)
+    })
+}
+
+/// Runs an action on each of the paths given to a builtin, continuing past any that fail
+// * Only the last error is returned, so any errors before it are written to `stderr` as soon as
+// * they occur
+fn for_each_path(
+    paths: Vec<PathBuf>,
+    stderr: &mut Output,
+    mut action: impl FnMut(&PathBuf) -> Result<()>,
+) -> Result<()> {
+    let mut result = Ok(());
+    for path in &paths {
+        if let Err(error) = action(path) {
+            if let Err(previous) = result {
+                stderr.println(previous)?;
+            }
+
+            result = Err(error);
+        }
+    }
+
+    result
+}
+
+/// Copies a readable source to an output line by line
+fn copy_lines(
+    source: &mut impl Read,
+    output: &mut Output,
+    source_name: &std::path::Path,
+) -> Result<()> {
+    let reader = BufReader::new(source);
     for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        println!("{}", &line);
+        let line = line.replace_err(|| file_err!(CouldNotReadFile: source_name))?;
+        output.println(&line)?;
     }
 
     Ok(())
 }
 
-pub fn run_executable(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn run_executable(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(RunExecutableArgs::try_parse_from(&args));
     let executable_name = arguments.path;
     let executable_path = Path::try_from_path(&executable_name, Some(&shell.environment.HOME))
@@ -338,10 +462,11 @@ pub fn run_executable(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         )?;
 
     // TODO: Fix the usage of args and arg parsing here
-    Executable::new(executable_path).run(shell, args)
+    let io = std::mem::take(io);
+    Executable::new(executable_path).run(shell, args, io)
 }
 
-pub fn configure(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn configure(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(ConfigureArgs::try_parse_from(args));
 
     if let Some(truncation) = arguments.truncation {
@@ -360,40 +485,429 @@ pub fn configure(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         shell.config.show_errors = show_errors.into();
     }
 
+    if let Some(noclobber) = arguments.noclobber {
+        shell.config.noclobber = noclobber.into();
+    }
+
+    if let Some(glob_no_match) = arguments.glob_no_match {
+        shell.config.glob_no_match = glob_no_match;
+    }
+
+    if let Some(glob_dotfiles) = arguments.glob_dotfiles {
+        shell.config.glob_dotfiles = glob_dotfiles.into();
+    }
+
+    if let Some(extglob) = arguments.extglob {
+        shell.config.extglob = extglob.into();
+    }
+
+    if let Some(calc_precision) = arguments.calc_precision {
+        shell.config.calc_precision = calc_precision.into();
+    }
+
     Ok(())
 }
 
-pub fn environment_variable(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn calculate(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(CalculateArgs::try_parse_from(args));
+    // * The expression can be split across arguments, but '*' still has to be quoted
+    // * (e.g. 'calc 2 "*" 3'), since an unquoted '*' is replaced by the names of files
+    let expression = arguments.expression.join(" ");
+    let result = arithmetic::evaluate(&expression, |name| shell.environment.get_variable(name))?;
+
+    let precision = arguments.precision.or(shell.config.calc_precision);
+    io.stdout.println(result.format(precision))
+}
+
+pub fn source(
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+    args: Vec<&str>,
+    io: &mut IoContext,
+) -> Result<()> {
+    let arguments = clap_handle!(SourceArgs::try_parse_from(args));
+    dispatcher.source(shell, &arguments.path, io)?;
+    shell.last_status.into_result()
+}
+
+pub fn rhai(
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+    args: Vec<&str>,
+    io: &mut IoContext,
+) -> Result<()> {
+    let arguments = clap_handle!(RhaiArgs::try_parse_from(args));
+    let script = match arguments.file {
+        Some(path) => {
+            fs_err::read_to_string(&path).replace_err(|| file_err!(CouldNotReadFile: path))?
+        }
+        None => arguments.code.unwrap_or_default(),
+    };
+
+    dispatcher.eval_rhai(shell, &script, &io.stdout)
+}
+
+pub fn environment_variable(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    io: &mut IoContext,
+) -> Result<()> {
     let arguments = clap_handle!(EnvironmentVariableArgs::try_parse_from(args));
     use EnvVariable::*;
     match arguments.variable {
-        USER => println!("{}", shell.environment.USER),
-        HOME => println!("{}", shell.environment.HOME.display()),
-        CWD => println!("{}", shell.CWD()),
+        USER => io.stdout.println(&shell.environment.USER),
+        HOME => io.stdout.println(shell.environment.HOME.display()),
+        CWD => io.stdout.println(shell.CWD()),
         PATH => {
             for (i, path) in shell.environment.PATH().iter().enumerate() {
-                println!("[{i}]: {path}");
+                io.stdout.println(format_args!("[{i}]: {path}"))?;
             }
+
+            Ok(())
+        }
+    }
+}
+
+pub fn set_variable(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(SetVariableArgs::try_parse_from(args));
+    let Some(name) = arguments.name else {
+        // List the variables in alphabetical order, since they are stored in no particular order
+        let mut variables: Vec<_> = shell.environment.custom_variables().iter().collect();
+        variables.sort();
+        for (name, value) in variables {
+            io.stdout.println(format_args!("{name}={value}"))?;
+        }
+
+        return Ok(());
+    };
+
+    shell.environment.set_variable(&name, &arguments.value)
+}
+
+pub fn set_alias(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(SetAliasArgs::try_parse_from(args));
+    if arguments.aliases.is_empty() {
+        // List the aliases in alphabetical order, since they are stored in no particular order
+        let mut aliases: Vec<_> = shell.config.aliases.iter().collect();
+        aliases.sort();
+        for (name, command) in aliases {
+            io.stdout.println(format_args!("{name}={command}"))?;
+        }
+
+        return Ok(());
+    }
+
+    for alias in arguments.aliases {
+        // An alias without a command is shown instead of being defined
+        let Some((name, command)) = alias.split_once('=') else {
+            let command = shell
+                .config
+                .aliases
+                .get(&alias)
+                .replace_err(|| state_err!(UnknownAlias: alias))?;
+            io.stdout.println(format_args!("{alias}={command}"))?;
+            continue;
+        };
+
+        if !is_valid_alias_name(name) {
+            return Err(state_err!(InvalidAliasName: name));
         }
+
+        if arguments.persist {
+            shell
+                .config
+                .persist(&format!("{ALIAS_KEY_PREFIX}{name}"), command)?;
+        }
+
+        shell
+            .config
+            .aliases
+            .insert(name.to_owned(), command.to_owned());
     }
 
     Ok(())
 }
 
-pub fn edit_path(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn unset_alias(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(UnsetAliasArgs::try_parse_from(args));
+    let names = match arguments.all {
+        true => shell.config.aliases.keys().cloned().collect(),
+        false => arguments.names,
+    };
+
+    for name in names {
+        if shell.config.aliases.remove(&name).is_none() {
+            return Err(state_err!(UnknownAlias: name));
+        }
+
+        if arguments.persist {
+            shell.config.forget(&format!("{ALIAS_KEY_PREFIX}{name}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn abbreviation(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(AbbreviationArgs::try_parse_from(args));
+    if !arguments.erase.is_empty() {
+        for name in arguments.erase {
+            if shell.config.abbreviations.remove(&name).is_none() {
+                return Err(state_err!(UnknownAbbreviation: name));
+            }
+
+            if arguments.persist {
+                shell
+                    .config
+                    .forget(&format!("{ABBREVIATION_KEY_PREFIX}{name}"))?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    let Some(name) = arguments.name else {
+        // List the abbreviations in alphabetical order, since they are stored in no particular order
+        let mut abbreviations: Vec<_> = shell.config.abbreviations.iter().collect();
+        abbreviations.sort();
+        for (name, expansion) in abbreviations {
+            io.stdout.println(format_args!("{name} {expansion}"))?;
+        }
+
+        return Ok(());
+    };
+
+    // An abbreviation without an expansion is shown instead of being defined
+    if arguments.expansion.is_empty() {
+        let expansion = shell
+            .config
+            .abbreviations
+            .get(&name)
+            .replace_err(|| state_err!(UnknownAbbreviation: name))?;
+        io.stdout.println(format_args!("{name} {expansion}"))?;
+        return Ok(());
+    }
+
+    // * The line editor only looks at the single word before the cursor when expanding them
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(state_err!(InvalidAbbreviationName: name));
+    }
+
+    let expansion = arguments.expansion.join(" ");
+    if arguments.persist {
+        shell
+            .config
+            .persist(&format!("{ABBREVIATION_KEY_PREFIX}{name}"), &expansion)?;
+    }
+
+    shell.config.abbreviations.insert(name, expansion);
+    Ok(())
+}
+
+pub fn command(
+    shell: &mut ShellState,
+    dispatcher: &Dispatcher,
+    args: Vec<&str>,
+    io: &mut IoContext,
+) -> Result<()> {
+    let arguments = clap_handle!(CommandArgs::try_parse_from(args));
+    let command = arguments
+        .command
+        .iter()
+        .map(|argument| argument.as_str())
+        .collect();
+    dispatcher.run_command(shell, command, std::mem::take(io))
+}
+
+pub fn local_variable(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(LocalVariableArgs::try_parse_from(args));
+    let Some(scope) = shell.local_scopes.last_mut() else {
+        return Err(builtin_err!(UsedOutsideOfFunction: "local-variable"));
+    };
+
+    for variable in arguments.variables {
+        let (name, value) = variable.split_once('=').unwrap_or((&variable, ""));
+        if !is_valid_variable_name(name) {
+            return Err(state_err!(InvalidVariableName: name));
+        }
+
+        // * Only the value from before the function was called is kept, so that it is the one
+        // * restored, even if the variable is made local more than once
+        if !scope.contains_key(name) {
+            scope.insert(name.to_owned(), shell.environment.get_variable(name));
+        }
+
+        shell.environment.set_variable(name, value)?;
+    }
+
+    Ok(())
+}
+
+pub fn unset_variable(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(UnsetVariableArgs::try_parse_from(args));
+    // * Unsetting a variable which does not exist is not an error, which matches other shells
+    for name in arguments.names {
+        shell.environment.unset_variable(&name)?;
+    }
+
+    Ok(())
+}
+
+pub fn export_variable(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(ExportVariableArgs::try_parse_from(args));
+    if arguments.variables.is_empty() {
+        // List the variables in alphabetical order, since they are stored in no particular order
+        let mut variables: Vec<_> = shell.environment.exported_variables().into_iter().collect();
+        variables.sort();
+        for (name, value) in variables {
+            io.stdout.println(format_args!("{name}={value}"))?;
+        }
+
+        return Ok(());
+    }
+
+    for variable in arguments.variables {
+        match (arguments.unset, variable.split_once('=')) {
+            (true, _) => shell.environment.unset_variable(&variable)?,
+            (false, Some((name, value))) => shell.environment.export_variable(name, Some(value))?,
+            (false, None) => shell.environment.export_variable(&variable, None)?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn edit_path(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
     let arguments = clap_handle!(EditPathArgs::try_parse_from(args));
     use EditPathSubcommand::*;
     match arguments.subcommand {
-        Append(AppendPathCommand { path }) => shell
-            .environment
-            .PATH_append(Path::try_from_path(&path, Some(&shell.environment.HOME))?),
-        Prepend(PrependPathCommand { path }) => shell
-            .environment
-            .PATH_prepend(Path::try_from_path(&path, Some(&shell.environment.HOME))?),
-        Insert(InsertPathCommand { index, path }) => shell.environment.PATH_insert(
-            index,
-            Path::try_from_path(&path, Some(&shell.environment.HOME))?,
-        ),
+        Append(AppendPathCommand { path }) => shell.environment.PATH_append(&path),
+        Prepend(PrependPathCommand { path }) => shell.environment.PATH_prepend(&path),
+        Insert(InsertPathCommand { index, path }) => shell.environment.PATH_insert(index, &path),
         Delete(DeletePathCommand { index }) => shell.environment.PATH_delete(index),
+    }?;
+
+    // * The whole PATH is saved rather than the edit itself, so that index-based edits
+    // * cannot apply to the wrong path if the inherited PATH changes between sessions
+    if arguments.persist {
+        shell
+            .config
+            .persist("path", &shell.environment.PATH_string())?;
+    }
+
+    Ok(())
+}
+
+pub fn list_jobs(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(ListJobsArgs::try_parse_from(args));
+    shell.jobs.poll();
+    for job in shell.jobs.jobs() {
+        match arguments.long {
+            true => {
+                let pids: Vec<String> = job.pids().iter().map(|pid| pid.to_string()).collect();
+                io.stdout
+                    .println(format_args!("{job}  (PIDs {})", pids.join(", ")))?;
+            }
+            false => io.stdout.println(job)?,
+        }
+    }
+
+    // * Any finished jobs have now been reported, so they are removed from the job table
+    shell.jobs.take_finished();
+    Ok(())
+}
+
+pub fn foreground_job(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(ForegroundJobArgs::try_parse_from(args));
+    let id = shell.jobs.find(arguments.job.as_deref())?;
+    let job = shell
+        .jobs
+        .get_mut(id)
+        .replace_err(|| state_err!(UnknownJob: id.to_string()))?;
+    io.stdout.println(job.command())?;
+
+    // The job is given the terminal, so that keypresses like Ctrl-C are sent to it
+    if let Some(process_group) = job.process_group() {
+        signals::set_foreground(process_group);
     }
+
+    let result = job.resume().and_then(|_| job.wait());
+    signals::reclaim_terminal();
+    result?;
+
+    match job.state() {
+        JobState::Stopped => Err(executable_err!(JobStopped: id)),
+        JobState::Done(status) => {
+            shell.jobs.remove(id);
+            status.into_result()
+        }
+        JobState::Running => Ok(()),
+    }
+}
+
+pub fn background_job(shell: &mut ShellState, args: Vec<&str>, io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(BackgroundJobArgs::try_parse_from(args));
+    let id = shell.jobs.find(arguments.job.as_deref())?;
+    let job = shell
+        .jobs
+        .get_mut(id)
+        .replace_err(|| state_err!(UnknownJob: id.to_string()))?;
+
+    job.resume()?;
+    io.stdout
+        .println(format_args!("[{}] {} &", id, job.command()))
+}
+
+pub fn wait_for_job(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(WaitForJobArgs::try_parse_from(args));
+    let ids = match arguments.jobs.is_empty() {
+        true => shell.jobs.jobs().iter().map(|job| job.id).collect(),
+        false => arguments
+            .jobs
+            .iter()
+            .map(|specifier| shell.jobs.find(Some(specifier)))
+            .collect::<Result<Vec<usize>>>()?,
+    };
+
+    // * Waiting stops if Ctrl-C is pressed, which would otherwise leave the shell waiting until
+    // * the jobs finish
+    shell.interrupted.store(false, Ordering::Relaxed);
+    let mut result = Ok(());
+    for id in ids {
+        let Some(job) = shell.jobs.get_mut(id) else {
+            continue;
+        };
+
+        signals::interruptible(|| job.wait_interruptible(&shell.interrupted))?;
+        result = match job.state() {
+            JobState::Stopped => Err(executable_err!(JobStopped: id)),
+            JobState::Done(status) => {
+                shell.jobs.remove(id);
+                status.into_result()
+            }
+            JobState::Running => Ok(()),
+        };
+    }
+
+    // Only the result of the last job is used as the result of the builtin
+    result
+}
+
+pub fn disown_job(shell: &mut ShellState, args: Vec<&str>, _io: &mut IoContext) -> Result<()> {
+    let arguments = clap_handle!(DisownJobArgs::try_parse_from(args));
+    let ids = match (arguments.all, arguments.jobs.is_empty()) {
+        (true, _) => shell.jobs.jobs().iter().map(|job| job.id).collect(),
+        (false, true) => vec![shell.jobs.find(None)?],
+        (false, false) => arguments
+            .jobs
+            .iter()
+            .map(|specifier| shell.jobs.find(Some(specifier)))
+            .collect::<Result<Vec<usize>>>()?,
+    };
+
+    for id in ids {
+        shell.jobs.disown(id);
+    }
+
+    Ok(())
 }
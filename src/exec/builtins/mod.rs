@@ -1,3 +1,3 @@
-mod args;
+pub mod args;
 pub mod command;
 pub mod functions;
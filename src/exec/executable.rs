@@ -1,8 +1,13 @@
-use std::process::Command as Process;
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command as Process};
+use std::time::Instant;
 
-use super::Runnable;
+use nix::unistd::Pid;
+
+use super::{signals, IoContext, Runnable};
 use crate::errors::{Handle, Result};
-use crate::state::{Path, ShellState};
+use crate::state::{Job, JobState, Path, ShellState};
 
 /// Represents an executable (external command)
 pub struct Executable {
@@ -17,34 +22,73 @@ impl Executable {
     pub fn new(path: Path) -> Self {
         Self { path }
     }
-}
 
-impl Runnable for Executable {
-    // * Executables do not have access to the shell state, but the context argument is required by the Runnable trait
-    fn run(&self, _shell: &mut ShellState, arguments: Vec<&str>) -> Result<()> {
+    /// Starts the executable with the given streams and environment variables, without waiting for it to finish
+    /// If a process group is given, the executable joins it, or starts a new one if it is zero
+    // * Pipelines use this directly so that every executable in the pipeline runs at the same time
+    pub fn spawn(
+        &self,
+        arguments: Vec<&str>,
+        io: IoContext,
+        environment: HashMap<String, String>,
+        process_group: Option<Pid>,
+    ) -> Result<Child> {
         // Create the Process, pass the provided arguments to it, and execute it
         // * Executable name has to be removed because `std::process::Command`
         // * automatically adds the executable name as the first argument
-        let mut process = Process::new(self.path.path())
+        // * The environment is cleared first, so that variables removed from the shell are not inherited
+        // * The `Process` is dropped after spawning, which closes the shell's copies of any pipes
+        let mut process = Process::new(self.path.path());
+        process
             .args(&arguments[1..])
+            .env_clear()
+            .envs(environment)
+            .stdin(io.stdin.into_stdio()?)
+            .stdout(io.stdout.into_stdio()?)
+            .stderr(io.stderr.into_stdio()?);
+
+        if let Some(process_group) = process_group {
+            process.process_group(process_group.as_raw());
+        }
+
+        process
             .spawn()
-            .replace_err(|| executable_err!(PathNoLongerExists: self.path))?;
-
-        let status = process
-            .wait()
-            .replace_err(|| executable_err!(CouldNotWait))?;
-
-        match status.success() {
-            true => Ok(()),
-            false => {
-                // * 126 is a special exit code that means that the command was found but could not be executed
-                // * as per https://tldp.org/LDP/abs/html/exitcodes.html
-                // * It can be assumed that the command was found here because the Executable path must have been validated already
-                // * Otherwise it could be a 127 for "command not found"
-                Err(executable_err!(FailedToExecute:
-                    status.code().unwrap_or(126) as isize
-                ))
-            }
+            .replace_err(|| executable_err!(PathNoLongerExists: self.path))
+    }
+}
+
+impl Runnable for Executable {
+    // * Executables do not have access to the shell state, only to the variables it exports
+    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>, io: IoContext) -> Result<()> {
+        let started = Instant::now();
+        let command = arguments.join(" ");
+        let process_group = signals::is_interactive().then(|| Pid::from_raw(0));
+        let process = self.spawn(
+            arguments,
+            io,
+            shell.environment.exported_variables(),
+            process_group,
+        )?;
+
+        // The executable is run in the foreground, so that Ctrl-C only interrupts the executable
+        let pid = Pid::from_raw(process.id() as i32);
+        if process_group.is_some() {
+            signals::set_foreground(pid);
+        }
+
+        // * The executable leads its own process group, if it was given one
+        let mut job = Job::new(command, process_group.map(|_| pid));
+        job.add_process(pid, started);
+        let result = job.wait();
+        signals::reclaim_terminal();
+        result?;
+
+        match job.state() {
+            // An executable which is stopped (e.g. with Ctrl-Z) is kept as a job,
+            // so it can be resumed later
+            JobState::Stopped => Err(executable_err!(JobStopped: shell.jobs.add(job))),
+            JobState::Done(status) => status.into_result(),
+            JobState::Running => Ok(()),
         }
     }
 }
@@ -0,0 +1,232 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Cursor, PipeReader, PipeWriter, Read, Write};
+use std::os::fd::AsFd;
+use std::process::Stdio;
+use std::thread::{self, JoinHandle};
+
+use crate::errors::{Handle, Result};
+
+/// The standard streams that a command reads from and writes to
+/// Builtins read and write through these directly, and executables have them attached as their
+/// stdin, stdout and stderr
+pub struct IoContext {
+    pub stdin: Input,
+    pub stdout: Output,
+    pub stderr: Output,
+}
+
+impl Default for IoContext {
+    /// Creates an `IoContext` which uses the shell's own standard streams
+    fn default() -> Self {
+        Self {
+            stdin: Input::Inherit,
+            stdout: Output::Stdout,
+            stderr: Output::Stderr,
+        }
+    }
+}
+
+impl IoContext {
+    /// Replaces the input stream with the given file descriptor number
+    pub fn set_input(&mut self, fd: u32, input: Input) -> Result<()> {
+        match fd {
+            0 => self.stdin = input,
+            _ => return Err(dispatch_err!(InvalidFileDescriptor: fd.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the output stream with the given file descriptor number
+    pub fn set_output(&mut self, fd: u32, output: Output) -> Result<()> {
+        match fd {
+            1 => self.stdout = output,
+            2 => self.stderr = output,
+            _ => return Err(dispatch_err!(InvalidFileDescriptor: fd.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Makes one file descriptor refer to the same stream as another (e.g. '2>&1')
+    pub fn duplicate(&mut self, fd: u32, target_fd: u32) -> Result<()> {
+        let output = match target_fd {
+            // * Standard input can only be duplicated onto itself, which has no effect
+            0 if fd == 0 => return Ok(()),
+            1 => self.stdout.try_clone(),
+            2 => self.stderr.try_clone(),
+            _ => return Err(dispatch_err!(InvalidFileDescriptor: target_fd.to_string())),
+        };
+
+        let output = output.replace_err(|| executable_err!(CouldNotCreatePipe))?;
+        self.set_output(fd, output)
+    }
+}
+
+/// The source that a command reads its input from
+pub enum Input {
+    /// The shell's standard input
+    Inherit,
+    /// The reading end of a pipe from another command
+    Pipe(PipeReader),
+    /// Data which has already been read into memory, such as the output of a previous builtin
+    Buffer(Cursor<Vec<u8>>),
+    /// A file opened by a redirection
+    File(File),
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Inherit => io::stdin().read(buf),
+            Self::Pipe(reader) => reader.read(buf),
+            Self::Buffer(buffer) => buffer.read(buf),
+            Self::File(file) => file.read(buf),
+        }
+    }
+}
+
+impl Input {
+    /// Converts the `Input` into a `Stdio` which can be attached to an executable
+    pub fn into_stdio(self) -> Result<Stdio> {
+        match self {
+            Self::Inherit => Ok(Stdio::inherit()),
+            Self::Pipe(reader) => Ok(reader.into()),
+            Self::File(file) => Ok(file.into()),
+            Self::Buffer(buffer) => Ok(buffer_to_pipe(buffer)?.into()),
+        }
+    }
+
+    /// Creates a new `Input` which reads from the same source, so that several commands can read
+    /// from it one after another, each continuing from where the last one stopped
+    // * Data in memory cannot be shared this way, so it is moved into a pipe first
+    pub fn try_clone(&mut self) -> Result<Self> {
+        let input = match self {
+            Self::Inherit => Ok(Self::Inherit),
+            Self::Pipe(reader) => reader.try_clone().map(Self::Pipe),
+            Self::File(file) => file.try_clone().map(Self::File),
+            Self::Buffer(buffer) => {
+                let reader = buffer_to_pipe(std::mem::take(buffer))?;
+                let input = reader.try_clone().map(Self::Pipe);
+                *self = Self::Pipe(reader);
+                input
+            }
+        };
+
+        input.replace_err(|| executable_err!(CouldNotCreatePipe))
+    }
+}
+
+/// Writes the rest of a buffer to a new pipe, giving the reading end of it
+// * The buffer is written to the pipe from a separate thread, so that large buffers do not block
+// * the shell while the command reading them has not yet done so
+fn buffer_to_pipe(buffer: Cursor<Vec<u8>>) -> Result<PipeReader> {
+    let (reader, mut writer) = io::pipe().replace_err(|| executable_err!(CouldNotCreatePipe))?;
+    let position = buffer.position() as usize;
+    let data = buffer.into_inner();
+    thread::spawn(move || {
+        // * The command may exit without reading all of its input, which is not an error
+        let _ = writer.write_all(data.get(position..).unwrap_or_default());
+    });
+
+    Ok(reader)
+}
+
+/// The destination that a command writes its output to
+pub enum Output {
+    /// The shell's standard output
+    Stdout,
+    /// The shell's standard error
+    Stderr,
+    /// The writing end of a pipe to another command
+    Pipe(PipeWriter),
+    /// A file opened by a redirection
+    File(File),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout => io::stdout().write(buf),
+            Self::Stderr => io::stderr().write(buf),
+            Self::Pipe(writer) => writer.write(buf),
+            Self::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().flush(),
+            Self::Stderr => io::stderr().flush(),
+            Self::Pipe(writer) => writer.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+impl Output {
+    /// Writes a value to the output, similarly to `print!()`
+    pub fn print(&mut self, value: impl Display) -> Result<()> {
+        write!(self, "{}", value)
+            .and_then(|_| self.flush())
+            .replace_err(|| builtin_err!(CouldNotWriteOutput))
+    }
+
+    /// Writes a value to the output followed by a newline, similarly to `println!()`
+    pub fn println(&mut self, value: impl Display) -> Result<()> {
+        self.print(format_args!("{}\n", value))
+    }
+
+    /// Converts the `Output` into a `Stdio` which can be attached to an executable
+    pub fn into_stdio(self) -> Result<Stdio> {
+        let stdio = match self {
+            Self::Stdout => io::stdout().as_fd().try_clone_to_owned().map(Stdio::from),
+            Self::Stderr => io::stderr().as_fd().try_clone_to_owned().map(Stdio::from),
+            Self::Pipe(writer) => Ok(writer.into()),
+            Self::File(file) => Ok(file.into()),
+        };
+
+        stdio.replace_err(|| executable_err!(CouldNotCreatePipe))
+    }
+
+    /// Creates a new `Output` which writes to the same destination
+    pub fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Self::Stdout => Ok(Self::Stdout),
+            Self::Stderr => Ok(Self::Stderr),
+            Self::Pipe(writer) => writer.try_clone().map(Self::Pipe),
+            Self::File(file) => file.try_clone().map(Self::File),
+        }
+    }
+}
+
+/// Collects everything written to a pipe into memory on a background thread
+/// This allows a builtin to write any amount of output without blocking, even when the command
+/// reading that output will not run until the builtin has finished
+pub struct Capture {
+    reader_thread: JoinHandle<Vec<u8>>,
+}
+
+impl Capture {
+    /// Creates a `Capture` along with the `Output` which feeds into it
+    pub fn new() -> Result<(Self, Output)> {
+        let (mut reader, writer) =
+            io::pipe().replace_err(|| executable_err!(CouldNotCreatePipe))?;
+        let reader_thread = thread::spawn(move || {
+            let mut data = Vec::new();
+            // * A read error can only occur once every writer is gone, so whatever was read
+            // * up until that point is kept
+            let _ = reader.read_to_end(&mut data);
+            data
+        });
+
+        Ok((Self { reader_thread }, Output::Pipe(writer)))
+    }
+
+    /// Waits for every copy of the associated `Output` to be dropped and returns the captured data
+    // $ This will block forever if the associated `Output` is still alive
+    pub fn finish(self) -> Vec<u8> {
+        self.reader_thread.join().unwrap_or_default()
+    }
+}
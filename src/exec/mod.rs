@@ -1,8 +1,12 @@
 mod builtins;
 mod executable;
+mod io;
 mod runnable;
+pub mod signals;
 
+pub use builtins::args as builtin_args;
 pub use builtins::command::Builtin;
 pub use builtins::functions as builtin_funcs;
 pub use executable::Executable;
+pub use io::{Capture, Input, IoContext, Output};
 pub use runnable::Runnable;
@@ -1,10 +1,13 @@
+use super::IoContext;
 use crate::errors::Result;
 use crate::state::ShellState;
 
-/// Represents either a builtin (internal command) or an executable (external command)
+/// Represents an executable (external command)
 /// A `Runnable` may be executed by calling its `.run()` method
+/// The `IoContext` provides the streams that the command reads from and writes to, and is
+/// dropped when the command finishes so that any pipes it holds are closed
 pub trait Runnable {
-    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>) -> Result<()>;
+    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>, io: IoContext) -> Result<()>;
 }
 
 /// Wrapper type that makes it easier to read code related to builtins
@@ -25,4 +28,8 @@ impl Aliases {
     pub fn contains(&self, alias: &str) -> bool {
         self.aliases.contains(&alias.to_string())
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.aliases.iter().map(String::as_str)
+    }
 }
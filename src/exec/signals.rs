@@ -0,0 +1,105 @@
+use std::io::{self, IsTerminal};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use nix::libc;
+use nix::sys::signal::{self as nix_signal, SigHandler, SigSet, Signal};
+use nix::unistd::{self, Pid};
+use signal_hook::consts::{SIGINT, SIGQUIT, SIGTSTP};
+
+use crate::errors::{Handle, Result};
+
+/// Whether this is a copy of the shell running a background job, set by `init_background()`
+static IN_BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+/// Checks whether the shell is reading from a terminal, in which case it controls which
+/// process group receives the signals sent by keypresses like Ctrl-C
+// * A background job shares the terminal, but must never take it from the shell that started it
+pub fn is_interactive() -> bool {
+    !IN_BACKGROUND.load(Ordering::Relaxed) && io::stdin().is_terminal()
+}
+
+/// Prepares the shell to run commands in the foreground of the terminal
+/// The shell is placed in its own process group, and is no longer stopped or killed by
+/// Ctrl-C (SIGINT), Ctrl-\ (SIGQUIT) or Ctrl-Z (SIGTSTP)
+/// The given flag is set whenever the shell receives SIGINT
+pub fn init(interrupted: Arc<AtomicBool>) -> Result<()> {
+    if !is_interactive() {
+        return Ok(());
+    }
+
+    // * Handlers are registered instead of ignoring the signals, because ignored signals stay
+    // * ignored in the processes that the shell starts, whereas handlers are reset to the default
+    // * The flag for SIGQUIT and SIGTSTP is never read, since they are only meant to be absorbed
+    let absorbed = Arc::new(AtomicBool::new(false));
+    for (signal, flag) in [
+        (SIGINT, interrupted),
+        (SIGQUIT, absorbed.clone()),
+        (SIGTSTP, absorbed),
+    ] {
+        signal_hook::flag::register(signal, flag)
+            .replace_err(|| state_err!(CouldNotHandleSignals))?;
+    }
+
+    // * This fails if the shell is the leader of its session, which already has its own group
+    let _ = unistd::setpgid(Pid::from_raw(0), Pid::from_raw(0));
+    reclaim_terminal();
+    Ok(())
+}
+
+/// Prepares a copy of the shell to run a background job
+/// The copy is placed in its own process group, and signals are handled as they would be by any
+/// other program, so that the job can be stopped or killed as a whole
+pub fn init_background() {
+    IN_BACKGROUND.store(true, Ordering::Relaxed);
+    let _ = unistd::setpgid(Pid::from_raw(0), Pid::from_raw(0));
+    for signal in [Signal::SIGINT, Signal::SIGQUIT, Signal::SIGTSTP] {
+        // * SAFETY: Restoring the default action does not run any code inside of the shell
+        let _ = unsafe { nix_signal::signal(signal, SigHandler::SigDfl) };
+    }
+}
+
+/// Makes a process group the foreground process group of the terminal,
+/// so that it receives the signals sent by keypresses like Ctrl-C instead of the shell
+pub fn set_foreground(process_group: Pid) {
+    if !is_interactive() {
+        return;
+    }
+
+    // * A process outside of the foreground process group is stopped with SIGTTOU when it changes
+    // * the foreground process group, unless it blocks that signal
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGTTOU);
+
+    // * Failing to hand over the terminal only means that signals go to the shell instead,
+    // * which it already absorbs, so errors are ignored
+    let _ = signals.thread_block();
+    let _ = unistd::tcsetpgrp(io::stdin().as_raw_fd(), process_group);
+    let _ = signals.thread_unblock();
+}
+
+/// Makes the shell the foreground process group of the terminal again
+pub fn reclaim_terminal() {
+    set_foreground(unistd::getpgrp());
+}
+
+/// Runs a blocking call, such as waiting for a process, in a way that lets Ctrl-C (SIGINT) stop it
+/// Normally the call is restarted once the shell has handled the signal, but here it returns
+/// early with `EINTR` instead, so the caller can check whether the shell was interrupted
+pub fn interruptible<T>(call: impl FnOnce() -> T) -> T {
+    // * The handler registered in `init()` is kept, and only the flag which restarts calls
+    // * interrupted by it is removed while the call runs
+    // * SAFETY: The actions are only read and written through pointers to local variables
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sigaction(libc::SIGINT, std::ptr::null(), &mut action) } != 0 {
+        return call();
+    }
+
+    let mut interruptible_action = action;
+    interruptible_action.sa_flags &= !libc::SA_RESTART;
+    unsafe { libc::sigaction(libc::SIGINT, &interruptible_action, std::ptr::null_mut()) };
+    let result = call();
+    unsafe { libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut()) };
+    result
+}
@@ -1,3 +1,4 @@
+mod cli;
 #[macro_use]
 mod errors;
 mod eval;
@@ -6,13 +7,24 @@ mod exec;
 mod plugins;
 mod state;
 
-use errors::{Result, RushError};
-use eval::{Dispatcher, LineEditor};
+use std::io::{self, Read};
+
+use clap::Parser;
+
+use cli::CommandLineArgs;
+use errors::{Handle, Result, RushError};
+use eval::{is_rhai_script, Dispatcher, LineEditor};
+use exec::IoContext;
 #[cfg(feature = "plugin")]
 use plugins::host::PluginHost;
-use state::ShellState;
+use state::{Jump, ShellState, INIT_SCRIPT, LOGOUT_SCRIPT, PROFILE_SCRIPT};
 
 fn main() {
+    let args = CommandLineArgs::parse();
+    let name = std::env::args().next().unwrap_or_else(|| "rush".to_owned());
+    // * The login program starts login shells with a '-' before their name (e.g. '-rush')
+    let login = args.login || name.starts_with('-');
+
     // The ShellState type stores all of the state for the shell, including its configuration,
     // its environment, and other miscellaneous data like command history
     let shell = match ShellState::new() {
@@ -20,32 +32,134 @@ fn main() {
         Err(err) => crash_with_error(err),
     };
 
+    {
+        let mut shell = shell.write().unwrap();
+        shell.name = args.script_name().unwrap_or(name);
+        shell.arguments = args.arguments.clone();
+        shell.interactive =
+            args.command.is_none() && args.script.is_none() && exec::signals::is_interactive();
+    }
+
+    // Signals sent by keypresses like Ctrl-C are directed at running commands instead of the shell
+    let interrupted = shell.read().unwrap().interrupted.clone();
+    if let Err(err) = exec::signals::init(interrupted) {
+        crash_with_error(err);
+    }
+
     #[cfg(feature = "plugin")]
     let _plugins = PluginHost::new(shell.clone());
 
+    // The Dispatcher type is responsible for resolving command names to actual function calls,
+    // or executables if needed, and then invoking them with the given arguments
+    let dispatcher = Dispatcher::default();
+
+    if login {
+        run_user_script(&dispatcher, &mut shell.write().unwrap(), PROFILE_SCRIPT);
+    }
+
+    // Commands given with '-c', in a script file, or piped into the shell are run without prompting
+    // * A script's shebang line (e.g. '#!/usr/bin/env rush') is skipped over as a comment
+    // Rhai scripts are run by the scripting engine rather than being read as shell commands
+    if let (None, Some(path)) = (&args.command, &args.script) {
+        if is_rhai_script(path) {
+            let mut shell = shell.write().unwrap();
+            handle_error(dispatcher.source(&mut shell, path, &mut IoContext::default()));
+            exit_shell(&dispatcher, &mut shell, login);
+        }
+    }
+
+    if let Some(script) = read_script(args) {
+        let mut shell = shell.write().unwrap();
+        dispatcher.eval_script(&mut shell, &script, &mut IoContext::default());
+        exit_shell(&dispatcher, &mut shell, login);
+    }
+
+    run_user_script(&dispatcher, &mut shell.write().unwrap(), INIT_SCRIPT);
+
     // The LineEditor type is responsible for reading lines of input from the user, storing history,
     // providing tab completion and other line-editing features
-    let mut line_editor = match LineEditor::new("./config/history.rush") {
+    let mut line_editor = match LineEditor::new("./config/history.rush", &dispatcher) {
         Ok(editor) => editor,
         Err(err) => crash_with_error(err),
     };
 
-    // The Dispatcher type is responsible for resolving command names to actual function calls,
-    // or executables if needed, and then invoking them with the given arguments
-    let dispatcher = Dispatcher::default();
-
     loop {
-        let line = line_editor.prompt_and_read_line(&shell.read().unwrap());
+        if shell.read().unwrap().jump.is_some() {
+            break;
+        }
+
+        report_finished_jobs(&mut shell.write().unwrap());
+        let Some(line) = line_editor.prompt_and_read_line(&shell.read().unwrap()) else {
+            break;
+        };
+
         let status = dispatcher.eval(&mut shell.write().unwrap(), &line);
-        handle_error(status, &mut shell.write().unwrap());
+        handle_error(status);
     }
+
+    exit_shell(&dispatcher, &mut shell.write().unwrap(), true);
 }
 
-// Handles the return value of running a builtin or executable, setting flags and/or printing errors
-fn handle_error(potential_error: Result<()>, shell: &mut ShellState) {
-    shell.last_command_succeeded = potential_error.is_ok();
+// Runs one of the scripts in the user's configuration directory, if it exists
+fn run_user_script(dispatcher: &Dispatcher, shell: &mut ShellState, name: &str) {
+    let path = shell.environment.HOME.join(name);
+    if path.is_file() {
+        handle_error(dispatcher.source(shell, &path, &mut IoContext::default()));
+    }
+}
+
+// Exits the shell with the code given to the 'exit' builtin, or the status of the last command
+// The logout script is run first if requested, which is the case for interactive and login shells
+fn exit_shell(dispatcher: &Dispatcher, shell: &mut ShellState, logout: bool) -> ! {
+    // * The exit code is taken so that the logout script is not skipped as if it had run 'exit'
+    let exit_code = match shell.jump.take() {
+        Some(Jump::Exit(code)) => Some(code),
+        _ => None,
+    };
+    if logout {
+        run_user_script(dispatcher, shell, LOGOUT_SCRIPT);
+    }
+
+    std::process::exit(exit_code.unwrap_or(shell.last_status.code));
+}
+
+// Reads the commands that the shell should run instead of prompting the user, if there are any
+fn read_script(args: CommandLineArgs) -> Option<String> {
+    let script = match (args.command, args.script) {
+        (Some(command), _) => Ok(command),
+        (None, Some(path)) => {
+            fs_err::read_to_string(&path).replace_err(|| file_err!(CouldNotReadFile: path))
+        }
+        (None, None) if !exec::signals::is_interactive() => {
+            let mut script = String::new();
+            io::stdin()
+                .read_to_string(&mut script)
+                .replace_err(|| file_err!(CouldNotReadFile: "/dev/stdin"))
+                .map(|_| script)
+        }
+        (None, None) => return None,
+    };
+
+    match script {
+        Ok(script) => Some(script),
+        Err(err) => crash_with_error(err),
+    }
+}
+
+// Handles the return value of running a builtin or executable, printing any errors
+// * The exit status of the command has already been recorded by the dispatcher, and errors from
+// * builtins have already been written to the builtin's own standard error
+fn handle_error(potential_error: Result<()>) {
     if let Err(error) = potential_error {
-        eprintln!("{}", error);
+        error.report();
+    }
+}
+
+// Reports any background jobs which have finished since the last prompt
+fn report_finished_jobs(shell: &mut ShellState) {
+    shell.jobs.poll();
+    for job in shell.jobs.take_finished() {
+        eprintln!("{}", job);
     }
 }
 
@@ -1,13 +1,32 @@
 use std::{
+    collections::HashMap,
     io::{BufRead, BufReader},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use fs_err::File;
 
-use crate::errors::{Handle, Result};
+use crate::errors::{Handle, Result, RushError};
+
+/// The location of the configuration file, relative to the directory the shell was started in
+pub const CONFIG_FILE: &str = "./config/config.rush";
+
+// Scripts which are run automatically, relative to the user's home directory
+/// Run when a login shell starts, before any other commands
+pub const PROFILE_SCRIPT: &str = ".config/rush/profile.rush";
+/// Run when an interactive shell starts, before the first prompt
+pub const INIT_SCRIPT: &str = ".config/rush/init.rush";
+/// Run when an interactive or login shell exits
+pub const LOGOUT_SCRIPT: &str = ".config/rush/logout.rush";
+
+/// The start of the configuration key for an alias, which is followed by the alias's name
+pub const ALIAS_KEY_PREFIX: &str = "alias ";
+/// The start of the configuration key for an abbreviation, which is followed by its name
+pub const ABBREVIATION_KEY_PREFIX: &str = "abbreviation ";
 
 /// Represents any settings for the shell, most of which can be configured by the user
+#[derive(Clone)]
 pub struct Configuration {
     /// The truncation length for the prompt
     pub truncation: Option<usize>,
@@ -17,8 +36,28 @@ pub struct Configuration {
     pub multiline_prompt: bool,
     /// Whether or not to print out full error messages and status codes when a command fails
     pub show_errors: bool,
+    /// Whether to prevent output redirections from overwriting existing files
+    pub noclobber: bool,
+    /// What to do with a glob pattern (e.g. '*.rs') which does not match any files
+    pub glob_no_match: NoMatchBehavior,
+    /// Whether glob patterns can match files whose names begin with a '.'
+    pub glob_dotfiles: bool,
+    /// Whether extended glob patterns like '@(a|b)' and '!(*.rs)' are recognized
+    pub extglob: bool,
+    /// The number of decimal places that the 'calculate' builtin rounds floats to
+    pub calc_precision: Option<usize>,
     /// Paths to recursively search for plugins
     pub plugin_paths: Vec<PathBuf>,
+    /// A PATH saved by the 'edit-path' builtin, which replaces the inherited PATH on startup
+    pub path: Option<String>,
+    /// Aliases defined by the user, mapping each name to the command line it stands for
+    pub aliases: HashMap<String, String>,
+    /// Abbreviations defined by the user, mapping each name to the text it expands to at the start
+    /// of a command when a line is entered
+    pub abbreviations: HashMap<String, String>,
+    /// The absolute path of the configuration file, which persisted settings are written to
+    // * This is absolute so that it still refers to the same file after changing directories
+    file: PathBuf,
 }
 
 impl Default for Configuration {
@@ -28,14 +67,25 @@ impl Default for Configuration {
             history_limit: None,
             multiline_prompt: false,
             show_errors: true,
+            noclobber: false,
+            glob_no_match: NoMatchBehavior::Pass,
+            glob_dotfiles: false,
+            extglob: false,
+            calc_precision: None,
             plugin_paths: vec![],
+            path: None,
+            aliases: HashMap::new(),
+            abbreviations: HashMap::new(),
+            file: std::path::absolute(CONFIG_FILE).unwrap_or_else(|_| PathBuf::from(CONFIG_FILE)),
         }
     }
 }
 
 impl Configuration {
     /// Scans a configuration file for settings and updates the configuration accordingly
-    pub fn from_file(filename: &str) -> Result<Self> {
+    /// Lines which cannot be read are skipped, and the errors they caused are given along with
+    /// the configuration, so that one bad line does not discard the rest of the settings
+    pub fn from_file(filename: &str) -> Result<(Self, Vec<RushError>)> {
         let filename = PathBuf::from(filename);
         let open_error_msg = format!("Config file '{}' could not be opened", filename.display());
         let read_error_msg = format!("Config file '{}' could not be read", filename.display());
@@ -44,60 +94,214 @@ impl Configuration {
             .parent()
             .replace_err(|| file_err!(CouldNotGetParent: filename))?;
 
-        let mut config = Self::default();
+        let mut config = Self {
+            file: std::path::absolute(&filename).unwrap_or_else(|_| filename.clone()),
+            ..Self::default()
+        };
         let file = File::open(&filename)
             .replace_err_with_msg(|| file_err!(CouldNotOpenFile: filename), &open_error_msg)?;
         let reader = BufReader::new(file);
 
-        for line in reader.lines() {
+        let mut errors = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
             let line = line
                 .replace_err_with_msg(|| file_err!(CouldNotReadFile: filename), &read_error_msg)?;
-            let tokens = line.split(": ").collect::<Vec<&str>>();
-            if tokens.len() != 2 {
-                return Err(file_err!(CouldNotReadFile: filename).set_context(&read_error_msg));
+            if let Err(error) = config.read_line(&line, dirname) {
+                let line_error_msg = format!(
+                    "Config file '{}' has an invalid setting on line {}, which was skipped: '{}'",
+                    filename.display(),
+                    index + 1,
+                    line
+                );
+                errors.push(error.set_context(&line_error_msg));
             }
+        }
 
-            let (key, value) = (tokens[0], tokens[1]);
-
-            // ? Should these be underscores instead of hyphens?
-            match key {
-                "truncation" => {
-                    if let Ok(length) = value.parse::<usize>() {
-                        config.truncation = Some(length);
-                    } else if value == "false" {
-                        config.truncation = None;
-                    } else {
-                        return Err(
-                            file_err!(CouldNotReadFile: filename).set_context(&read_error_msg)
-                        );
-                    }
-                }
-                "history-limit" => {
-                    if let Ok(limit) = value.parse::<usize>() {
-                        config.history_limit = Some(limit);
-                    } else if value == "false" {
-                        config.history_limit = None;
-                    }
-                }
-                "multiline-prompt" => {
-                    config.multiline_prompt = value.parse::<bool>().replace_err_with_msg(
-                        || file_err!(CouldNotReadFile: filename),
-                        &read_error_msg,
-                    )?;
+        Ok((config, errors))
+    }
+
+    /// Updates the configuration with the setting on one line of a configuration file
+    fn read_line(&mut self, line: &str, dirname: &Path) -> Result<()> {
+        let invalid_line = || file_err!(CouldNotReadFile: self.file);
+        // * Only the first separator is used, so that values like alias commands can contain one
+        let (key, value) = line.split_once(": ").replace_err(invalid_line)?;
+        let value = unescape_value(value);
+
+        // * Aliases and abbreviations are saved with their name as part of the key
+        // * (e.g. 'alias ll: ls -l' or 'abbreviation gco: git checkout')
+        if let Some(name) = key.strip_prefix(ALIAS_KEY_PREFIX) {
+            self.aliases.insert(name.to_owned(), value);
+            return Ok(());
+        }
+
+        if let Some(name) = key.strip_prefix(ABBREVIATION_KEY_PREFIX) {
+            self.abbreviations.insert(name.to_owned(), value);
+            return Ok(());
+        }
+
+        // ? Should these be underscores instead of hyphens?
+        match key {
+            "truncation" => {
+                if let Ok(length) = value.parse::<usize>() {
+                    self.truncation = Some(length);
+                } else if value == "false" {
+                    self.truncation = None;
+                } else {
+                    return Err(invalid_line());
                 }
-                "show-errors" => {
-                    config.show_errors = value.parse::<bool>().replace_err_with_msg(
-                        || file_err!(CouldNotReadFile: filename),
-                        &read_error_msg,
-                    )?;
+            }
+            "history-limit" => {
+                if let Ok(limit) = value.parse::<usize>() {
+                    self.history_limit = Some(limit);
+                } else if value == "false" {
+                    self.history_limit = None;
                 }
-                "plugin-path" => {
-                    config.plugin_paths.push(dirname.join(value));
+            }
+            "multiline-prompt" => {
+                self.multiline_prompt = value.parse::<bool>().replace_err(invalid_line)?;
+            }
+            "show-errors" => {
+                self.show_errors = value.parse::<bool>().replace_err(invalid_line)?;
+            }
+            "noclobber" => {
+                self.noclobber = value.parse::<bool>().replace_err(invalid_line)?;
+            }
+            "glob-no-match" => {
+                self.glob_no_match = value.parse::<NoMatchBehavior>().replace_err(invalid_line)?;
+            }
+            "glob-dotfiles" => {
+                self.glob_dotfiles = value.parse::<bool>().replace_err(invalid_line)?;
+            }
+            "extglob" => {
+                self.extglob = value.parse::<bool>().replace_err(invalid_line)?;
+            }
+            "calc-precision" => {
+                if let Ok(precision) = value.parse::<usize>() {
+                    self.calc_precision = Some(precision);
+                } else if value == "false" {
+                    self.calc_precision = None;
+                } else {
+                    return Err(invalid_line());
                 }
-                _ => return Err(file_err!(CouldNotReadFile: filename).set_context(&read_error_msg)),
             }
+            "plugin-path" => {
+                self.plugin_paths.push(dirname.join(value));
+            }
+            "path" => {
+                self.path = Some(value);
+            }
+            _ => return Err(invalid_line()),
+        }
+
+        Ok(())
+    }
+
+    /// Saves a setting to the configuration file, replacing any existing value for the same key
+    pub fn persist(&self, key: &str, value: &str) -> Result<()> {
+        self.update_file(key, Some(value))
+    }
+
+    /// Removes a setting from the configuration file, if it has been saved there
+    pub fn forget(&self, key: &str) -> Result<()> {
+        self.update_file(key, None)
+    }
+
+    /// Replaces the line for a key in the configuration file, or removes it if `value` is `None`
+    fn update_file(&self, key: &str, value: Option<&str>) -> Result<()> {
+        // * A missing configuration file is treated as an empty one, and created when written to
+        let contents = fs_err::read_to_string(&self.file).unwrap_or_default();
+        let mut lines: Vec<String> = contents.lines().map(|line| line.to_owned()).collect();
+        let position = lines
+            .iter()
+            .position(|line| line.split(": ").next() == Some(key));
+
+        // * Values are escaped so that they always fit on a single line
+        match (position, value.map(escape_value)) {
+            (Some(index), Some(value)) => lines[index] = format!("{}: {}", key, value),
+            (None, Some(value)) => lines.push(format!("{}: {}", key, value)),
+            (Some(index), None) => {
+                lines.remove(index);
+            }
+            (None, None) => return Ok(()),
         }
 
-        Ok(config)
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        fs_err::write(&self.file, contents).replace_err(|| file_err!(CouldNotWriteFile: self.file))
+    }
+}
+
+/// Escapes a value so that it can be saved on a single line of the configuration file,
+/// by writing backslashes as '\\' and line breaks as '\n' or '\r'
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Reverses `escape_value()`, turning a value read from the configuration file back into the
+/// text it stands for
+// * A backslash before any other character is kept, so that values written before they were
+// * escaped are read the same way as before
+fn unescape_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut characters = value.chars().peekable();
+    while let Some(c) = characters.next() {
+        let escaped = match (c, characters.peek()) {
+            ('\\', Some('\\')) => '\\',
+            ('\\', Some('n')) => '\n',
+            ('\\', Some('r')) => '\r',
+            (c, _) => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+
+        characters.next();
+        unescaped.push(escaped);
+    }
+
+    unescaped
+}
+
+/// Checks whether a string can be used as an alias name
+/// Alias names cannot contain whitespace, quotes, '=' or characters which separate commands or
+/// begin expansions, since they would not be read as a single unquoted word
+pub fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('#')
+        && name
+            .chars()
+            .all(|c| !c.is_whitespace() && !"='\"\\$`|&;<>()".contains(c))
+}
+
+/// What to do with a glob pattern which does not match any files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoMatchBehavior {
+    /// Fail the command with an error, without running it
+    Error,
+    /// Pass the pattern to the command unchanged
+    Pass,
+    /// Remove the pattern from the command's arguments
+    Empty,
+}
+
+impl FromStr for NoMatchBehavior {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "pass" => Ok(Self::Pass),
+            "empty" => Ok(Self::Empty),
+            _ => Err("expected 'error', 'pass' or 'empty'".to_owned()),
+        }
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::path::{Path as StdPath, PathBuf};
@@ -6,7 +6,7 @@ use std::path::{Path as StdPath, PathBuf};
 use bitflags::bitflags;
 use clap::ValueEnum;
 
-use super::path::Path;
+use super::path::{expand_home, Path};
 use crate::errors::{Handle, Result};
 
 /// Identifier enum for safely accessing environment variables
@@ -60,6 +60,7 @@ bitflags! {
 // * Environment variables are represented in all caps by convention,
 // * any fields that are not actual environment variables are represented in the usual snake_case
 #[allow(non_snake_case)]
+#[derive(Clone)]
 pub struct Environment {
     pub USER: String,
     pub HOME: PathBuf,
@@ -67,11 +68,20 @@ pub struct Environment {
     // * PATH is not to be confused with the WORKING_DIRECTORY. PATH is a list of directories which
     // * the shell will search for executables in. WORKING_DIRECTORY is the current directory the user is in.
     PATH: VecDeque<Path>,
+    /// The text that each directory in the PATH was given as, which is what executables are
+    /// passed and what is saved, since the resolved directory can differ from it (e.g. a symlink)
+    PATH_text: VecDeque<String>,
     // ? Should these be `ShellState` fields instead?
     backward_directories: VecDeque<Path>,
     forward_directories: VecDeque<Path>,
-    #[allow(dead_code)]
+    /// Variables defined by the user with the 'set-variable' builtin
     custom_variables: HashMap<String, String>,
+    /// Variables which are passed to every executable that the shell runs,
+    /// starting out as a copy of the shell's own environment variables
+    exported_variables: HashMap<String, String>,
+    /// Variables which were exported before they were given a value, and are exported as soon
+    /// as they are set
+    unset_exports: HashSet<String>,
 }
 
 #[allow(non_snake_case)]
@@ -80,28 +90,41 @@ impl Environment {
         let USER = get_parent_env_var(EnvVariable::USER)?;
         let HOME = PathBuf::from(get_parent_env_var(EnvVariable::HOME)?);
         let CWD = Path::try_from_str(get_parent_env_var(EnvVariable::CWD)?.as_str(), Some(&HOME))?;
-        let PATH = convert_path_var(get_parent_env_var(EnvVariable::PATH)?.as_str())?;
+        let (PATH, PATH_text) = convert_path_var(get_parent_env_var(EnvVariable::PATH)?.as_str())?;
 
         Ok(Self {
             USER,
             HOME,
             CWD,
             PATH,
+            PATH_text,
             backward_directories: VecDeque::new(),
             forward_directories: VecDeque::new(),
             custom_variables: HashMap::new(),
+            // * Variables which are not valid unicode cannot be represented, so they are skipped
+            exported_variables: env::vars_os()
+                .filter_map(|(name, value)| {
+                    Some((name.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+            unset_exports: HashSet::new(),
         })
     }
 
     /// Updates the shell process's environment variables to match the internal representation
-    fn update_process_env_vars(&self, vars: EnvVariables) -> Result<()> {
+    /// The exported copies of the variables are updated as well, so that executables see the changes
+    fn update_process_env_vars(&mut self, vars: EnvVariables) -> Result<()> {
         // TODO: How to detect errors here?
         if vars.contains(EnvVariables::USER) {
             env::set_var("USER", &self.USER);
+            self.exported_variables
+                .insert("USER".to_owned(), self.USER.clone());
         }
 
         if vars.contains(EnvVariables::HOME) {
             env::set_var("HOME", &self.HOME);
+            self.exported_variables
+                .insert("HOME".to_owned(), self.HOME.display().to_string());
         }
 
         if vars.contains(EnvVariables::CWD) {
@@ -109,9 +132,21 @@ impl Environment {
                 .replace_err(|| state_err!(CouldNotUpdateEnv: EnvVariable::CWD))?;
         }
 
+        if vars.contains(EnvVariables::PATH) {
+            let path = self.PATH_string();
+            env::set_var("PATH", &path);
+            self.exported_variables.insert("PATH".to_owned(), path);
+        }
+
         Ok(())
     }
 
+    /// Updates the shell process's working directory and environment variables to match the
+    /// internal representation again, after they may have been changed by another `Environment`
+    pub fn restore_process_env_vars(&mut self) -> Result<()> {
+        self.update_process_env_vars(EnvVariables::all())
+    }
+
     /// Sets the current working directory and stores the previous working directory
     pub fn set_CWD(&mut self, new_directory: &StdPath, history_limit: Option<usize>) -> Result<()> {
         let starting_directory = self.CWD.clone();
@@ -160,24 +195,28 @@ impl Environment {
     }
 
     /// Appends a path to the PATH variable
-    pub fn PATH_append(&mut self, path: Path) -> Result<()> {
-        self.PATH.push_back(path);
-        self.update_process_env_vars(EnvVariables::PATH)
+    pub fn PATH_append(&mut self, path: &StdPath) -> Result<()> {
+        self.PATH_insert(self.PATH.len(), path)
     }
 
     /// Prepends a path to the PATH variable
-    pub fn PATH_prepend(&mut self, path: Path) -> Result<()> {
-        self.PATH.push_front(path);
-        self.update_process_env_vars(EnvVariables::PATH)
+    pub fn PATH_prepend(&mut self, path: &StdPath) -> Result<()> {
+        self.PATH_insert(0, path)
     }
 
     /// Inserts a path at the specified index in the PATH variable
-    pub fn PATH_insert(&mut self, index: usize, path: Path) -> Result<()> {
+    pub fn PATH_insert(&mut self, index: usize, path: &StdPath) -> Result<()> {
         if index > self.PATH.len() {
             return Err(state_err!(InvalidPathIndex: index));
         }
 
-        self.PATH.insert(index, path);
+        // * The path is kept as it was given, other than the home directory shorthand,
+        // * which executables would not expand
+        let resolved_path = Path::try_from_path(path, Some(&self.HOME))?;
+        let text = expand_home(&path.to_string_lossy(), &self.HOME)?;
+        self.PATH.insert(index, resolved_path);
+        self.PATH_text
+            .insert(index, text.to_string_lossy().into_owned());
         self.update_process_env_vars(EnvVariables::PATH)
     }
 
@@ -186,6 +225,7 @@ impl Environment {
         self.PATH
             .remove(index)
             .replace_err(|| state_err!(InvalidPathIndex: index))?;
+        self.PATH_text.remove(index);
         self.update_process_env_vars(EnvVariables::PATH)
     }
 
@@ -194,10 +234,152 @@ impl Environment {
         &self.CWD
     }
 
+    /// Gets the directory `count` steps back in the directory history, where 1 is the previous directory
+    pub fn backward_directory(&self, count: usize) -> Option<&Path> {
+        let index = self.backward_directories.len().checked_sub(count)?;
+        self.backward_directories.get(index)
+    }
+
+    /// Gets the directory `count` steps forward in the directory history, where 1 is the next directory
+    pub fn forward_directory(&self, count: usize) -> Option<&Path> {
+        self.forward_directories.get(count.checked_sub(1)?)
+    }
+
     /// Getter for the PATH
     pub fn PATH(&self) -> &VecDeque<Path> {
         &self.PATH
     }
+
+    /// Gets the PATH in the format used by environment variables, with each path separated by ':'
+    /// Each path is given as the text it was added as, rather than the directory it resolved to
+    pub fn PATH_string(&self) -> String {
+        self.PATH_text
+            .iter()
+            .map(|path| path.as_str())
+            .collect::<Vec<&str>>()
+            .join(":")
+    }
+
+    /// Gets the value of a variable, falling back to the shell's environment variables
+    /// if no custom variable with the given name exists
+    pub fn get_variable(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.custom_variables.get(name) {
+            return Some(value.clone());
+        }
+
+        // * The strongly-typed environment variables are stored separately from the process environment,
+        // * so they are read from their internal representation instead
+        match name {
+            "USER" => Some(self.USER.clone()),
+            "HOME" => Some(self.HOME.display().to_string()),
+            "PWD" => Some(self.CWD.to_string()),
+            "PATH" => Some(self.PATH_string()),
+            _ => self.exported_variables.get(name).cloned(),
+        }
+    }
+
+    /// Sets the value of a custom variable, creating it if it does not exist
+    /// If the variable has been exported, its exported value is updated instead
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<()> {
+        if !is_valid_variable_name(name) {
+            return Err(state_err!(InvalidVariableName: name));
+        }
+
+        if self.exported_variables.contains_key(name) || self.unset_exports.contains(name) {
+            return self.export_variable(name, Some(value));
+        }
+
+        self.custom_variables
+            .insert(name.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    /// Sets the value of a variable which is passed to executables
+    /// If no value is provided, the custom variable with the same name is exported instead, or if
+    /// there is none, the variable is exported once it is set
+    pub fn export_variable(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        if !is_valid_variable_name(name) {
+            return Err(state_err!(InvalidVariableName: name));
+        }
+
+        if name == "PWD" {
+            return Err(state_err!(ProtectedVariable: name));
+        }
+
+        // * A variable is either custom or exported, never both, so that it only has one value
+        let value = match (value, self.custom_variables.remove(name)) {
+            (Some(value), _) => value.to_owned(),
+            (None, Some(custom_value)) => custom_value,
+            (None, None) => {
+                self.unset_exports.insert(name.to_owned());
+                return Ok(());
+            }
+        };
+
+        self.unset_exports.remove(name);
+
+        // The strongly-typed environment variables are updated instead,
+        // and their exported values are then updated to match
+        let typed_variable = match name {
+            "USER" => {
+                self.USER = value;
+                EnvVariables::USER
+            }
+            "HOME" => {
+                self.HOME = PathBuf::from(value);
+                EnvVariables::HOME
+            }
+            "PATH" => {
+                (self.PATH, self.PATH_text) = convert_path_var(&value)?;
+                EnvVariables::PATH
+            }
+            _ => {
+                self.exported_variables.insert(name.to_owned(), value);
+                return Ok(());
+            }
+        };
+
+        self.update_process_env_vars(typed_variable)
+    }
+
+    /// Removes a variable, whether it is a custom variable or an exported one
+    pub fn unset_variable(&mut self, name: &str) -> Result<()> {
+        if PROTECTED_VARIABLES.contains(&name) {
+            return Err(state_err!(ProtectedVariable: name));
+        }
+
+        self.custom_variables.remove(name);
+        self.exported_variables.remove(name);
+        self.unset_exports.remove(name);
+        Ok(())
+    }
+
+    /// Gets the variables to pass to an executable
+    pub fn exported_variables(&self) -> HashMap<String, String> {
+        let mut variables = self.exported_variables.clone();
+        // * The working directory changes without going through `export_variable()`,
+        // * so its exported value is taken from the current working directory instead
+        variables.insert("PWD".to_owned(), self.CWD.to_string());
+        variables
+    }
+
+    /// Getter for the custom variables
+    pub fn custom_variables(&self) -> &HashMap<String, String> {
+        &self.custom_variables
+    }
+}
+
+/// Variables which are managed by the shell itself, and cannot be removed
+const PROTECTED_VARIABLES: [&str; 4] = ["USER", "HOME", "PWD", "PATH"];
+
+/// Checks whether a string can be used as a variable name
+/// Variable names consist of letters, digits and underscores, and cannot start with a digit
+pub fn is_valid_variable_name(name: &str) -> bool {
+    let mut characters = name.chars();
+    characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 /// Gets the environment variables from the parent process during shell initialization
@@ -205,16 +387,19 @@ fn get_parent_env_var(variable: EnvVariable) -> Result<String> {
     std::env::var(variable.to_legacy_string()).replace_err(|| state_err!(MissingEnv: variable))
 }
 
-/// Converts the PATH environment variable from a string to a collection of `Path`s
-fn convert_path_var(path: &str) -> Result<VecDeque<Path>> {
+/// Converts the PATH environment variable from a string to a collection of `Path`s,
+/// along with the text of each of them
+fn convert_path_var(path: &str) -> Result<(VecDeque<Path>, VecDeque<String>)> {
     let mut paths = VecDeque::new();
+    let mut path_texts = VecDeque::new();
     let path_strings = path.split(':').collect::<Vec<&str>>();
 
     for path_string in path_strings {
         if let Ok(path) = Path::try_from_str(path_string, None) {
             paths.push_back(path);
+            path_texts.push_back(path_string.to_owned());
         }
     }
 
-    Ok(paths)
+    Ok((paths, path_texts))
 }
@@ -0,0 +1,293 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use nix::errno::Errno;
+use nix::sys::signal::{self, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+
+use super::status::ExitStatus;
+use crate::errors::{Handle, Result};
+
+/// A pipeline of executables which was started by the shell and may still be running
+pub struct Job {
+    /// The number used to refer to the job in builtins like 'fg' (e.g. '%1')
+    pub id: usize,
+    /// The commands of the pipeline, as they were run
+    command: String,
+    /// The process group shared by every process in the job, if it has its own
+    // * Foreground pipelines only get a process group when the shell is reading from a terminal
+    process_group: Option<Pid>,
+    processes: Vec<JobProcess>,
+}
+
+/// A single process within a `Job`
+struct JobProcess {
+    pid: Pid,
+    state: ProcessState,
+    started: Instant,
+}
+
+/// The progress of a single process within a `Job`
+enum ProcessState {
+    Running,
+    Stopped,
+    Finished(ExitStatus),
+}
+
+/// The overall progress of a `Job`
+pub enum JobState {
+    /// At least one process is still running, and none are stopped
+    Running,
+    /// At least one process has been stopped (e.g. with Ctrl-Z)
+    Stopped,
+    /// Every process has finished, with the status of the last one being the status of the job
+    Done(ExitStatus),
+}
+
+impl Job {
+    pub fn new(command: String, process_group: Option<Pid>) -> Self {
+        Self {
+            id: 0,
+            command,
+            process_group,
+            processes: Vec::new(),
+        }
+    }
+
+    /// Adds a process which has been started as part of the job
+    pub fn add_process(&mut self, pid: Pid, started: Instant) {
+        self.processes.push(JobProcess {
+            pid,
+            state: ProcessState::Running,
+            started,
+        });
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn process_group(&self) -> Option<Pid> {
+        self.process_group
+    }
+
+    /// Gets the IDs of the job's processes, in the order they appear in the pipeline
+    pub fn pids(&self) -> Vec<Pid> {
+        self.processes.iter().map(|process| process.pid).collect()
+    }
+
+    pub fn state(&self) -> JobState {
+        if self
+            .processes
+            .iter()
+            .any(|process| matches!(process.state, ProcessState::Stopped))
+        {
+            return JobState::Stopped;
+        }
+
+        match self.processes.last() {
+            Some(JobProcess {
+                state: ProcessState::Finished(status),
+                ..
+            }) if self.is_finished() => JobState::Done(*status),
+            _ => JobState::Running,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|process| matches!(process.state, ProcessState::Finished(_)))
+    }
+
+    /// Gets the exit status of each process, in the order they appear in the pipeline
+    /// Processes which have not finished yet are given the default status
+    pub fn statuses(&self) -> Vec<ExitStatus> {
+        self.processes
+            .iter()
+            .map(|process| match process.state {
+                ProcessState::Finished(status) => status,
+                _ => ExitStatus::default(),
+            })
+            .collect()
+    }
+
+    /// Waits until every process in the job has finished, or until the job is stopped
+    pub fn wait(&mut self) -> Result<()> {
+        self.wait_until(None)
+    }
+
+    /// Waits like `wait()`, but stops early with `BuiltinError::Interrupted` if waiting is
+    /// interrupted by a signal after `interrupted` has been set (e.g. by Ctrl-C)
+    pub fn wait_interruptible(&mut self, interrupted: &AtomicBool) -> Result<()> {
+        self.wait_until(Some(interrupted))
+    }
+
+    fn wait_until(&mut self, interrupted: Option<&AtomicBool>) -> Result<()> {
+        for process in &mut self.processes {
+            while matches!(process.state, ProcessState::Running) {
+                match waitpid(process.pid, Some(WaitPidFlag::WUNTRACED)) {
+                    Ok(status) => process.update(status),
+                    // * Waiting is interrupted whenever the shell receives a signal, such as SIGINT
+                    Err(Errno::EINTR) => {
+                        if interrupted.is_some_and(|flag| flag.swap(false, Ordering::Relaxed)) {
+                            return Err(builtin_err!(Interrupted));
+                        }
+                    }
+                    Err(Errno::ECHILD) => {
+                        process.state = ProcessState::Finished(ExitStatus::default())
+                    }
+                    Err(_) => return Err(executable_err!(CouldNotWait)),
+                }
+            }
+
+            // * Stopping a job stops every process in its group, so the rest need not be waited on
+            if matches!(process.state, ProcessState::Stopped) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether any of the job's processes have finished, stopped or continued, without blocking
+    pub fn poll(&mut self) {
+        let flags = WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED;
+        for process in &mut self.processes {
+            if matches!(process.state, ProcessState::Finished(_)) {
+                continue;
+            }
+
+            match waitpid(process.pid, Some(flags)) {
+                Ok(status) => process.update(status),
+                Err(Errno::ECHILD) => process.state = ProcessState::Finished(ExitStatus::default()),
+                Err(_) => (),
+            }
+        }
+    }
+
+    /// Continues the job if it has been stopped
+    pub fn resume(&mut self) -> Result<()> {
+        let result = match self.process_group {
+            Some(process_group) => signal::killpg(process_group, Signal::SIGCONT),
+            None => self
+                .processes
+                .iter()
+                .try_for_each(|process| signal::kill(process.pid, Signal::SIGCONT)),
+        };
+        result.replace_err(|| state_err!(CouldNotResumeJob: self.id))?;
+
+        for process in &mut self.processes {
+            if matches!(process.state, ProcessState::Stopped) {
+                process.state = ProcessState::Running;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl JobProcess {
+    /// Updates the state of the process based on a status reported by `waitpid()`
+    fn update(&mut self, status: WaitStatus) {
+        self.state = match status {
+            WaitStatus::Stopped(..) => ProcessState::Stopped,
+            WaitStatus::Continued(_) => ProcessState::Running,
+            status => match ExitStatus::from_wait_status(status, self.started.elapsed()) {
+                Some(status) => ProcessState::Finished(status),
+                // * No change was reported, which happens when the process is still running
+                None => return,
+            },
+        };
+    }
+}
+
+impl Display for Job {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self.state() {
+            JobState::Running => "Running".to_owned(),
+            JobState::Stopped => "Stopped".to_owned(),
+            JobState::Done(status) if status.success() => "Done".to_owned(),
+            JobState::Done(status) => format!("Exit {}", status),
+        };
+
+        write!(f, "[{}]  {:<16}{}", self.id, state, self.command)
+    }
+}
+
+/// The jobs which the shell has started, and which have not been reported as finished yet
+#[derive(Default)]
+pub struct JobTable {
+    /// The jobs, ordered by their IDs, where the last job is the current job
+    jobs: Vec<Job>,
+    /// Jobs which have been removed from the table with 'disown', but have not finished yet
+    // * These are still waited on, so that their processes do not linger after they finish
+    disowned: Vec<Job>,
+}
+
+impl JobTable {
+    /// Adds a job to the table, giving it the next available ID
+    pub fn add(&mut self, mut job: Job) -> usize {
+        let id = self.jobs.last().map_or(1, |last| last.id + 1);
+        job.id = id;
+        self.jobs.push(job);
+        id
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    /// Removes a job from the table without stopping it, so that it is no longer reported
+    pub fn disown(&mut self, id: usize) {
+        if let Some(job) = self.remove(id) {
+            self.disowned.push(job);
+        }
+    }
+
+    /// Finds the ID of the job referred to by a job specifier, such as '%1', '1', '%+' or '%-'
+    /// If no specifier is given, the current job (the one started most recently) is used
+    pub fn find(&self, specifier: Option<&str>) -> Result<usize> {
+        let id = match specifier {
+            None | Some("%" | "%%" | "%+") => self.jobs.last().map(|job| job.id),
+            Some("%-") => self.jobs.iter().rev().nth(1).map(|job| job.id),
+            Some(specifier) => specifier
+                .strip_prefix('%')
+                .unwrap_or(specifier)
+                .parse::<usize>()
+                .ok()
+                .filter(|id| self.jobs.iter().any(|job| job.id == *id)),
+        };
+
+        id.replace_err(|| state_err!(UnknownJob: specifier.unwrap_or("%+")))
+    }
+
+    /// Checks on every job without blocking, so that their states are up to date
+    pub fn poll(&mut self) {
+        for job in self.jobs.iter_mut().chain(self.disowned.iter_mut()) {
+            job.poll();
+        }
+
+        self.disowned.retain(|job| !job.is_finished());
+    }
+
+    /// Removes every job which has finished from the table, so that they can be reported
+    pub fn take_finished(&mut self) -> Vec<Job> {
+        let (finished, unfinished) = std::mem::take(&mut self.jobs)
+            .into_iter()
+            .partition(Job::is_finished);
+        self.jobs = unfinished;
+        finished
+    }
+}
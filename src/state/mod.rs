@@ -1,8 +1,16 @@
 mod config;
 mod environment;
+mod jobs;
 mod path;
 mod shell;
+mod status;
 
-pub use environment::{EnvVariable, EnvVariables, Environment};
+pub use config::{
+    is_valid_alias_name, NoMatchBehavior, ABBREVIATION_KEY_PREFIX, ALIAS_KEY_PREFIX, INIT_SCRIPT,
+    LOGOUT_SCRIPT, PROFILE_SCRIPT,
+};
+pub use environment::{is_valid_variable_name, EnvVariable};
+pub use jobs::{Job, JobState};
 pub use path::Path;
-pub use shell::ShellState;
+pub use shell::{Jump, ShellState};
+pub use status::ExitStatus;
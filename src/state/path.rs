@@ -123,17 +123,16 @@ impl Path {
 }
 
 /// Expands the home directory shorthand in a path string
-fn expand_home(path: &str, home_directory: &StdPath) -> Result<PathBuf> {
-    if path.starts_with('~') {
-        Ok(PathBuf::from(
-            path.replace(
-                '~',
-                home_directory
-                    .to_str()
-                    .replace_err(|| file_err!(FailedToConvertPathToString: home_directory))?,
-            ),
-        ))
-    } else {
-        Ok(PathBuf::from(path))
+// * Only a '~' which makes up the whole first component of the path is expanded
+pub fn expand_home(path: &str, home_directory: &StdPath) -> Result<PathBuf> {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => Ok(PathBuf::from(format!(
+            "{}{}",
+            home_directory
+                .to_str()
+                .replace_err(|| file_err!(FailedToConvertPathToString: home_directory))?,
+            rest
+        ))),
+        _ => Ok(PathBuf::from(path)),
     }
 }
@@ -1,31 +1,149 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, RwLock};
 
 use crossterm::style::Stylize;
 
-use super::config::Configuration;
+use super::config::{Configuration, CONFIG_FILE};
 use super::environment::Environment;
+use super::jobs::JobTable;
+use super::status::ExitStatus;
 use super::Path;
-use crate::errors::Result;
+use crate::errors::{Result, RushError};
+use crate::eval::{Command, ScriptEngine};
+
+/// A change to the flow of control requested by a builtin, which stops the commands after it
+/// from running until it reaches whatever it applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    /// Set by 'exit' to the code that the shell should exit with,
+    /// so that the shell can stop running commands and run its logout script before exiting
+    Exit(i32),
+    /// Set by 'return' to the status that the current function or sourced script returns
+    Return(i32),
+    /// Set by 'break' to the number of enclosing loops to stop
+    Break(usize),
+    /// Set by 'continue' to the number of enclosing loops whose current iteration is stopped
+    Continue(usize),
+}
 
 /// Represents the shell state and provides methods for interacting with it
 pub struct ShellState {
     pub environment: Environment,
     pub config: Configuration,
-    pub last_command_succeeded: bool,
-    pub should_exit: bool,
+    /// The status of the most recently run command, which is the last command of the last pipeline
+    pub last_status: ExitStatus,
+    /// The statuses of every command in the most recently run pipeline, in order
+    pub pipeline_status: Vec<ExitStatus>,
+    /// The status of the last command substitution run while expanding the current command,
+    /// which becomes the status of a command made up of only assignments
+    pub substitution_status: Option<ExitStatus>,
+    /// Jobs which were started in the background or stopped while in the foreground
+    pub jobs: JobTable,
+    /// Set when the shell receives SIGINT, so that builtins which wait for a long time can stop
+    pub interrupted: Arc<AtomicBool>,
+    /// Set by builtins like 'exit' and 'break' which stop the commands after them from running
+    pub jump: Option<Jump>,
+    /// Whether the shell is prompting the user for commands, rather than running a script,
+    /// a '-c' command or commands piped into it
+    pub interactive: bool,
+    /// The name of the shell, or of the script it is running ('$0')
+    pub name: String,
+    /// The positional parameters ('$1', '$2', ...), which are the arguments of the function
+    /// or script currently running
+    pub arguments: Vec<String>,
+    /// Functions defined by the user, by name
+    pub functions: HashMap<String, Arc<Command>>,
+    /// For each function currently running, innermost last, the values that its local variables
+    /// had before they were made local, so that they can be restored when it returns
+    pub local_scopes: Vec<HashMap<String, Option<String>>>,
+    /// The number of functions and sourced scripts currently running inside one another
+    pub call_depth: usize,
+    /// The number of loops currently running inside one another, within the current function
+    pub loop_depth: usize,
+    /// The engine which runs Rhai scripts, which is created the first time a script runs
+    pub scripting: Option<ScriptEngine>,
 }
 
 impl ShellState {
     pub fn new() -> Result<Arc<RwLock<Self>>> {
-        let config =
-            Configuration::from_file("./config/config.rush").unwrap_or(Configuration::default());
+        // * A missing configuration file is the same as an empty one, but lines which cannot be
+        // * read are reported, while the rest of the user's saved settings are still used
+        let config = match Configuration::from_file(CONFIG_FILE) {
+            Ok((config, errors)) => {
+                errors.iter().for_each(RushError::report);
+                config
+            }
+            Err(_) => Configuration::default(),
+        };
+
+        let mut environment = Environment::new()?;
+        // A PATH saved using 'edit-path --persist' takes the place of the inherited one
+        if let Some(path) = &config.path {
+            environment.export_variable("PATH", Some(path))?;
+        }
+
+        Ok(Arc::new(RwLock::new(Self::with(environment, config))))
+    }
 
-        Ok(Arc::new(RwLock::new(Self {
-            environment: Environment::new()?,
+    /// Creates the state for a shell which has not run any commands yet
+    fn with(environment: Environment, config: Configuration) -> Self {
+        Self {
+            environment,
             config,
-            last_command_succeeded: true,
-            should_exit: false,
-        })))
+            last_status: ExitStatus::default(),
+            pipeline_status: Vec::new(),
+            substitution_status: None,
+            jobs: JobTable::default(),
+            interrupted: Arc::new(AtomicBool::new(false)),
+            jump: None,
+            interactive: false,
+            name: "rush".to_owned(),
+            arguments: Vec::new(),
+            functions: HashMap::new(),
+            local_scopes: Vec::new(),
+            call_depth: 0,
+            loop_depth: 0,
+            scripting: None,
+        }
+    }
+
+    /// Creates a copy of the shell's state to run commands in, such as those of a command
+    /// substitution, so that changes they make to variables, the working directory, functions
+    /// and other state do not affect the shell itself
+    /// The job table and scripting engine are shared with the copy, and are given back to the
+    /// shell along with the status of its last command by `exit_subshell()`
+    pub fn enter_subshell(&mut self) -> Self {
+        Self {
+            environment: self.environment.clone(),
+            config: self.config.clone(),
+            last_status: self.last_status,
+            pipeline_status: self.pipeline_status.clone(),
+            substitution_status: None,
+            jobs: std::mem::take(&mut self.jobs),
+            interrupted: self.interrupted.clone(),
+            jump: None,
+            interactive: false,
+            name: self.name.clone(),
+            arguments: self.arguments.clone(),
+            functions: self.functions.clone(),
+            local_scopes: self.local_scopes.clone(),
+            call_depth: self.call_depth,
+            loop_depth: self.loop_depth,
+            scripting: self.scripting.take(),
+        }
+    }
+
+    /// Discards a copy of the shell's state created by `enter_subshell()`
+    pub fn exit_subshell(&mut self, subshell: Self) -> Result<()> {
+        self.jobs = subshell.jobs;
+        self.scripting = subshell.scripting;
+        self.last_status = subshell.last_status;
+        self.pipeline_status = subshell.pipeline_status;
+        self.substitution_status = Some(subshell.last_status);
+        // * The copy shares the shell's process, so a change of directory or to PATH in the copy
+        // * has to be undone
+        self.environment.restore_process_env_vars()
     }
 
     /// Generates the prompt string used by the `LineEditor`
@@ -40,24 +158,56 @@ impl ShellState {
         };
 
         // ? What is the actual name for this?
-        let prompt_tick = match self.last_command_succeeded {
+        let prompt_tick = match self.last_status.success() {
             true => "❯".green(),
             false => "❯".red(),
         }
         .bold();
+        // The exit code of a failed command is shown before the tick
+        let status = match self.last_status.success() || !self.config.show_errors {
+            true => String::new(),
+            false => format!("{} ", self.last_status.to_string().red()),
+        };
 
         format!(
-            "\n{} on {}{}{} ",
+            "\n{} on {}{}{}{} ",
             user.dark_blue(),
             cwd.dark_green(),
             prompt_delimiter,
+            status,
             prompt_tick
         )
     }
 
+    /// Records the statuses of the commands in a pipeline which has finished running
+    pub fn record_status(&mut self, statuses: Vec<ExitStatus>) {
+        if let Some(last_status) = statuses.last() {
+            self.last_status = *last_status;
+        }
+
+        self.pipeline_status = statuses;
+    }
+
     /// Convenience getter for the current working directory
     #[allow(non_snake_case)]
     pub fn CWD(&self) -> &Path {
         self.environment.CWD()
     }
 }
+
+#[cfg(test)]
+impl ShellState {
+    /// Creates a shell state for tests, which uses the default configuration rather than the
+    /// user's, and starts in the root of the crate
+    pub fn for_tests() -> Self {
+        // * The environment variables that the shell needs may be missing where tests are run
+        if std::env::var_os("USER").is_none() {
+            std::env::set_var("USER", "rush");
+        }
+
+        let mut environment = Environment::new().unwrap();
+        let cwd = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        environment.set_CWD(cwd, None).unwrap();
+        Self::with(environment, Configuration::default())
+    }
+}
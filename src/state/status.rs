@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use nix::sys::wait::WaitStatus;
+
+use crate::errors::{Result, RushError};
+
+/// The outcome of a command which has finished running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExitStatus {
+    /// The exit code of the command, where zero means that it succeeded
+    // * Commands which could not be run or were killed by a signal are given the same
+    // * exit codes that other shells use, such as 127 for a command which could not be found
+    pub code: i32,
+    /// The signal which terminated the command, if it was killed before exiting
+    pub signal: Option<i32>,
+    /// How long the command took to run
+    pub duration: Duration,
+}
+
+impl ExitStatus {
+    /// Creates an `ExitStatus` from the wait status of a process,
+    /// or gives `None` if the process has not finished (e.g. if it was only stopped)
+    pub fn from_wait_status(status: WaitStatus, duration: Duration) -> Option<Self> {
+        match status {
+            WaitStatus::Exited(_, code) => Some(Self {
+                code,
+                signal: None,
+                duration,
+            }),
+            WaitStatus::Signaled(_, signal, _) => Some(Self {
+                code: 128 + signal as i32,
+                signal: Some(signal as i32),
+                duration,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Creates an `ExitStatus` from the result of a builtin, or of a command which could not run
+    pub fn from_result(result: &Result<()>, duration: Duration) -> Self {
+        match result {
+            Ok(()) => Self {
+                duration,
+                ..Self::default()
+            },
+            Err(error) => Self::from_error(error, duration),
+        }
+    }
+
+    /// Creates an `ExitStatus` for a command which failed with the given error
+    pub fn from_error(error: &RushError, duration: Duration) -> Self {
+        Self {
+            code: error.exit_code(),
+            signal: error.signal(),
+            duration,
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == 0
+    }
+
+    /// Converts the status into a `Result`, which is an error if the command did not succeed
+    pub fn into_result(self) -> Result<()> {
+        match (self.success(), self.signal) {
+            (true, _) => Ok(()),
+            (false, Some(signal)) => Err(executable_err!(KilledBySignal: signal)),
+            (false, None) => Err(executable_err!(FailedToExecute: self.code)),
+        }
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.signal {
+            Some(signal) => write!(f, "{} (signal {})", self.code, signal),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}